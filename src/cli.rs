@@ -1,15 +1,39 @@
 use crate::{
-    model::{due_date::Due, item::Item, Model},
+    model::{
+        item::{Id as ItemId, Item},
+        Model,
+    },
     storage::{
-        config_manager::{Auth, ConfigManager},
-        model_manager::ModelManager,
+        config_manager::{Auth, Config, ConfigManager, SyncMode},
+        model_store::{ItemSelection, ModelStore},
+        profile_manager::ProfileManager,
     },
     sync::{client::Client, Request, ResourceType},
 };
-use anyhow::{anyhow, Result};
+use add::AddOptions;
+use anyhow::{anyhow, bail, Context, Result};
 use chrono::{Local, NaiveDateTime};
-use clap::{Parser, Subcommand};
-use std::io::{self, Write};
+use clap::{Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use completions::ValueKind;
+use export::ExportFormat;
+use import::ImportArgs;
+use list::{Group, Query};
+use output::{ItemRecord, OutputFormat, SyncFailed, SyncRecord};
+use pending::PendingCommand;
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+pub mod add;
+pub mod completions;
+pub mod export;
+pub mod import;
+pub mod item_ref;
+pub mod list;
+pub mod output;
+pub mod pending;
 
 #[derive(Parser, Clone)]
 #[command(author)]
@@ -25,6 +49,20 @@ pub struct Args {
     #[arg(long = "local-dir-override", hide = true)]
     pub local_dir_override: Option<String>,
 
+    /// The profile to use, for keeping separate Todoist accounts (defaults to the profile
+    /// set with `profile default`)
+    #[arg(long, global = true, env = "TODOIST_TUI_PROFILE")]
+    pub profile: Option<String>,
+
+    /// How to print results: `text` is for reading, the others are for scripts
+    #[arg(long, short, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// How often (in seconds) the TUI syncs with the Todoist server while it's open
+    /// (overrides the `sync_interval` setting)
+    #[arg(long = "sync-interval")]
+    pub sync_interval: Option<u64>,
+
     /// Override the date/time the app uses as current date/time
     #[arg(long = "date-time-override", hide = true)]
     pub datetime_override: Option<NaiveDateTime>,
@@ -32,41 +70,100 @@ pub struct Args {
 
 #[derive(Subcommand, Clone)]
 pub enum Command {
-    /// Add a new todo to your inbox
+    /// Add a new todo to your inbox (or the project set with the `default_project` setting)
     #[command(name = "add")]
     AddTodo {
-        /// The text of the todo
-        todo: String,
+        /// The text of the todo, which can use Todoist's quick-add syntax for its project
+        /// (`#Work`), section (`/Calls`), labels (`@phone`), priority (`p1`), and due date
+        #[arg(required_unless_present = "stdin")]
+        todo: Option<String>,
 
-        /// When the todo is due
-        #[arg(long, short)]
-        due: Option<String>,
+        #[command(flatten)]
+        options: AddOptions,
+
+        /// Read todos from stdin instead, one per line, with lines that are indented under
+        /// another one as its subtasks
+        #[arg(long, conflicts_with = "todo")]
+        stdin: bool,
 
         /// Don't sync data with the server
         #[arg(long = "no-sync", short)]
         no_sync: bool,
     },
 
-    /// Mark a todo in the inbox complete
+    /// Mark a todo complete
     #[command(name = "complete")]
     CompleteTodo {
-        /// The number of the todo that's displayed with the `list` command
-        number: usize,
+        /// The todo's id (or the start of it, like the short ids from `list --tree`), its
+        /// number in the `list` output, or part of its text
+        #[arg(
+            id = "todo_ref",
+            value_name = "TODO",
+            required_unless_present = "stdin"
+        )]
+        todo: Option<String>,
+
+        /// Read todos from stdin instead, one per line (given like above)
+        #[arg(long, conflicts_with = "todo_ref")]
+        stdin: bool,
 
         /// Don't sync data with the server
         #[arg(long = "no-sync", short)]
         no_sync: bool,
     },
 
-    /// List the items in your inbox
+    /// List the items in your inbox, or in other projects
     #[command(name = "list")]
-    ListInbox,
+    List {
+        #[command(flatten)]
+        query: Query,
+
+        /// Show the todos grouped by project and section, with subtasks under their
+        /// parents (this is how filtered lists are always shown)
+        #[arg(long, short)]
+        tree: bool,
+    },
+
+    /// Print a snapshot of your projects and todos (including completed ones), for
+    /// reports or backups
+    #[command(name = "export")]
+    Export {
+        /// What to write the snapshot as
+        #[arg(long, short, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+
+        /// Only export this project (by name or id) and its subprojects
+        #[arg(long, short)]
+        project: Option<String>,
+    },
+
+    /// Add the todos (and the sections they're in) from a markdown checklist, a CSV file,
+    /// or a todo.txt file to a project
+    #[command(name = "import")]
+    Import {
+        #[command(flatten)]
+        import: ImportArgs,
+
+        /// Don't sync data with the server
+        #[arg(long = "no-sync", short)]
+        no_sync: bool,
+    },
 
     /// Store a Todoist API token
     #[command(name = "set-token")]
     SetApiToken {
         /// The Todoist API token
-        token: String,
+        #[arg(required_unless_present = "command")]
+        token: Option<String>,
+
+        /// Where to store the token (in a file, unless this says otherwise)
+        #[arg(long, value_enum, conflicts_with = "command")]
+        store: Option<TokenStore>,
+
+        /// Instead of storing the token, get it from the output of this command whenever
+        /// it's needed (eg. 'pass show todoist')
+        #[arg(long, conflicts_with = "token")]
+        command: Option<String>,
     },
 
     /// Sync data with the Todoist server
@@ -76,111 +173,731 @@ pub enum Command {
         #[arg(long, short)]
         incremental: bool,
     },
+
+    /// List the changes that haven't been synced yet
+    #[command(name = "pending")]
+    Pending {
+        #[command(subcommand)]
+        command: Option<PendingCommand>,
+    },
+
+    /// Print a script that sets up tab completion for a shell, eg. with
+    /// `todoist-tui completions bash > ~/.local/share/bash-completion/completions/todoist-tui`
+    #[command(name = "completions")]
+    Completions {
+        /// The shell to set up
+        shell: Shell,
+    },
+
+    /// Print the values that tab completion fills in (used by the `completions` scripts)
+    #[command(name = "completion-values", hide = true)]
+    CompletionValues {
+        /// What to print
+        kind: ValueKind,
+    },
+
+    /// Manage profiles, which each have their own API token and data
+    #[command(name = "profile")]
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+
+    /// View and change settings
+    #[command(name = "config")]
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ConfigCommand {
+    /// Print the value of a setting
+    #[command(name = "get")]
+    Get {
+        /// The name of the setting, eg. `date_format` or `keybindings.quit`
+        key: String,
+    },
+
+    /// Change a setting
+    #[command(name = "set")]
+    Set {
+        /// The name of the setting, eg. `date_format` or `keybindings.quit`
+        key: String,
+
+        /// The new value
+        value: String,
+    },
+
+    /// Print all settings
+    #[command(name = "list")]
+    List,
+
+    /// Print the path of the config file
+    #[command(name = "path")]
+    Path,
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum TokenStore {
+    /// In plain text, in the app's data directory
+    File,
+
+    /// In the OS keyring (if the app was built with the `keyring` feature)
+    Keyring,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum ProfileCommand {
+    /// List all profiles
+    #[command(name = "list")]
+    List,
+
+    /// Add a new profile
+    #[command(name = "add")]
+    Add {
+        /// The name of the profile
+        name: String,
+    },
+
+    /// Remove a profile along with its API token and data
+    #[command(name = "remove")]
+    Remove {
+        /// The name of the profile
+        name: String,
+    },
+
+    /// Set the profile that's used when no profile is given
+    #[command(name = "default")]
+    Default {
+        /// The name of the profile
+        name: String,
+    },
+}
+
+/// The items in the inbox, in the order that `list` shows them.
+#[must_use]
+pub fn inbox_items<'a>(model: &'a Model, config: &Config) -> Vec<&'a Item> {
+    let mut items = model.get_inbox_items(!config.show_completed);
+    list::sort_items(&mut items, config.sort_order);
+    items
 }
 
+/// Marks the todos that `references` refer to complete, and returns their ids. They're all
+/// found before any are marked, so that numbers from `list` keep pointing at the same todos.
+///
 /// # Errors
 ///
-/// Returns an error if `number` does not correspond to a valid item
-pub fn complete_item(number: usize, model: &mut Model) -> Result<()> {
-    // look at the current inbox and determine which task is targeted
-    let inbox_items = model.get_inbox_items(true);
-    let num_items = inbox_items.len();
-
-    let item = inbox_items.get(number - 1).ok_or_else(|| {
-        anyhow!(
-            "'{number}' is outside of the valid range. Pass a number between 1 and {num_items}.",
-        )
-    })?;
-    let content = item.content.clone();
-
-    model.mark_item(&item.id.clone(), true);
-    println!("'{content}' marked complete.");
-
-    Ok(())
+/// Returns an `InvalidInput` error if a reference doesn't refer to exactly one todo, in
+/// which case nothing is marked.
+pub fn complete_items(
+    references: &[&str],
+    model: &mut Model,
+    config: &Config,
+) -> Result<Vec<ItemId>> {
+    let ids = references
+        .iter()
+        .map(|reference| Ok(item_ref::find_item(reference, model, config)?.id.clone()))
+        .collect::<Result<Vec<_>>>()?;
+    for id in &ids {
+        model.mark_item(id, true);
+    }
+    Ok(ids)
 }
 
 /// # Errors
 ///
-/// Returns an error if something goes awry while processing the command.
+/// Returns an error if something goes awry while processing the command, or if it's one of the
+/// commands that has its own handler (`profile`, `config`, or `completions`).
 pub async fn handle_command(
     command: &Command,
     args: Args,
-    model_manager: ModelManager<'_>,
+    model_store: &dyn ModelStore,
     client: Result<Client>,
     config_manager: ConfigManager<'_>,
+    config: &Config,
 ) -> Result<()> {
     match command {
-        Command::AddTodo { todo, no_sync, due } => {
-            // TODO: parse the date first, it might be no good and we'll need to error out
-            let today = args
-                .datetime_override
-                .unwrap_or(Local::now().naive_local())
-                .date();
-            let due_date = due.as_ref().and_then(|due| {
-                Due::parse_from_str(due, today).and_then(|(date, range)| {
-                    // reject the due date if it didn't parse exactly
-                    if range == (0..due.len()) {
-                        Some(date)
-                    } else {
-                        None
-                    }
-                })
-            });
-
-            let mut model = model_manager.read_model()?;
-            model.add_item_to_inbox(todo, due_date);
-            println!("'{todo}' added to inbox.");
+        Command::AddTodo {
+            todo,
+            options,
+            stdin: _,
+            no_sync,
+        } => {
+            let _lock = model_store.lock()?;
+            let mut model = model_store.read_model()?;
+            let mut ids = add_todos(todo.as_deref(), options, &args, &mut model, config)?;
             if !no_sync {
-                sync(&mut model, &client?, true).await?;
+                let id_mapping =
+                    sync_changes(&mut model, model_store, client, args.output, config).await?;
+                ids = with_real_ids(ids, &id_mapping);
             }
-            model_manager.write_model(&model)?;
+            model_store.write_model(&model)?;
+            print_items(args.output, &ids, todo.is_none(), &model)?;
         }
 
-        Command::CompleteTodo { number, no_sync } => {
-            let mut model = model_manager.read_model()?;
-            complete_item(*number, &mut model)?;
+        Command::CompleteTodo {
+            todo,
+            stdin: _,
+            no_sync,
+        } => {
+            let references = match todo {
+                Some(todo) => vec![todo.clone()],
+                None => stdin_lines()?,
+            };
+            let references: Vec<&str> = references.iter().map(String::as_str).collect();
+
+            let _lock = model_store.lock()?;
+            let mut model = model_store.read_model()?;
+            let ids = complete_items(&references, &mut model, config)?;
+            if args.output == OutputFormat::Text {
+                for item in items_with_ids(&ids, &model) {
+                    println!("'{}' marked complete.", item.content);
+                }
+            }
+            // completed todos are gone from the model once they're synced
+            let mut records = item_records(&ids, &model)?;
             if !no_sync {
-                sync(&mut model, &client?, true).await?;
+                let id_mapping =
+                    sync_changes(&mut model, model_store, client, args.output, config).await?;
+                records = records_with_real_ids(records, &id_mapping);
             }
-            model_manager.write_model(&model)?;
+            model_store.write_model(&model)?;
+            print_item_records(args.output, &records, todo.is_none())?;
         }
 
-        Command::ListInbox => {
-            let model = model_manager.read_model()?;
-            let inbox_items = model.get_inbox_items(true);
+        Command::List { query, tree } => {
+            let now = args.datetime_override.unwrap_or(Local::now().naive_local());
+            let selection = model_store
+                .read_model_with_items(&|model| list::item_filter(model, query, config, now))?;
+            print_list(args.output, query, *tree, now, selection, config)?;
+        }
 
-            if inbox_items.is_empty() {
-                println!("Your inbox is empty.");
-            } else {
-                println!("Inbox: ");
-                for (index, Item { content, .. }) in inbox_items.iter().enumerate() {
-                    println!("[{}] {content}", index + 1);
-                }
-            }
+        Command::Export { format, project } => {
+            let model = model_store.read_model()?;
+            export::print_snapshot(*format, project.as_deref(), &model, config)?;
         }
 
-        Command::SetApiToken { token } => {
-            config_manager.write_auth_config(&Auth {
-                api_token: token.clone(),
-            })?;
-            println!("Stored API token.");
+        Command::Import { import, no_sync } => {
+            import_todos(import, *no_sync, &args, model_store, client, config).await?;
+        }
+
+        Command::SetApiToken {
+            token,
+            store,
+            command,
+        } => {
+            set_api_token(
+                &config_manager,
+                token.as_deref(),
+                *store,
+                command.as_deref(),
+            )?;
         }
 
         Command::Sync { incremental } => {
-            let mut model = model_manager.read_model()?;
-            sync(&mut model, &client?, *incremental).await?;
-            model_manager.write_model(&model)?;
+            sync_command(*incremental, args.output, model_store, client).await?;
+        }
+
+        Command::Pending { command } => {
+            pending::handle_pending_command(command.as_ref(), args.output, model_store)?;
+        }
+
+        Command::CompletionValues { kind } => {
+            // completion happens all the time while typing, so it only goes by what's stored
+            let model = model_store.read_model()?;
+            for value in completions::values(*kind, &model) {
+                println!("{value}");
+            }
+        }
+
+        // these don't need the model (or work without a valid config), so they have their
+        // own handlers
+        Command::Profile { .. } | Command::Config { .. } | Command::Completions { .. } => {
+            bail!(
+                "The profile, config, and completions commands are handled by \
+                 `handle_profile_command`, `handle_config_command`, and \
+                 `completions::print_script`."
+            );
+        }
+    };
+
+    Ok(())
+}
+
+/// Syncs with the server for the `sync` command, and prints what was synced as a record
+/// unless the output is for reading.
+async fn sync_command(
+    incremental: bool,
+    format: OutputFormat,
+    model_store: &dyn ModelStore,
+    client: Result<Client>,
+) -> Result<()> {
+    let _lock = model_store.lock()?;
+    let mut model = model_store.read_model()?;
+    let commands_sent = model.commands.len();
+    sync(&mut model, &client?, incremental, format).await?;
+    model_store.write_model(&model)?;
+
+    if let Some(format) = format.records() {
+        output::print_record(
+            format,
+            SyncRecord {
+                full_sync: !incremental,
+                commands_sent,
+                commands_pending: model.commands.len(),
+                projects: model.projects.len(),
+                items: model.items.len(),
+            },
+        )?;
+    }
+    Ok(())
+}
+
+/// Adds the todos from the file that `import` was given (see `import::add_todos`), or
+/// shows what would be added for a dry run.
+async fn import_todos(
+    import: &ImportArgs,
+    no_sync: bool,
+    args: &Args,
+    model_store: &dyn ModelStore,
+    client: Result<Client>,
+    config: &Config,
+) -> Result<()> {
+    let (text, format) = import::read_file(import)?;
+
+    let _lock = model_store.lock()?;
+    let mut model = model_store.read_model()?;
+    let project = import::find_project(&import.project, &model)?;
+    let (project_id, project_name) = (project.id.clone(), project.name.clone());
+    let todos = import::parse(&text, format, project)?;
+    let queued = model.commands.len();
+    let ids = import::add_todos(&todos, &project_id, &mut model);
+
+    if import.dry_run {
+        if args.output == OutputFormat::Text {
+            import::print_changes(&model.commands[queued..], &model);
+            println!("Nothing was changed, since this was a dry run.");
+        }
+        return print_items(args.output, &ids, true, &model);
+    }
+
+    if args.output == OutputFormat::Text {
+        match ids.len() {
+            1 => println!("1 todo added to {project_name}."),
+            count => println!("{count} todos added to {project_name}."),
+        }
+    }
+    // the todos that were checked off are gone from the model once they're synced
+    let mut records = item_records(&ids, &model)?;
+    if !no_sync {
+        let id_mapping = sync_changes(&mut model, model_store, client, args.output, config).await?;
+        records = records_with_real_ids(records, &id_mapping);
+    }
+    model_store.write_model(&model)?;
+    print_item_records(args.output, &records, true)
+}
+
+/// Adds the todo from the command line, or the ones from stdin if there isn't one (see
+/// `add::new_item` and `add::add_items`), and returns their ids.
+fn add_todos(
+    todo: Option<&str>,
+    options: &AddOptions,
+    args: &Args,
+    model: &mut Model,
+    config: &Config,
+) -> Result<Vec<ItemId>> {
+    let today = args
+        .datetime_override
+        .unwrap_or(Local::now().naive_local())
+        .date();
+    let ids = match todo {
+        Some(todo) => {
+            let item = add::new_item(todo, options, today, model, config)?;
+            let id = item.id.clone();
+            model.add_item(item);
+            vec![id]
+        }
+        None => add::add_items(&read_stdin()?, options, today, model, config)?,
+    };
+    if args.output == OutputFormat::Text {
+        for item in items_with_ids(&ids, model) {
+            println!("'{}' added to {}.", item.content, project_name(item, model));
+        }
+    }
+    Ok(ids)
+}
+
+/// The server gives new todos real ids in place of their temporary ones when they're synced.
+fn with_real_ids(ids: Vec<ItemId>, id_mapping: &HashMap<String, String>) -> Vec<ItemId> {
+    ids.into_iter()
+        .map(|id| {
+            id_mapping
+                .get(&id.to_string())
+                .map_or(id, |real_id| real_id.as_str().into())
+        })
+        .collect()
+}
+
+/// Like `with_real_ids`, for the todos (and their parents) in records made before syncing.
+fn records_with_real_ids(
+    records: Vec<ItemRecord>,
+    id_mapping: &HashMap<String, String>,
+) -> Vec<ItemRecord> {
+    let real_id = |id: String| id_mapping.get(&id).cloned().unwrap_or(id);
+    records
+        .into_iter()
+        .map(|record| ItemRecord {
+            id: real_id(record.id),
+            parent_id: record.parent_id.map(real_id),
+            ..record
+        })
+        .collect()
+}
+
+fn items_with_ids<'a>(ids: &'a [ItemId], model: &'a Model) -> impl Iterator<Item = &'a Item> {
+    ids.iter()
+        .filter_map(|id| model.items.iter().find(|item| item.id == *id))
+}
+
+/// The name of the project a todo is in, for messages (which call the inbox "inbox").
+fn project_name(item: &Item, model: &Model) -> String {
+    if item.project_id == model.user.inbox_project_id {
+        return "inbox".to_string();
+    }
+    model.project_with_id(&item.project_id).map_or_else(
+        || item.project_id.to_string(),
+        |project| project.name.clone(),
+    )
+}
+
+/// Prints the todos that `list` asks for. The model only needs to have the todos that could
+/// be listed, as long as `item_ids` has the ids of all of them.
+fn print_list(
+    format: OutputFormat,
+    query: &Query,
+    tree: bool,
+    now: NaiveDateTime,
+    selection: ItemSelection,
+    config: &Config,
+) -> Result<()> {
+    let ItemSelection {
+        user,
+        projects,
+        sections,
+        items,
+        item_ids,
+    } = selection;
+    // only for looking the todos up, since it's missing the ones that weren't read
+    let model = &Model {
+        items,
+        projects,
+        sections,
+        user,
+        ..Model::default()
+    };
+
+    // the numbered inbox list is what `complete` goes by, so it's only replaced
+    // when something else is asked for
+    if tree || !query.is_inbox() {
+        let groups = list::find_items(model, config, query, now)?;
+        return print_groups(format, &groups, model, &item_ids, config);
+    }
+
+    let inbox_items = inbox_items(model, config);
+    if let Some(format) = format.records() {
+        let records: Vec<ItemRecord> = inbox_items
+            .iter()
+            .map(|item| ItemRecord::new(item, model))
+            .collect();
+        output::print_records(format, &records)?;
+    } else if inbox_items.is_empty() {
+        println!("Your inbox is empty.");
+    } else {
+        println!("Inbox: ");
+        for (index, Item { content, .. }) in inbox_items.iter().enumerate() {
+            println!("[{}] {content}", index + 1);
+        }
+    }
+    Ok(())
+}
+
+/// Prints todos grouped by project and section, with subtasks indented under their parents.
+fn print_groups(
+    format: OutputFormat,
+    groups: &[Group<'_>],
+    model: &Model,
+    item_ids: &[ItemId],
+    config: &Config,
+) -> Result<()> {
+    if let Some(format) = format.records() {
+        let records: Vec<ItemRecord> = groups
+            .iter()
+            .flat_map(|group| &group.items)
+            .map(|(_, item)| ItemRecord::new(item, model))
+            .collect();
+        return output::print_records(format, &records);
+    }
+
+    let short_ids = item_ref::short_ids(item_ids);
+    let groups: Vec<&Group<'_>> = groups.iter().filter(|g| !g.items.is_empty()).collect();
+    if groups.is_empty() {
+        println!("There are no todos to show.");
+    }
+    for (index, group) in groups.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        println!("{}", group.title());
+        for (depth, item) in &group.items {
+            let indent = "  ".repeat(*depth);
+            let check = if item.checked { "✓" } else { "-" };
+            let due = item.due.as_ref().map_or(String::new(), |due| {
+                format!(
+                    "  ({})",
+                    due.format(&config.date_format, &config.time_format)
+                )
+            });
+            let short_id = short_ids.get(&item.id).map_or("", String::as_str);
+            println!("{indent}{check} {}{due}  [{short_id}]", item.content);
         }
+    }
+    Ok(())
+}
+
+/// Prints the records for the todos that a command changed, unless the output is for
+/// reading. A single todo is printed as a single record unless there could've been more.
+fn print_items(format: OutputFormat, ids: &[ItemId], many: bool, model: &Model) -> Result<()> {
+    if format.records().is_none() {
+        return Ok(());
+    }
+    print_item_records(format, &item_records(ids, model)?, many)
+}
+
+/// The records for the todos with the given ids, in the same order.
+fn item_records(ids: &[ItemId], model: &Model) -> Result<Vec<ItemRecord>> {
+    ids.iter()
+        .map(|id| {
+            let item = model
+                .items
+                .iter()
+                .find(|item| item.id == *id)
+                .ok_or_else(|| anyhow!("The todo with id '{id}' went missing."))?;
+            Ok(ItemRecord::new(item, model))
+        })
+        .collect()
+}
+
+/// Like `print_items`, for records that were already made.
+fn print_item_records(format: OutputFormat, records: &[ItemRecord], many: bool) -> Result<()> {
+    let Some(format) = format.records() else {
+        return Ok(());
     };
+    match records {
+        [record] if !many => output::print_record(format, record.clone()),
+        records => output::print_records(format, records),
+    }
+}
+
+fn read_stdin() -> Result<String> {
+    io::read_to_string(io::stdin()).context("Couldn't read from stdin.")
+}
+
+/// The lines from stdin that aren't blank, trimmed.
+fn stdin_lines() -> Result<Vec<String>> {
+    Ok(read_stdin()?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Stores the API token, or the command that gets it.
+///
+/// # Errors
+///
+/// Returns an error if the token can't be stored.
+fn set_api_token(
+    config_manager: &ConfigManager<'_>,
+    token: Option<&str>,
+    store: Option<TokenStore>,
+    command: Option<&str>,
+) -> Result<()> {
+    if let Some(command) = command {
+        config_manager.write_auth_config(&Auth {
+            token_command: Some(command.to_string()),
+            ..Default::default()
+        })?;
+        println!("The API token will be read from the output of '{command}'.");
+    } else if let Some(token) = token {
+        match store.unwrap_or(TokenStore::File) {
+            TokenStore::File => {
+                config_manager.write_auth_config(&Auth {
+                    api_token: Some(token.to_string()),
+                    ..Default::default()
+                })?;
+                println!("Stored API token.");
+                eprintln!(
+                    "Warning: the token is stored in plain text in '{}'. Use '--store keyring' \
+                    or '--command' to keep it somewhere safer.",
+                    config_manager.auth_file_path().display()
+                );
+            }
+            TokenStore::Keyring => {
+                config_manager.write_keyring_token(token)?;
+                config_manager.write_auth_config(&Auth {
+                    keyring: true,
+                    ..Default::default()
+                })?;
+                println!("Stored API token in the keyring.");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Config commands are handled before the settings are read, so that they can be used
+/// to fix a broken config file.
+///
+/// # Errors
+///
+/// Returns an error if something goes awry while processing the command.
+pub fn handle_config_command(
+    command: &ConfigCommand,
+    config_manager: &ConfigManager<'_>,
+) -> Result<()> {
+    match command {
+        ConfigCommand::Get { key } => {
+            println!("{}", format_setting(config_manager.get_setting(key)?));
+        }
+
+        ConfigCommand::Set { key, value } => {
+            config_manager.set_setting(key, value)?;
+            println!("Set '{key}' to '{value}'.");
+        }
+
+        ConfigCommand::List => {
+            for (key, value) in config_manager.list_settings()? {
+                println!("{key} = {value}");
+            }
+        }
+
+        ConfigCommand::Path => {
+            println!("{}", config_manager.config_file_path().display());
+        }
+    }
+
+    Ok(())
+}
+
+/// Strings are printed without quotes, everything else as TOML.
+fn format_setting(value: toml::Value) -> String {
+    match value {
+        toml::Value::String(string) => string,
+        toml::Value::Table(table) => toml::to_string(&table).unwrap_or_default(),
+        value => value.to_string(),
+    }
+}
+
+/// Profile commands don't need a profile to be open (and might be about to create it),
+/// so they're handled separately from the others.
+///
+/// # Errors
+///
+/// Returns an error if something goes awry while processing the command.
+pub fn handle_profile_command(
+    command: &ProfileCommand,
+    profile_manager: &ProfileManager<'_>,
+) -> Result<()> {
+    match command {
+        ProfileCommand::List => {
+            let default_profile = profile_manager.default_profile()?;
+            for name in profile_manager.list()? {
+                if name == default_profile {
+                    println!("{name} (default)");
+                } else {
+                    println!("{name}");
+                }
+            }
+        }
+
+        ProfileCommand::Add { name } => {
+            profile_manager.add(name)?;
+            println!("Added profile '{name}'. Store its API token with '--profile {name} set-token <TOKEN>'.");
+        }
+
+        ProfileCommand::Remove { name } => {
+            profile_manager.remove(name)?;
+            println!("Removed profile '{name}'.");
+        }
+
+        ProfileCommand::Default { name } => {
+            profile_manager.set_default_profile(name)?;
+            println!("'{name}' is now the default profile.");
+        }
+    }
 
     Ok(())
 }
 
+/// Syncs the changes that `add` or `complete` made, the way the `sync_mode` setting says to,
+/// and returns the ids that the server gave to new todos (see `sync`).
+///
+/// # Errors
+///
+/// Returns an error if syncing fails in the immediate sync mode. In the deferred mode, the
+/// changes are stored before syncing, so that failing is only a warning.
+async fn sync_changes(
+    model: &mut Model,
+    model_store: &dyn ModelStore,
+    client: Result<Client>,
+    format: OutputFormat,
+    config: &Config,
+) -> Result<HashMap<String, String>> {
+    if config.sync_mode == SyncMode::Immediate {
+        return sync(model, &client?, true, format).await;
+    }
+
+    model_store.write_model(model)?;
+    let synced = match client {
+        Ok(client) => sync(model, &client, true, format).await,
+        Err(err) => Err(err),
+    };
+    Ok(synced.unwrap_or_else(|err| {
+        warn_sync_failed(&err, model);
+        HashMap::new()
+    }))
+}
+
+/// Warns that a sync failed, so the model's pending changes are waiting for the next one.
+pub fn warn_sync_failed(err: &anyhow::Error, model: &Model) {
+    let waiting = match model.commands.len() {
+        1 => "1 change is".to_string(),
+        count => format!("{count} changes are"),
+    };
+    eprintln!(
+        "Warning: {err} {waiting} waiting to be sent with the next sync \
+         (`todoist-tui pending` lists them)."
+    );
+}
+
 // FIXME: this probably isn't the right place for this function
+/// Syncs the model with the server, showing progress unless the output is meant for scripts.
+/// Returns the ids that the server gave to things created locally, by their temporary ids.
+///
 /// # Errors
 ///
-/// Returns an error if something goes wrong while sending/receiving data from the Todoist API.
-pub async fn sync(model: &mut Model, client: &Client, incremental: bool) -> Result<()> {
+/// Returns an error (with `SyncFailed` as context) if something goes wrong while
+/// sending/receiving data from the Todoist API.
+pub async fn sync(
+    model: &mut Model,
+    client: &Client,
+    incremental: bool,
+    format: OutputFormat,
+) -> Result<HashMap<String, String>> {
     let sync_token = if incremental {
         model.sync_token.clone()
     } else {
@@ -193,14 +910,21 @@ pub async fn sync(model: &mut Model, client: &Client, incremental: bool) -> Resu
         commands: model.commands.clone(),
     };
 
-    print!("Syncing... ");
-    io::stdout().flush()?;
+    let show_progress = format == OutputFormat::Text;
+    if show_progress {
+        print!("Syncing... ");
+        io::stdout().flush()?;
+    }
 
-    let response = client.make_request(&request).await?;
-    println!("Done.");
+    let response = client.make_request(&request).await;
+    if show_progress {
+        println!("{}", if response.is_ok() { "Done." } else { "Failed." });
+    }
+    let response = response.context(SyncFailed)?;
 
     // update the sync_data with the result
+    let id_mapping = response.temp_id_mapping.clone();
     model.update(response);
 
-    Ok(())
+    Ok(id_mapping)
 }
@@ -0,0 +1,181 @@
+use super::{item_ref, output::InvalidInput};
+use crate::{
+    model::{
+        due_date::Due,
+        item::{Id as ItemId, Item},
+        quick_add::{self, QuickAdd},
+        Model,
+    },
+    storage::config_manager::Config,
+};
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use clap::Args;
+
+/// Where a new todo goes and what it's like, on top of what's typed in its text.
+#[derive(Args, Debug, Clone, Default)]
+pub struct AddOptions {
+    /// When the todo is due (instead of a date in its text)
+    #[arg(long, short)]
+    pub due: Option<String>,
+
+    /// The project to add the todo to, by name or id (instead of the inbox or the
+    /// `default_project` setting)
+    #[arg(long, short)]
+    pub project: Option<String>,
+
+    /// The section to add the todo to, by name or id
+    #[arg(long, short)]
+    pub section: Option<String>,
+
+    /// Make the todo a subtask of this one (given like in `complete`), in the same
+    /// project and section
+    #[arg(long)]
+    pub parent: Option<String>,
+
+    /// The priority as Todoist shows it, from 1 (or p1) for urgent to 4 for normal
+    #[arg(long, value_parser = parse_priority)]
+    pub priority: Option<u8>,
+
+    /// A label to put on the todo (give this more than once for more labels)
+    #[arg(long = "label", short, value_name = "LABEL")]
+    pub labels: Vec<String>,
+}
+
+fn parse_priority(input: &str) -> Result<u8, String> {
+    quick_add::parse_priority(input).ok_or_else(|| "it must be 1, 2, 3, or 4".to_string())
+}
+
+/// Makes a new todo from text with quick-add syntax (see `QuickAdd`). The options win
+/// over anything in the text.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if the due date can't be understood, if there's no text
+/// left for the todo, or if its project, section, or parent can't be found.
+pub fn new_item(
+    text: &str,
+    options: &AddOptions,
+    today: NaiveDate,
+    model: &Model,
+    config: &Config,
+) -> Result<Item> {
+    let due = options
+        .due
+        .as_deref()
+        .map(|due| {
+            parse_exact_due(due, today)
+                .ok_or_else(|| InvalidInput(format!("Couldn't understand the due date '{due}'.")))
+        })
+        .transpose()?;
+
+    // a date in the text is left alone if there's one in the options
+    let mut quick_add = QuickAdd::parse(text, due.is_none().then_some(today));
+    if quick_add.content.is_empty() {
+        return Err(InvalidInput(format!("There's no text for the todo in '{text}'.")).into());
+    }
+    if due.is_some() {
+        quick_add.due = due;
+    }
+    if options.project.is_some() {
+        quick_add.project.clone_from(&options.project);
+    }
+    if options.section.is_some() {
+        quick_add.section.clone_from(&options.section);
+    }
+    if options.priority.is_some() {
+        quick_add.priority = options.priority;
+    }
+    quick_add.labels.extend(options.labels.iter().cloned());
+
+    let default_project_id = if config.default_project.is_empty() {
+        model.user.inbox_project_id.clone()
+    } else {
+        model
+            .find_project(&config.default_project)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Could not find the project '{}' from the `default_project` setting.",
+                    config.default_project
+                )
+            })?
+            .id
+            .clone()
+    };
+    let mut item = quick_add
+        .to_item(model, &default_project_id)
+        .map_err(|err| InvalidInput(err.to_string()))?;
+
+    if let Some(reference) = &options.parent {
+        make_subtask(&mut item, item_ref::find_item(reference, model, config)?);
+    }
+
+    Ok(item)
+}
+
+/// Adds a todo for each line of `text`, which are made like `new_item` makes them (and
+/// can start with a checklist's `-` or `- [ ]`). A line that's indented further than the
+/// one before it is a subtask of that one. Blank lines are skipped.
+///
+/// Nothing is added unless every line can be.
+///
+/// # Errors
+///
+/// Returns an error (which says which line it's on) if any of the todos can't be made.
+pub fn add_items(
+    text: &str,
+    options: &AddOptions,
+    today: NaiveDate,
+    model: &mut Model,
+    config: &Config,
+) -> Result<Vec<ItemId>> {
+    let mut new_items: Vec<(usize, Item)> = vec![];
+    for (index, line) in text.lines().enumerate() {
+        let todo = strip_list_marker(line.trim());
+        if todo.is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        let mut item = new_item(todo, options, today, model, config)
+            .with_context(|| format!("Couldn't add the todo on line {}.", index + 1))?;
+        // the nearest todo before this one that's indented less is its parent
+        let parent = new_items
+            .iter()
+            .rev()
+            .find(|(parent_indent, _)| *parent_indent < indent);
+        if let Some((_, parent)) = parent {
+            make_subtask(&mut item, parent);
+        }
+        new_items.push((indent, item));
+    }
+
+    Ok(new_items
+        .into_iter()
+        .map(|(_, item)| {
+            let id = item.id.clone();
+            model.add_item(item);
+            id
+        })
+        .collect())
+}
+
+fn make_subtask(item: &mut Item, parent: &Item) {
+    item.project_id = parent.project_id.clone();
+    item.section_id.clone_from(&parent.section_id);
+    item.parent_id = Some(parent.id.clone());
+}
+
+fn strip_list_marker(line: &str) -> &str {
+    let line = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .unwrap_or(line);
+    line.strip_prefix("[ ] ").unwrap_or(line).trim_start()
+}
+
+/// Parses a due date, rejecting it unless all of it was understood.
+fn parse_exact_due(due: &str, today: NaiveDate) -> Option<Due> {
+    Due::parse_from_str(due, today)
+        .and_then(|(date, range)| (range == (0..due.len())).then_some(date))
+}
@@ -0,0 +1,185 @@
+use super::{item_ref, Args};
+use crate::model::Model;
+use anyhow::Result;
+use clap::{CommandFactory, ValueEnum};
+use clap_complete::Shell;
+use std::{collections::BTreeSet, io};
+
+/// The things that tab completion fills in from the todos that were last synced.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    Projects,
+    Sections,
+    Labels,
+    Todos,
+}
+
+/// The values that tab completion can fill in, each with a description after a tab if
+/// there's one worth showing.
+#[must_use]
+pub fn values(kind: ValueKind, model: &Model) -> Vec<String> {
+    match kind {
+        ValueKind::Projects => model
+            .projects
+            .iter()
+            .map(|project| project.name.clone())
+            .collect(),
+        ValueKind::Sections => model
+            .sections
+            .iter()
+            .map(|section| {
+                let project = model
+                    .project_with_id(&section.project_id)
+                    .map_or("", |project| project.name.as_str());
+                format!("{}\t{project}", section.name)
+            })
+            .collect(),
+        ValueKind::Labels => model
+            .items
+            .iter()
+            .flat_map(|item| &item.labels)
+            .cloned()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect(),
+        ValueKind::Todos => {
+            let short_ids = item_ref::short_ids(model.items.iter().map(|item| &item.id));
+            model
+                .items
+                .iter()
+                .filter(|item| !item.checked)
+                .filter_map(|item| {
+                    let short_id = short_ids.get(&item.id)?;
+                    Some(format!("{short_id}\t{}", item.content))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Prints the script that sets up tab completion for a shell. Most of it is generated from
+/// `Args`; what's added on top asks the app for the values that change (like project names),
+/// which clap can't know about.
+///
+/// # Errors
+///
+/// Returns an error if the script can't be printed.
+pub fn print_script(shell: Shell) -> Result<()> {
+    let mut script = vec![];
+    clap_complete::generate(shell, &mut Args::command(), BIN_NAME, &mut script);
+    let mut script = String::from_utf8(script)?;
+
+    match shell {
+        Shell::Bash => script.push_str(BASH_VALUES),
+        Shell::Fish => script.push_str(FISH_VALUES),
+        Shell::Zsh => {
+            // clap leaves these arguments without anything to complete them with
+            for (argument, kind) in ZSH_OPTIONS {
+                script = script.replace(
+                    &format!(":{argument}: '"),
+                    &format!(":{argument}:_{BIN_NAME}_values {kind}'"),
+                );
+            }
+            script = script
+                .lines()
+                .map(|line| match line.strip_suffix(":' \\") {
+                    Some(start) if line.starts_with(&format!("'::{ZSH_TODO_REF} -- ")) => {
+                        format!("{start}:_{BIN_NAME}_values todos' \\\n")
+                    }
+                    _ => format!("{line}\n"),
+                })
+                .collect();
+            // zsh wants `#compdef` on the first line, so the helper goes right after it
+            let first_line_end = script.find('\n').map_or(script.len(), |end| end + 1);
+            script.insert_str(first_line_end, ZSH_VALUES);
+        }
+        _ => {}
+    }
+
+    io::Write::write_all(&mut io::stdout(), script.as_bytes())?;
+    Ok(())
+}
+
+const BIN_NAME: &str = "todoist-tui";
+
+/// The value names of the options that zsh completes from the model, and what with.
+const ZSH_OPTIONS: [(&str, &str); 4] = [
+    ("PROJECT", "projects"),
+    ("SECTION", "sections"),
+    ("LABEL", "labels"),
+    ("PARENT", "todos"),
+];
+
+/// The id of `complete`'s argument, which is how zsh's script names it.
+const ZSH_TODO_REF: &str = "todo_ref";
+
+const BASH_VALUES: &str = r#"
+_todoist-tui_values() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}" kind=""
+    case "${prev}" in
+        --project|-p) kind=projects ;;
+        --section|-s) kind=sections ;;
+        --label|-l) kind=labels ;;
+        --parent|complete) kind=todos ;;
+    esac
+    if [[ -z "${kind}" ]]; then
+        _todoist-tui "$@"
+        return
+    fi
+    local IFS=$'\n'
+    COMPREPLY=($(compgen -W "$(todoist-tui completion-values "${kind}" 2>/dev/null | cut -f1)" -- "${cur}"))
+}
+
+complete -F _todoist-tui_values -o bashdefault -o default todoist-tui
+"#;
+
+const FISH_VALUES: &str = r#"
+complete -c todoist-tui -n "__fish_seen_subcommand_from add list export import" -s p -l project -x -a "(todoist-tui completion-values projects 2>/dev/null)"
+complete -c todoist-tui -n "__fish_seen_subcommand_from add list" -s s -l section -x -a "(todoist-tui completion-values sections 2>/dev/null)"
+complete -c todoist-tui -n "__fish_seen_subcommand_from add" -s l -l label -x -a "(todoist-tui completion-values labels 2>/dev/null)"
+complete -c todoist-tui -n "__fish_seen_subcommand_from add" -l parent -x -a "(todoist-tui completion-values todos 2>/dev/null)"
+complete -c todoist-tui -n "__fish_seen_subcommand_from complete" -f -a "(todoist-tui completion-values todos 2>/dev/null)"
+"#;
+
+const ZSH_VALUES: &str = r#"
+_todoist-tui_values() {
+    local -a values
+    local value description
+    todoist-tui completion-values $1 2>/dev/null | while IFS=$'\t' read -r value description; do
+        values+=("${value//:/\\:}${description:+:$description}")
+    done
+    _describe -t $1 $1 values
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::model::{item::Item, project::Project, section::Section};
+
+    #[test]
+    fn complete_values_from_model() {
+        let work = Project::new("Work");
+        let model = Model {
+            items: vec![
+                Item {
+                    id: "6Jf8VQXxpwv56VQ7".into(),
+                    ..Item::new("Call Bob", &work.id).labels(vec!["phone".to_string()])
+                },
+                Item::new("Called Alice", &work.id)
+                    .labels(vec!["phone".to_string(), "errands".to_string()])
+                    .checked(true),
+            ],
+            sections: vec![Section::new("Calls", &work.id)],
+            projects: vec![work],
+            ..Default::default()
+        };
+
+        assert_eq!(values(ValueKind::Projects, &model), ["Work"]);
+        assert_eq!(values(ValueKind::Sections, &model), ["Calls\tWork"]);
+        assert_eq!(values(ValueKind::Labels, &model), ["errands", "phone"]);
+        assert_eq!(values(ValueKind::Todos, &model), ["6Jf8\tCall Bob"]);
+    }
+}
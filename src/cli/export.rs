@@ -0,0 +1,327 @@
+use super::list::{self, Group, Query};
+use crate::{
+    model::{due_date::DueDate, item::Item, project::Project, section::Section, Model},
+    storage::config_manager::{Config, SortOrder},
+};
+use anyhow::Result;
+use chrono::NaiveDateTime;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+/// What `export` writes the snapshot as.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Headings for projects and sections, with the todos in nested checklists
+    #[default]
+    Markdown,
+
+    /// The projects, sections, and todos as they're stored, for backups
+    Json,
+
+    /// One row per todo, with a header row
+    Csv,
+
+    /// One line per todo, with the project, section, and subtasks in `key:value` tags
+    Todotxt,
+}
+
+/// Everything in the projects that were exported, as it's stored. Subtasks point to
+/// their parents with `parent_id`, and `child_order` keeps them in order.
+#[derive(Serialize)]
+struct Snapshot<'a> {
+    projects: Vec<&'a Project>,
+    sections: Vec<&'a Section>,
+    items: Vec<&'a Item>,
+}
+
+/// Prints a snapshot of a project (by name or id) and its subprojects, or of every
+/// project if there isn't one, including completed todos. Todos are in the order
+/// they're in on Todoist, whatever the `sort_order` setting is.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if the project can't be found, or an error if the
+/// snapshot can't be written.
+pub fn print_snapshot(
+    format: ExportFormat,
+    project: Option<&str>,
+    model: &Model,
+    config: &Config,
+) -> Result<()> {
+    write_snapshot(&mut io::stdout().lock(), format, project, model, config)
+}
+
+fn write_snapshot(
+    out: &mut impl Write,
+    format: ExportFormat,
+    project: Option<&str>,
+    model: &Model,
+    config: &Config,
+) -> Result<()> {
+    let query = Query {
+        project: project.map(ToString::to_string),
+        subprojects: true,
+        all: project.is_none(),
+        completed: true,
+        ..Default::default()
+    };
+    let config = Config {
+        sort_order: SortOrder::Manual,
+        ..config.clone()
+    };
+    // the time only matters for filtering by due date, which this doesn't do
+    let groups = list::find_items(model, &config, &query, NaiveDateTime::default())?;
+
+    match format {
+        ExportFormat::Markdown => write_markdown(out, &groups),
+        ExportFormat::Json => write_json(out, &groups),
+        ExportFormat::Csv => write_csv(out, &groups),
+        ExportFormat::Todotxt => write_todotxt(out, &groups),
+    }
+}
+
+fn write_markdown(out: &mut impl Write, groups: &[Group<'_>]) -> Result<()> {
+    let mut project = None;
+    for group in groups {
+        if project != Some(&group.project.id) {
+            if project.is_some() {
+                writeln!(out)?;
+            }
+            writeln!(out, "# {}", group.project.name)?;
+            project = Some(&group.project.id);
+        }
+        if let Some(section) = group.section {
+            writeln!(out, "\n## {}", section.name)?;
+        }
+        if !group.items.is_empty() {
+            writeln!(out)?;
+        }
+
+        for (depth, item) in &group.items {
+            let mut words = vec![format!(
+                "{}- [{}] {}",
+                "  ".repeat(*depth),
+                if item.checked { 'x' } else { ' ' },
+                item.content
+            )];
+            // the same as quick-add's p1 to p3
+            if item.priority > 1 {
+                words.push(format!("p{}", 5 - item.priority));
+            }
+            words.extend(item.labels.iter().map(|label| format!("@{label}")));
+            words.extend(due_tag(item));
+            writeln!(out, "{}", words.join(" "))?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json(out: &mut impl Write, groups: &[Group<'_>]) -> Result<()> {
+    let mut projects: Vec<&Project> = groups.iter().map(|group| group.project).collect();
+    projects.dedup_by_key(|project| &project.id);
+    let snapshot = Snapshot {
+        projects,
+        sections: groups.iter().filter_map(|group| group.section).collect(),
+        items: groups
+            .iter()
+            .flat_map(|group| &group.items)
+            .map(|(_, item)| *item)
+            .collect(),
+    };
+
+    serde_json::to_writer_pretty(&mut *out, &snapshot)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+fn write_csv(out: &mut impl Write, groups: &[Group<'_>]) -> Result<()> {
+    writeln!(
+        out,
+        "id,parent_id,child_order,project,section,content,due,priority,checked,labels"
+    )?;
+    for group in groups {
+        for (_, item) in &group.items {
+            let values = [
+                item.id.to_string(),
+                item.parent_id
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+                item.child_order.to_string(),
+                group.project.name.clone(),
+                group
+                    .section
+                    .map(|section| section.name.clone())
+                    .unwrap_or_default(),
+                item.content.clone(),
+                item.due
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+                item.priority.to_string(),
+                item.checked.to_string(),
+                item.labels.join(","),
+            ];
+            let values: Vec<String> = values.iter().map(|value| escape_csv(value)).collect();
+            writeln!(out, "{}", values.join(","))?;
+        }
+    }
+    Ok(())
+}
+
+/// Values with commas, quotes, or line breaks in them are quoted, with quotes doubled.
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn write_todotxt(out: &mut impl Write, groups: &[Group<'_>]) -> Result<()> {
+    let parent_ids: HashSet<_> = groups
+        .iter()
+        .flat_map(|group| &group.items)
+        .filter_map(|(_, item)| item.parent_id.as_ref())
+        .collect();
+
+    for group in groups {
+        for (_, item) in &group.items {
+            // todo.txt's priorities go from (A) for urgent down, and normal todos have none
+            let priority = match item.priority {
+                4 => Some('A'),
+                3 => Some('B'),
+                2 => Some('C'),
+                _ => None,
+            };
+
+            let mut words = vec![];
+            match (item.checked, priority) {
+                (true, _) => words.push("x".to_string()),
+                (false, Some(priority)) => words.push(format!("({priority})")),
+                (false, None) => {}
+            }
+            words.push(item.content.replace('\n', " "));
+            words.push(format!("+{}", todotxt_tag(&group.project.name)));
+            if let Some(section) = group.section {
+                words.push(format!("section:{}", todotxt_tag(&section.name)));
+            }
+            words.extend(
+                item.labels
+                    .iter()
+                    .map(|label| format!("@{}", todotxt_tag(label))),
+            );
+            words.extend(due_tag(item));
+            // completed lines start with `x`, so their priority goes in a tag instead
+            if let (true, Some(priority)) = (item.checked, priority) {
+                words.push(format!("pri:{priority}"));
+            }
+            if parent_ids.contains(&item.id) {
+                words.push(format!("id:{}", item.id));
+            }
+            if let Some(parent_id) = &item.parent_id {
+                words.push(format!("parent:{parent_id}"));
+            }
+            writeln!(out, "{}", words.join(" "))?;
+        }
+    }
+    Ok(())
+}
+
+/// Tags in todo.txt end at the first space, so spaces in names become underscores.
+fn todotxt_tag(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// The due date as a `due:` tag, with a `T` rather than a space before the time (if there is
+/// one) so that it stays one word.
+fn due_tag(item: &Item) -> Option<String> {
+    item.due.as_ref().map(|due| match due.date {
+        DueDate::Date(date) => format!("due:{date}"),
+        DueDate::DateTime(datetime) => format!("due:{}", datetime.format("%Y-%m-%dT%H:%M:%S")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::model::due_date::Due;
+    use chrono::NaiveDate;
+
+    fn model() -> Model {
+        let work = Project::new("Work");
+        let calls = Section::new("Sales calls", &work.id);
+        let plan = Item {
+            id: "1".into(),
+            ..Item::new("Plan the offsite", &work.id)
+                .priority(4)
+                .due(Some(Due {
+                    date: DueDate::Date(NaiveDate::from_ymd_opt(2021, 10, 7).unwrap()),
+                }))
+        };
+        let book = Item {
+            id: "2".into(),
+            ..Item::new("Book a venue, with parking", &work.id)
+                .parent_id(&plan.id)
+                .child_order(1)
+                .checked(true)
+        };
+        let call = Item {
+            id: "3".into(),
+            ..Item::new("Call Bob", &work.id)
+                .section_id(&calls.id)
+                .labels(vec!["phone".to_string()])
+        };
+
+        Model {
+            items: vec![call, book, plan],
+            sections: vec![calls],
+            projects: vec![work],
+            ..Default::default()
+        }
+    }
+
+    fn export(format: ExportFormat) -> String {
+        let mut out = vec![];
+        write_snapshot(&mut out, format, None, &model(), &Config::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn export_markdown() {
+        assert_eq!(
+            export(ExportFormat::Markdown),
+            "# Work\n\
+             \n\
+             - [ ] Plan the offsite p1 due:2021-10-07\n  \
+               - [x] Book a venue, with parking\n\
+             \n\
+             ## Sales calls\n\
+             \n\
+             - [ ] Call Bob @phone\n"
+        );
+    }
+
+    #[test]
+    fn export_csv_and_todotxt() {
+        assert_eq!(
+            export(ExportFormat::Csv),
+            "id,parent_id,child_order,project,section,content,due,priority,checked,labels\n\
+             1,,0,Work,,Plan the offsite,2021-10-07,4,false,\n\
+             2,1,1,Work,,\"Book a venue, with parking\",,1,true,\n\
+             3,,0,Work,Sales calls,Call Bob,,1,false,phone\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Todotxt),
+            "(A) Plan the offsite +Work due:2021-10-07 id:1\n\
+             x Book a venue, with parking +Work parent:1\n\
+             Call Bob +Work section:Sales_calls @phone\n"
+        );
+    }
+}
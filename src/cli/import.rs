@@ -0,0 +1,718 @@
+use super::{output::InvalidInput, pending};
+use crate::model::{
+    command::Command,
+    due_date::{Due, DueDate},
+    item::{Id as ItemId, Item},
+    project::{self, Project},
+    quick_add,
+    section::{self, Section},
+    Model,
+};
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::{Args, ValueEnum};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// What `import` reads.
+#[derive(Args, Debug, Clone)]
+pub struct ImportArgs {
+    /// The file to add todos from, or `-` for stdin
+    pub file: PathBuf,
+
+    /// What the file is written as (guessed from its extension if this isn't given)
+    #[arg(long, short, value_enum)]
+    pub format: Option<ImportFormat>,
+
+    /// The project to add the todos to, by name or id
+    #[arg(long, short)]
+    pub project: String,
+
+    /// Show what would be added without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// The kinds of files that todos can be imported from.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportFormat {
+    /// Checklists (`- [ ]`, or `- [x]` for completed todos), with indented todos as
+    /// subtasks and headings as sections
+    Markdown,
+
+    /// A header row with a `content` column, like `export --format csv` writes
+    Csv,
+
+    /// One todo per line, with `+project` (or `section:`) tags as sections and `@context`
+    /// tags as labels
+    Todotxt,
+}
+
+impl ImportFormat {
+    /// Guesses the format from a file's extension.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "csv" => Some(Self::Csv),
+            "txt" => Some(Self::Todotxt),
+            _ => None,
+        }
+    }
+}
+
+/// A todo read from a file, before it's added.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NewTodo {
+    pub content: String,
+
+    /// The name of the section it goes in (subtasks go in their parent's instead)
+    pub section: Option<String>,
+
+    /// Which of the todos read before this one it's a subtask of
+    pub parent: Option<usize>,
+    pub due: Option<Due>,
+
+    /// From 1 (normal) to 4 (urgent), like Todoist's API
+    pub priority: Option<u8>,
+    pub labels: Vec<String>,
+    pub checked: bool,
+}
+
+/// Reads the file that `import` was given, along with the format it's in.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if the format isn't given and can't be guessed, or an
+/// error if the file can't be read.
+pub fn read_file(args: &ImportArgs) -> Result<(String, ImportFormat)> {
+    let format = args
+        .format
+        .or_else(|| ImportFormat::from_path(&args.file))
+        .ok_or_else(|| {
+            InvalidInput("Pass `--format` to say what the file is written as.".to_string())
+        })?;
+
+    let text = if args.file == Path::new("-") {
+        io::read_to_string(io::stdin()).context("Couldn't read from stdin.")?
+    } else {
+        fs::read_to_string(&args.file)
+            .with_context(|| format!("Couldn't read {}.", args.file.display()))?
+    };
+    Ok((text, format))
+}
+
+/// Reads the todos in a file that's being imported into a project. A heading (or todo.txt
+/// `+project` tag) that names the project itself, like the ones `export` writes, doesn't
+/// make a section.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error (which says which line or row it's on) if a todo can't
+/// be understood, or if there aren't any.
+pub fn parse(text: &str, format: ImportFormat, project: &Project) -> Result<Vec<NewTodo>> {
+    let todos = match format {
+        ImportFormat::Markdown => parse_markdown(text, &project.name)?,
+        ImportFormat::Csv => parse_csv(text)?,
+        ImportFormat::Todotxt => parse_todotxt(text, &project.name)?,
+    };
+    if todos.is_empty() {
+        return Err(InvalidInput("There aren't any todos to import.".to_string()).into());
+    }
+    Ok(todos)
+}
+
+fn parse_markdown(text: &str, project_name: &str) -> Result<Vec<NewTodo>> {
+    let mut todos: Vec<(usize, NewTodo)> = vec![];
+    let mut section = None;
+    // todos under an earlier heading can't be parents
+    let mut section_start = 0;
+
+    for (index, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        let heading = trimmed.trim_start_matches('#');
+        if heading.len() < trimmed.len() && (heading.is_empty() || heading.starts_with(' ')) {
+            let name = heading.trim();
+            section = (!name.is_empty() && !name.eq_ignore_ascii_case(project_name))
+                .then(|| name.to_string());
+            section_start = todos.len();
+            continue;
+        }
+
+        // anything other than headings and list items is left out, like notes
+        let Some(item) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        else {
+            continue;
+        };
+        let (checked, item) = match item.trim_start().split_at_checked(3) {
+            Some(("[ ]", rest)) => (false, rest),
+            Some(("[x]" | "[X]", rest)) => (true, rest),
+            _ => (false, item),
+        };
+        // a checkbox with nothing after it yet
+        if item.trim().is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        let mut todo = parse_words(item)
+            .with_context(|| format!("Couldn't import the todo on line {}.", index + 1))?;
+        todo.checked = checked;
+        todo.section.clone_from(&section);
+        // the nearest todo before this one that's indented less is its parent
+        todo.parent = todos[section_start..]
+            .iter()
+            .rposition(|(parent_indent, _)| *parent_indent < indent)
+            .map(|position| section_start + position);
+        todos.push((indent, todo));
+    }
+
+    Ok(todos.into_iter().map(|(_, todo)| todo).collect())
+}
+
+/// Makes a todo from text with the tags that `export` writes in markdown: `@label`,
+/// `due:2021-10-07`, and `p1` to `p4`.
+fn parse_words(text: &str) -> Result<NewTodo> {
+    let mut todo = NewTodo::default();
+    let mut content = vec![];
+    for word in text.split_whitespace() {
+        if let Some(label) = word.strip_prefix('@').filter(|label| !label.is_empty()) {
+            todo.labels.push(label.to_string());
+        } else if let Some(due) = word.strip_prefix("due:") {
+            todo.due = Some(parse_due(due)?);
+        } else if let Some(priority) = typed_priority(word) {
+            todo.priority = Some(priority);
+        } else {
+            content.push(word);
+        }
+    }
+
+    todo.content = content.join(" ");
+    if todo.content.is_empty() {
+        return Err(InvalidInput("The todo doesn't have any text.".to_string()).into());
+    }
+    Ok(todo)
+}
+
+/// Parses a priority typed like `p1`, but not a plain number, which is more likely to be
+/// part of the text.
+fn typed_priority(word: &str) -> Option<u8> {
+    word.starts_with(['p', 'P'])
+        .then(|| quick_add::parse_priority(word))
+        .flatten()
+}
+
+/// Parses a due date (and time, if there is one) in ISO 8601 format.
+fn parse_due(due: &str) -> Result<Due> {
+    let date = NaiveDate::parse_from_str(due, "%Y-%m-%d")
+        .map(DueDate::Date)
+        .or_else(|_| NaiveDateTime::parse_from_str(due, "%Y-%m-%dT%H:%M:%S").map(DueDate::DateTime))
+        .or_else(|_| NaiveDateTime::parse_from_str(due, "%Y-%m-%d %H:%M:%S").map(DueDate::DateTime))
+        .map_err(|_| {
+            InvalidInput(format!(
+                "'{due}' isn't a date like 2021-10-07 (or 2021-10-07T09:00:00)."
+            ))
+        })?;
+    Ok(Due { date })
+}
+
+fn parse_todotxt(text: &str, project_name: &str) -> Result<Vec<NewTodo>> {
+    let mut todos = vec![];
+    // what the `id:` tags stand for, so that `parent:` tags can point to them
+    let mut ids = HashMap::new();
+
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let todo = parse_todotxt_line(line, project_name, &mut ids, todos.len())
+            .with_context(|| format!("Couldn't import the todo on line {}.", index + 1))?;
+        todos.push(todo);
+    }
+    Ok(todos)
+}
+
+fn parse_todotxt_line(
+    line: &str,
+    project_name: &str,
+    ids: &mut HashMap<String, usize>,
+    index: usize,
+) -> Result<NewTodo> {
+    let mut words = line.split_whitespace().peekable();
+    let checked = words.next_if_eq(&"x").is_some();
+    let mut priority = words
+        .next_if(|word| {
+            !checked
+                && leading_priority(word).is_some_and(|letter| todotxt_priority(letter).is_some())
+        })
+        .and_then(leading_priority);
+    // the dates it was completed and created on, which Todoist keeps track of itself
+    while words
+        .next_if(|word| NaiveDate::parse_from_str(word, "%Y-%m-%d").is_ok())
+        .is_some()
+    {}
+
+    let mut todo = NewTodo {
+        checked,
+        ..NewTodo::default()
+    };
+    let (mut section, mut project) = (None, None);
+    let mut content = vec![];
+    for word in words {
+        let tag = |prefix: &str| {
+            word.strip_prefix(prefix)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.replace('_', " "))
+        };
+        if let Some(name) = tag("+") {
+            project = project.or(Some(name));
+        } else if let Some(name) = tag("section:") {
+            section = Some(name);
+        } else if let Some(label) = word.strip_prefix('@').filter(|label| !label.is_empty()) {
+            todo.labels.push(label.to_string());
+        } else if let Some(due) = word.strip_prefix("due:") {
+            todo.due = Some(parse_due(due)?);
+        } else if let Some(value) = word.strip_prefix("pri:") {
+            priority = Some(value);
+        } else if let Some(id) = word.strip_prefix("id:") {
+            ids.insert(id.to_string(), index);
+        } else if let Some(id) = word.strip_prefix("parent:") {
+            let parent = ids.get(id).ok_or_else(|| {
+                InvalidInput(format!("There's no todo with `id:{id}` before this one."))
+            })?;
+            todo.parent = Some(*parent);
+        } else {
+            content.push(word);
+        }
+    }
+
+    todo.content = content.join(" ");
+    if todo.content.is_empty() {
+        return Err(InvalidInput("The todo doesn't have any text.".to_string()).into());
+    }
+    todo.priority = priority.and_then(todotxt_priority);
+    todo.section =
+        section.or(project.filter(|project| !project.eq_ignore_ascii_case(project_name)));
+    Ok(todo)
+}
+
+/// The letter of a priority at the start of a todo.txt line, which is in brackets, like `(A)`.
+fn leading_priority(word: &str) -> Option<&str> {
+    word.strip_prefix('(')?.strip_suffix(')')
+}
+
+/// Turns a todo.txt priority letter into Todoist's priority. Todoist only has three
+/// priorities above normal, so D and below are normal.
+fn todotxt_priority(letter: &str) -> Option<u8> {
+    match letter {
+        "A" => Some(4),
+        "B" => Some(3),
+        "C" => Some(2),
+        letter if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => Some(1),
+        _ => None,
+    }
+}
+
+fn parse_csv(text: &str) -> Result<Vec<NewTodo>> {
+    let mut rows = csv_rows(text)?.into_iter();
+    let header = rows.next().unwrap_or_default();
+    let column = |name: &str| header.iter().position(|field| field.trim() == name);
+    if column("content").is_none() {
+        return Err(
+            InvalidInput("The first row doesn't have a `content` column.".to_string()).into(),
+        );
+    }
+    let columns = CSV_COLUMNS.map(column);
+
+    let mut todos = vec![];
+    let mut ids = HashMap::new();
+    for (index, row) in rows.enumerate() {
+        let fields = columns.map(|column| {
+            column
+                .and_then(|column| row.get(column))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        });
+        // the header is row 1
+        let todo = parse_csv_row(fields, &ids)
+            .with_context(|| format!("Couldn't import the todo in row {}.", index + 2))?;
+
+        if let [Some(id), ..] = fields {
+            ids.insert(id.to_string(), todos.len());
+        }
+        todos.push(todo);
+    }
+    Ok(todos)
+}
+
+/// The columns that are imported from CSV (the others, like `project`, are left out).
+const CSV_COLUMNS: [&str; 8] = [
+    "id",
+    "parent_id",
+    "section",
+    "content",
+    "due",
+    "priority",
+    "checked",
+    "labels",
+];
+
+/// Makes a todo from the values in a row, in the order of `CSV_COLUMNS`. `ids` are the
+/// todos in the rows before, by the ids in the file.
+fn parse_csv_row(fields: [Option<&str>; 8], ids: &HashMap<String, usize>) -> Result<NewTodo> {
+    let [_, parent_id, section, content, due, priority, checked, labels] = fields;
+
+    let parent = parent_id
+        .map(|id| {
+            ids.get(id).copied().ok_or_else(|| {
+                InvalidInput(format!(
+                    "There's no todo with the id '{id}' before this one."
+                ))
+            })
+        })
+        .transpose()?;
+    let priority = priority
+        .map(|priority| {
+            priority
+                .parse()
+                .ok()
+                .filter(|priority| (1..=4).contains(priority))
+                .ok_or_else(|| {
+                    InvalidInput(format!(
+                        "The priority '{priority}' isn't from 1 (normal) to 4 (urgent)."
+                    ))
+                })
+        })
+        .transpose()?;
+
+    Ok(NewTodo {
+        content: content
+            .ok_or_else(|| InvalidInput("The todo doesn't have any text.".to_string()))?
+            .to_string(),
+        section: section.map(ToString::to_string),
+        parent,
+        due: due.map(parse_due).transpose()?,
+        priority,
+        labels: labels.map_or(vec![], |labels| {
+            labels
+                .split(',')
+                .map(str::trim)
+                .filter(|label| !label.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        }),
+        checked: checked.is_some_and(|checked| checked.eq_ignore_ascii_case("true")),
+    })
+}
+
+/// Splits CSV into rows of values, undoing the quotes around values with commas, quotes, or
+/// line breaks in them. Blank lines are left out.
+fn csv_rows(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut value = String::new();
+    let mut quoted = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.next_if_eq(&'"').is_some() => value.push('"'),
+            (true, '"') => quoted = false,
+            (false, '"') if value.is_empty() => quoted = true,
+            (false, ',') => row.push(std::mem::take(&mut value)),
+            (false, '\n') => {
+                row.push(std::mem::take(&mut value));
+                rows.push(std::mem::take(&mut row));
+            }
+            (false, '\r') => {}
+            (_, c) => value.push(c),
+        }
+    }
+    if quoted {
+        return Err(
+            InvalidInput("A quoted value doesn't have a closing quote.".to_string()).into(),
+        );
+    }
+    if !value.is_empty() || !row.is_empty() {
+        row.push(value);
+        rows.push(row);
+    }
+
+    rows.retain(|row| row.iter().any(|value| !value.is_empty()));
+    Ok(rows)
+}
+
+/// Adds the todos to a project, along with the sections they're in that aren't there yet,
+/// and queues the commands that add them in Todoist: the sections first, then the todos
+/// (parents before their subtasks), and then the ones that complete todos. Returns the
+/// ids of the todos.
+pub fn add_todos(todos: &[NewTodo], project_id: &project::Id, model: &mut Model) -> Vec<ItemId> {
+    let mut section_ids: HashMap<String, section::Id> = HashMap::new();
+    for name in todos.iter().filter_map(|todo| todo.section.as_ref()) {
+        if section_ids.contains_key(&name.to_lowercase()) {
+            continue;
+        }
+        let existing = model
+            .sections
+            .iter()
+            .filter(|section| section.project_id == *project_id)
+            .find(|section| section.name.eq_ignore_ascii_case(name));
+        let id = if let Some(section) = existing {
+            section.id.clone()
+        } else {
+            let section = Section::new(name, project_id);
+            let id = section.id.clone();
+            model.add_section(section);
+            id
+        };
+        section_ids.insert(name.to_lowercase(), id);
+    }
+
+    let mut items: Vec<Item> = vec![];
+    for todo in todos {
+        let mut item = Item::new(&todo.content, project_id)
+            .due(todo.due.clone())
+            .priority(todo.priority.unwrap_or(1))
+            .labels(todo.labels.clone());
+        match todo.parent.and_then(|parent| items.get(parent)) {
+            Some(parent) => {
+                item.section_id.clone_from(&parent.section_id);
+                item.parent_id = Some(parent.id.clone());
+            }
+            None => {
+                item.section_id = todo
+                    .section
+                    .as_ref()
+                    .and_then(|name| section_ids.get(&name.to_lowercase()))
+                    .cloned();
+            }
+        }
+        items.push(item);
+    }
+
+    let ids: Vec<ItemId> = items.iter().map(|item| item.id.clone()).collect();
+    for item in items {
+        model.add_item(item);
+    }
+    for (id, _) in ids.iter().zip(todos).filter(|(_, todo)| todo.checked) {
+        model.mark_item(id, true);
+    }
+    ids
+}
+
+/// Prints the changes that an import would make (which are the commands it queued), with
+/// subtasks indented under their parents.
+pub fn print_changes(commands: &[Command], model: &Model) {
+    for command in commands {
+        let depth = command
+            .temp_id
+            .as_ref()
+            .and_then(|id| model.items.iter().find(|item| item.id.to_string() == *id))
+            .map_or(0, |item| depth(item, model));
+        println!(
+            "{}{}",
+            "  ".repeat(depth),
+            pending::describe(command, model)
+        );
+    }
+}
+
+fn depth(item: &Item, model: &Model) -> usize {
+    item.parent_id
+        .as_ref()
+        .and_then(|parent_id| model.items.iter().find(|item| item.id == *parent_id))
+        .map_or(0, |parent| depth(parent, model) + 1)
+}
+
+/// Finds the project that todos are being imported into.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if there's no such project.
+pub fn find_project<'a>(name_or_id: &str, model: &'a Model) -> Result<&'a Project> {
+    model
+        .find_project(name_or_id)
+        .ok_or_else(|| InvalidInput(format!("There's no project named '{name_or_id}'.")).into())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn todo(content: &str) -> NewTodo {
+        NewTodo {
+            content: content.to_string(),
+            ..NewTodo::default()
+        }
+    }
+
+    fn date(day: u32) -> Due {
+        Due {
+            date: DueDate::Date(NaiveDate::from_ymd_opt(2021, 10, day).unwrap()),
+        }
+    }
+
+    #[test]
+    fn parse_markdown_checklists() {
+        let text = "# Work\n\
+                    Notes that aren't todos.\n\
+                    - [ ] Plan the offsite p1 due:2021-10-07\n  \
+                      - [x] Book a venue @phone\n  \
+                      - [ ]\n\
+                    \n\
+                    ## Sales calls\n\
+                    * Call Bob\n";
+
+        assert_eq!(
+            parse_markdown(text, "work").unwrap(),
+            [
+                NewTodo {
+                    due: Some(date(7)),
+                    priority: Some(4),
+                    ..todo("Plan the offsite")
+                },
+                NewTodo {
+                    parent: Some(0),
+                    labels: vec!["phone".to_string()],
+                    checked: true,
+                    ..todo("Book a venue")
+                },
+                NewTodo {
+                    section: Some("Sales calls".to_string()),
+                    ..todo("Call Bob")
+                },
+            ]
+        );
+
+        let err = parse_markdown("- [ ] Fine\n- [ ] Not fine due:soon\n", "Work").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn parse_todotxt_lines() {
+        let text = "(A) 2021-10-01 Plan the offsite +Work due:2021-10-07 id:1\n\
+                    x 2021-10-02 Book a venue +Work pri:B parent:1\n\
+                    Call Bob +Sales_calls @phone http://example.com\n";
+
+        assert_eq!(
+            parse_todotxt(text, "Work").unwrap(),
+            [
+                NewTodo {
+                    due: Some(date(7)),
+                    priority: Some(4),
+                    ..todo("Plan the offsite")
+                },
+                NewTodo {
+                    parent: Some(0),
+                    priority: Some(3),
+                    checked: true,
+                    ..todo("Book a venue")
+                },
+                NewTodo {
+                    section: Some("Sales calls".to_string()),
+                    labels: vec!["phone".to_string()],
+                    ..todo("Call Bob http://example.com")
+                },
+            ]
+        );
+
+        assert!(parse_todotxt("Orphan parent:2\n", "Work").is_err());
+
+        // only a bracketed letter at the start is a priority
+        assert_eq!(
+            parse_todotxt("I need to call Bob\nB pri:C\n", "Work").unwrap(),
+            [
+                todo("I need to call Bob"),
+                NewTodo {
+                    priority: Some(2),
+                    ..todo("B")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_csv_rows() {
+        let text = "id,parent_id,section,content,priority,checked,labels\r\n\
+                    1,,,\"Book a venue, with \"\"parking\"\"\",4,false,\n\
+                    2,1,Calls,\"Two\nlines\",,true,\"errands, phone\"\n";
+
+        assert_eq!(
+            parse_csv(text).unwrap(),
+            [
+                NewTodo {
+                    priority: Some(4),
+                    ..todo("Book a venue, with \"parking\"")
+                },
+                NewTodo {
+                    section: Some("Calls".to_string()),
+                    parent: Some(0),
+                    checked: true,
+                    labels: vec!["errands".to_string(), "phone".to_string()],
+                    ..todo("Two\nlines")
+                },
+            ]
+        );
+
+        let err = parse_csv("content,priority\nFine,1\nNot fine,5\n").unwrap_err();
+        assert!(err.to_string().contains("row 3"));
+    }
+
+    #[test]
+    fn add_todos_with_sections_first() {
+        let work = Project::new("Work");
+        let calls = Section::new("Calls", &work.id);
+        let mut model = Model {
+            sections: vec![calls.clone()],
+            projects: vec![work.clone()],
+            ..Default::default()
+        };
+        let todos = [
+            NewTodo {
+                section: Some("Plans".to_string()),
+                ..todo("Plan the offsite")
+            },
+            NewTodo {
+                parent: Some(0),
+                checked: true,
+                ..todo("Book a venue")
+            },
+            NewTodo {
+                section: Some("calls".to_string()),
+                ..todo("Call Bob")
+            },
+        ];
+
+        let ids = add_todos(&todos, &work.id, &mut model);
+
+        let request_types: Vec<&str> = model
+            .commands
+            .iter()
+            .map(|command| command.request_type.as_str())
+            .collect();
+        assert_eq!(
+            request_types,
+            [
+                "section_add",
+                "item_add",
+                "item_add",
+                "item_add",
+                "item_complete"
+            ]
+        );
+        let plans = &model.sections[1];
+        assert_eq!(plans.name, "Plans");
+        assert_eq!(model.items[0].section_id, Some(plans.id.clone()));
+        assert_eq!(model.items[1].section_id, Some(plans.id.clone()));
+        assert_eq!(model.items[1].parent_id, Some(ids[0].clone()));
+        assert_eq!(model.items[2].section_id, Some(calls.id));
+    }
+}
@@ -0,0 +1,183 @@
+use super::{inbox_items, output::InvalidInput};
+use crate::{
+    model::{
+        item::{Id as ItemId, Item},
+        Model,
+    },
+    storage::config_manager::Config,
+};
+use anyhow::Result;
+use std::collections::HashMap;
+
+/// Short ids are never shorter than this, so that they don't get mixed up with numbers
+/// from the `list` output.
+pub const MIN_SHORT_ID_LEN: usize = 4;
+
+/// The shortest prefix of each todo's id (but at least `MIN_SHORT_ID_LEN` long) that no
+/// other todo's id starts with. `ids` has to have the ids of all of the todos.
+#[must_use]
+pub fn short_ids<'a>(ids: impl IntoIterator<Item = &'a ItemId>) -> HashMap<ItemId, String> {
+    let mut ids: Vec<String> = ids.into_iter().map(ToString::to_string).collect();
+    ids.sort_unstable();
+
+    // a prefix only needs to tell an id apart from its neighbours in sorted order
+    let common_prefix_len =
+        |a: &str, b: &str| a.chars().zip(b.chars()).take_while(|(a, b)| a == b).count();
+    ids.iter()
+        .enumerate()
+        .map(|(index, id)| {
+            let before = index
+                .checked_sub(1)
+                .map_or(0, |before| common_prefix_len(&ids[before], id));
+            let after = ids
+                .get(index + 1)
+                .map_or(0, |after| common_prefix_len(after, id));
+            let len = (before.max(after) + 1).max(MIN_SHORT_ID_LEN);
+            let short_id: String = id.chars().take(len).collect();
+            (id.as_str().into(), short_id)
+        })
+        .collect()
+}
+
+/// Finds the todo that the user means by `reference`, which is tried as (in order):
+/// - its id
+/// - the start of its id (at least `MIN_SHORT_ID_LEN` characters of it)
+/// - its number in the inbox that `list` shows
+/// - part of its text (ignoring case), among the todos that aren't complete
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if no todo matches, or if more than one does (which
+/// lists them so that the user can pick).
+pub fn find_item<'a>(reference: &str, model: &'a Model, config: &Config) -> Result<&'a Item> {
+    if let Some(item) = model
+        .items
+        .iter()
+        .find(|item| item.id.to_string() == reference)
+    {
+        return Ok(item);
+    }
+
+    if reference.chars().count() >= MIN_SHORT_ID_LEN {
+        let matches: Vec<&Item> = model
+            .items
+            .iter()
+            .filter(|item| item.id.to_string().starts_with(reference))
+            .collect();
+        if !matches.is_empty() {
+            return only_match(reference, &matches, model);
+        }
+    }
+
+    if let Ok(number) = reference.parse::<usize>() {
+        let inbox_items = inbox_items(model, config);
+        if let Some(item) = number
+            .checked_sub(1)
+            .and_then(|index| inbox_items.get(index))
+        {
+            return Ok(item);
+        }
+    }
+
+    let needle = reference.to_lowercase();
+    let matches: Vec<&Item> = model
+        .items
+        .iter()
+        .filter(|item| !item.checked && item.content.to_lowercase().contains(&needle))
+        .collect();
+    if matches.is_empty() {
+        return Err(InvalidInput(format!(
+            "There's no todo matching '{reference}'. Pass its id, part of its text, or its \
+             number in the `list` output."
+        ))
+        .into());
+    }
+    only_match(reference, &matches, model)
+}
+
+fn only_match<'a>(reference: &str, matches: &[&'a Item], model: &Model) -> Result<&'a Item> {
+    if let [item] = matches {
+        return Ok(item);
+    }
+
+    let short_ids = short_ids(model.items.iter().map(|item| &item.id));
+    let candidates: Vec<String> = matches
+        .iter()
+        .map(|item| {
+            let project = model
+                .project_with_id(&item.project_id)
+                .map_or("", |project| project.name.as_str());
+            format!(
+                "  {}  {}  ({project})",
+                short_ids.get(&item.id).map_or("", String::as_str),
+                item.content
+            )
+        })
+        .collect();
+    Err(InvalidInput(format!(
+        "'{reference}' matches more than one todo. Pass the id of the one you mean:\n{}",
+        candidates.join("\n")
+    ))
+    .into())
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::model::user::User;
+
+    fn item(id: &str, content: &str) -> Item {
+        Item {
+            id: id.into(),
+            ..Item::new(content, "INBOX")
+        }
+    }
+
+    fn model() -> Model {
+        Model {
+            user: User {
+                full_name: "Drew".to_string(),
+                inbox_project_id: "INBOX".into(),
+            },
+            items: vec![
+                item("2995104339", "Do a barrel roll!"),
+                item("2995104340", "Use the boost to get through!"),
+                item("6Jf8VQXxpwv56VQ7", "Roll out"),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shorten_ids_until_they_are_unique() {
+        let short_ids = short_ids(model().items.iter().map(|item| &item.id));
+
+        assert_eq!(short_ids[&"2995104339".into()], "299510433");
+        assert_eq!(short_ids[&"2995104340".into()], "299510434");
+        assert_eq!(short_ids[&"6Jf8VQXxpwv56VQ7".into()], "6Jf8");
+    }
+
+    #[test]
+    fn find_items_by_id_number_or_text() {
+        let model = model();
+        let config = Config::default();
+        let find = |reference| find_item(reference, &model, &config).map(|item| &item.content);
+
+        assert_eq!(find("2995104340").unwrap(), "Use the boost to get through!");
+        assert_eq!(find("6Jf8").unwrap(), "Roll out");
+        assert_eq!(find("1").unwrap(), "Do a barrel roll!");
+        assert_eq!(find("BOOST").unwrap(), "Use the boost to get through!");
+
+        let err = find("roll").unwrap_err().to_string();
+        assert!(err.contains("more than one todo"));
+        assert!(err.contains("  299510433  Do a barrel roll!"));
+        assert!(err.contains("  6Jf8  Roll out"));
+
+        let err = find("299510").unwrap_err().to_string();
+        assert!(err.contains("more than one todo"));
+
+        assert!(find("nothing like this").is_err());
+    }
+}
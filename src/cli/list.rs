@@ -0,0 +1,338 @@
+use super::output::InvalidInput;
+use crate::{
+    model::{
+        due_date::{Due, DueDate},
+        item::{Id as ItemId, Item},
+        project::{Id as ProjectId, Project},
+        section::Section,
+        Model,
+    },
+    storage::{
+        config_manager::{Config, SortOrder},
+        model_store::ItemFilter,
+    },
+};
+use anyhow::{anyhow, Result};
+use chrono::{NaiveDate, NaiveDateTime, Weekday};
+use clap::{Args, ValueEnum};
+
+/// Which due dates `list --due` keeps.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DueFilter {
+    /// Due today
+    Today,
+
+    /// Due before today (or before now, for todos with a time)
+    Overdue,
+
+    /// Due this week (weeks start on the day from the `week_start` setting)
+    Week,
+}
+
+impl DueFilter {
+    #[must_use]
+    pub fn matches(self, due: Option<&Due>, now: NaiveDateTime, week_start: Weekday) -> bool {
+        let Some(due) = due else {
+            return false;
+        };
+        let today = now.date();
+        let date = match due.date {
+            DueDate::Date(date) => date,
+            DueDate::DateTime(datetime) => datetime.date(),
+        };
+
+        match self {
+            DueFilter::Today | DueFilter::Week => {
+                let (first_day, last_day) = self.days(today, week_start);
+                first_day.is_none_or(|first_day| first_day <= date) && date <= last_day
+            }
+            DueFilter::Overdue => match due.date {
+                DueDate::Date(date) => date < today,
+                DueDate::DateTime(datetime) => datetime < now,
+            },
+        }
+    }
+
+    /// The first (if there is one) and last days that the todos this keeps can be due on.
+    #[must_use]
+    pub fn days(self, today: NaiveDate, week_start: Weekday) -> (Option<NaiveDate>, NaiveDate) {
+        match self {
+            DueFilter::Today => (Some(today), today),
+            DueFilter::Overdue => (None, today),
+            DueFilter::Week => {
+                let week = today.week(week_start);
+                (Some(week.first_day()), week.last_day())
+            }
+        }
+    }
+}
+
+/// What `list` shows.
+#[derive(Args, Debug, Clone, Default)]
+pub struct Query {
+    /// List the todos in this project (by name or id) instead of the inbox
+    #[arg(long, short)]
+    pub project: Option<String>,
+
+    /// Also list the todos in the project's subprojects (and theirs)
+    #[arg(long, short = 'r')]
+    pub subprojects: bool,
+
+    /// Only list the todos in this section (by name or id)
+    #[arg(long, short)]
+    pub section: Option<String>,
+
+    /// List the todos in every project
+    #[arg(long, short, conflicts_with_all = ["project", "subprojects"])]
+    pub all: bool,
+
+    /// Only list the todos that are due today, overdue, or due this week
+    #[arg(long, short, value_enum)]
+    pub due: Option<DueFilter>,
+
+    /// Also list completed todos (syncing clears them out, so only the ones completed since
+    /// the last sync)
+    #[arg(long, short)]
+    pub completed: bool,
+}
+
+impl Query {
+    /// Whether this is the query for a plain `list`, which is just the inbox.
+    #[must_use]
+    pub fn is_inbox(&self) -> bool {
+        self.project.is_none()
+            && self.section.is_none()
+            && !self.all
+            && self.due.is_none()
+            && !self.completed
+    }
+}
+
+/// The todos listed in one section of a project (or the ones in no section).
+#[derive(Debug)]
+pub struct Group<'a> {
+    pub project: &'a Project,
+    pub section: Option<&'a Section>,
+
+    /// The todos in tree order, with how deeply each one is nested
+    pub items: Vec<(usize, &'a Item)>,
+}
+
+impl Group<'_> {
+    /// The project name, followed by the section name if there is one.
+    #[must_use]
+    pub fn title(&self) -> String {
+        match self.section {
+            Some(section) => format!("{} / {}", self.project.name, section.name),
+            None => self.project.name.clone(),
+        }
+    }
+}
+
+/// Finds the todos that a query asks for, grouped by project and section. Subtasks
+/// follow their parents; if a parent isn't listed, its subtasks take its place.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if the project or section in the query can't be found.
+pub fn find_items<'a>(
+    model: &'a Model,
+    config: &Config,
+    query: &Query,
+    now: NaiveDateTime,
+) -> Result<Vec<Group<'a>>> {
+    let projects = listed_projects(model, query)?;
+
+    let mut groups = vec![];
+    let mut found_section = false;
+    for project in projects {
+        for (section, items) in model.sections_and_items_in_project(&project.id) {
+            if let Some(name_or_id) = &query.section {
+                let matches = section.is_some_and(|section| {
+                    section.id.to_string() == *name_or_id
+                        || section.name.eq_ignore_ascii_case(name_or_id)
+                });
+                if !matches {
+                    continue;
+                }
+                found_section = true;
+            }
+
+            let listed: Vec<&Item> = items
+                .iter()
+                .copied()
+                .filter(|item| query.completed || config.show_completed || !item.checked)
+                .filter(|item| {
+                    query
+                        .due
+                        .is_none_or(|due| due.matches(item.due.as_ref(), now, config.week_start))
+                })
+                .collect();
+            groups.push(Group {
+                project,
+                section,
+                items: item_tree(&listed, &items, config.sort_order),
+            });
+        }
+    }
+
+    if let (Some(name_or_id), false) = (&query.section, found_section) {
+        return Err(InvalidInput(format!("There's no section named '{name_or_id}' here.")).into());
+    }
+
+    Ok(groups)
+}
+
+/// Which todos need to be read for a query, so that a store that can look them up doesn't
+/// have to read the rest. It can pick out more todos than the query lists, but not fewer.
+#[must_use]
+pub fn item_filter(
+    model: &Model,
+    query: &Query,
+    config: &Config,
+    now: NaiveDateTime,
+) -> ItemFilter {
+    let project_ids = if query.all {
+        None
+    } else {
+        // a project that can't be found is reported when the todos are listed
+        let projects = listed_projects(model, query).unwrap_or_default();
+        let mut project_ids: Vec<ProjectId> =
+            projects.iter().map(|project| project.id.clone()).collect();
+        // the plain inbox list doesn't need the inbox project to have been synced
+        if query.project.is_none() {
+            project_ids.push(model.user.inbox_project_id.clone());
+        }
+        Some(project_ids)
+    };
+    let section_ids = query.section.as_ref().map(|name_or_id| {
+        model
+            .sections
+            .iter()
+            .filter(|section| {
+                project_ids
+                    .as_ref()
+                    .is_none_or(|project_ids| project_ids.contains(&section.project_id))
+            })
+            .filter(|section| {
+                section.id.to_string() == *name_or_id
+                    || section.name.eq_ignore_ascii_case(name_or_id)
+            })
+            .map(|section| section.id.clone())
+            .collect()
+    });
+    let (due_from, due_until) = query.due.map_or((None, None), |due| {
+        let (first_day, last_day) = due.days(now.date(), config.week_start);
+        (first_day, Some(last_day))
+    });
+
+    ItemFilter {
+        project_ids,
+        section_ids,
+        due_from,
+        due_until,
+    }
+}
+
+/// The projects that a query lists the todos in.
+fn listed_projects<'a>(model: &'a Model, query: &Query) -> Result<Vec<&'a Project>> {
+    if query.all {
+        return Ok(project_tree(model, None));
+    }
+
+    let project = match &query.project {
+        Some(name_or_id) => model
+            .find_project(name_or_id)
+            .ok_or_else(|| InvalidInput(format!("There's no project named '{name_or_id}'.")))?,
+        None => model
+            .project_with_id(&model.user.inbox_project_id)
+            .ok_or_else(|| anyhow!("The inbox hasn't been synced yet."))?,
+    };
+    let mut projects = vec![project];
+    if query.subprojects {
+        projects.extend(project_tree(model, Some(&project.id)));
+    }
+    Ok(projects)
+}
+
+/// Sorts todos (that are next to each other in a list or tree) in the order from the
+/// `sort_order` setting.
+pub fn sort_items(items: &mut [&Item], sort_order: SortOrder) {
+    match sort_order {
+        SortOrder::Manual => items.sort_by_key(|item| item.child_order),
+        SortOrder::Due => items.sort_by_key(|item| {
+            // items without a due date go last
+            item.due
+                .as_ref()
+                .map_or((true, None), |due| (false, Some(due.date.clone())))
+        }),
+        SortOrder::Alphabetical => items.sort_by_key(|item| item.content.to_lowercase()),
+    }
+}
+
+/// The descendants of a project (or all projects, if there's no parent) in the order the
+/// TUI shows them, with the inbox first.
+fn project_tree<'a>(model: &'a Model, parent_id: Option<&ProjectId>) -> Vec<&'a Project> {
+    let mut children: Vec<&Project> = model
+        .projects
+        .iter()
+        .filter(|project| project.parent_id.as_ref() == parent_id)
+        .collect();
+    children.sort_by_key(|project| (!project.inbox_project, project.child_order));
+
+    children
+        .into_iter()
+        .flat_map(|project| std::iter::once(project).chain(project_tree(model, Some(&project.id))))
+        .collect()
+}
+
+/// Puts the listed todos in tree order, each paired with its depth among the listed todos.
+/// `all_items` are all of the todos they're listed from, so that ancestors can be found.
+fn item_tree<'a>(
+    listed: &[&'a Item],
+    all_items: &[&Item],
+    sort_order: SortOrder,
+) -> Vec<(usize, &'a Item)> {
+    // the nearest ancestor that's also listed stands in for the parent
+    let listed_parent = |item: &Item| -> Option<ItemId> {
+        let mut parent_id = item.parent_id.as_ref();
+        while let Some(id) = parent_id {
+            if listed.iter().any(|item| item.id == *id) {
+                return Some(id.clone());
+            }
+            parent_id = all_items
+                .iter()
+                .find(|item| item.id == *id)
+                .and_then(|parent| parent.parent_id.as_ref());
+        }
+        None
+    };
+    let parents: Vec<(&Item, Option<ItemId>)> = listed
+        .iter()
+        .map(|item| (*item, listed_parent(item)))
+        .collect();
+
+    let mut tree = vec![];
+    add_children(&parents, None, 0, sort_order, &mut tree);
+    tree
+}
+
+fn add_children<'a>(
+    parents: &[(&'a Item, Option<ItemId>)],
+    parent_id: Option<&ItemId>,
+    depth: usize,
+    sort_order: SortOrder,
+    tree: &mut Vec<(usize, &'a Item)>,
+) {
+    let mut children: Vec<&Item> = parents
+        .iter()
+        .filter(|(_, parent)| parent.as_ref() == parent_id)
+        .map(|(item, _)| *item)
+        .collect();
+    sort_items(&mut children, sort_order);
+
+    for child in children {
+        tree.push((depth, child));
+        add_children(parents, Some(&child.id), depth + 1, sort_order, tree);
+    }
+}
@@ -0,0 +1,406 @@
+use crate::model::{due_date::DueDate, item::Item, Model};
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    fmt,
+    io::{self, Write},
+    process::ExitCode,
+};
+
+/// How commands print their results.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Messages for people to read
+    #[default]
+    Text,
+
+    /// A JSON array of records, or a single object for commands that act on one todo
+    Json,
+
+    /// One JSON object per line
+    Ndjson,
+
+    /// Tab-separated values, with a header line
+    Tsv,
+
+    /// Lined-up columns, with a header line
+    Table,
+}
+
+impl OutputFormat {
+    /// The format that records are printed in, or `None` for text, which commands
+    /// print their own way.
+    #[must_use]
+    pub fn records(self) -> Option<RecordFormat> {
+        match self {
+            Self::Text => None,
+            Self::Json => Some(RecordFormat::Json),
+            Self::Ndjson => Some(RecordFormat::Ndjson),
+            Self::Tsv => Some(RecordFormat::Tsv),
+            Self::Table => Some(RecordFormat::Table),
+        }
+    }
+}
+
+/// The output formats that print records, which is all of them but text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecordFormat {
+    Json,
+    Ndjson,
+    Tsv,
+    Table,
+}
+
+/// Something that commands print, with fields that scripts can rely on.
+pub trait Record: Serialize {
+    /// The names of the fields, in the order they're printed in tables
+    const FIELDS: &'static [&'static str];
+
+    /// The values of the fields as text, in the same order as `FIELDS`.
+    fn values(&self) -> Vec<String>;
+}
+
+/// A todo, with its project and section given by name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ItemRecord {
+    pub id: String,
+    pub content: String,
+    pub project: String,
+    pub section: Option<String>,
+
+    /// The due date (and time, if there is one) in ISO 8601 format
+    pub due: Option<String>,
+
+    /// From 1 (normal) to 4 (urgent), like Todoist's API
+    pub priority: u8,
+    pub checked: bool,
+
+    /// The id of the todo this is a subtask of
+    pub parent_id: Option<String>,
+    pub labels: Vec<String>,
+}
+
+impl ItemRecord {
+    #[must_use]
+    pub fn new(item: &Item, model: &Model) -> Self {
+        let project = model.project_with_id(&item.project_id).map_or_else(
+            || item.project_id.to_string(),
+            |project| project.name.clone(),
+        );
+        let section = item.section_id.as_ref().map(|section_id| {
+            model
+                .sections
+                .iter()
+                .find(|section| section.id == *section_id)
+                .map_or_else(|| section_id.to_string(), |section| section.name.clone())
+        });
+
+        Self {
+            id: item.id.to_string(),
+            content: item.content.clone(),
+            project,
+            section,
+            due: item.due.as_ref().map(|due| match due.date {
+                DueDate::Date(date) => date.to_string(),
+                DueDate::DateTime(datetime) => datetime.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            }),
+            priority: item.priority,
+            checked: item.checked,
+            parent_id: item.parent_id.as_ref().map(ToString::to_string),
+            labels: item.labels.clone(),
+        }
+    }
+}
+
+impl Record for ItemRecord {
+    const FIELDS: &'static [&'static str] = &[
+        "id",
+        "content",
+        "project",
+        "section",
+        "due",
+        "priority",
+        "checked",
+        "parent_id",
+        "labels",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.id.clone(),
+            self.content.clone(),
+            self.project.clone(),
+            self.section.clone().unwrap_or_default(),
+            self.due.clone().unwrap_or_default(),
+            self.priority.to_string(),
+            self.checked.to_string(),
+            self.parent_id.clone().unwrap_or_default(),
+            self.labels.join(","),
+        ]
+    }
+}
+
+/// What happened during a sync.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyncRecord {
+    pub full_sync: bool,
+
+    /// How many local changes were sent to the server
+    pub commands_sent: usize,
+
+    /// How many local changes the server didn't accept, which are kept to try again
+    pub commands_pending: usize,
+    pub projects: usize,
+    pub items: usize,
+}
+
+impl Record for SyncRecord {
+    const FIELDS: &'static [&'static str] = &[
+        "full_sync",
+        "commands_sent",
+        "commands_pending",
+        "projects",
+        "items",
+    ];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.full_sync.to_string(),
+            self.commands_sent.to_string(),
+            self.commands_pending.to_string(),
+            self.projects.to_string(),
+            self.items.to_string(),
+        ]
+    }
+}
+
+/// Prints a list of records to stdout.
+///
+/// # Errors
+///
+/// Returns an error if the records can't be written.
+pub fn print_records<R: Record>(format: RecordFormat, records: &[R]) -> Result<()> {
+    write_records(&mut io::stdout().lock(), format, records, false)
+}
+
+/// Prints the one record that a command produced to stdout. It's printed as an object
+/// rather than an array in JSON.
+///
+/// # Errors
+///
+/// Returns an error if the record can't be written.
+pub fn print_record<R: Record>(format: RecordFormat, record: R) -> Result<()> {
+    write_records(&mut io::stdout().lock(), format, &[record], true)
+}
+
+fn write_records<R: Record>(
+    out: &mut impl Write,
+    format: RecordFormat,
+    records: &[R],
+    single: bool,
+) -> Result<()> {
+    match format {
+        RecordFormat::Json => {
+            match records {
+                [record] if single => serde_json::to_writer_pretty(&mut *out, record)?,
+                records => serde_json::to_writer_pretty(&mut *out, records)?,
+            }
+            writeln!(out)?;
+        }
+        RecordFormat::Ndjson => {
+            for record in records {
+                serde_json::to_writer(&mut *out, record)?;
+                writeln!(out)?;
+            }
+        }
+        RecordFormat::Tsv => {
+            writeln!(out, "{}", R::FIELDS.join("\t"))?;
+            for record in records {
+                let values: Vec<String> = record.values().iter().map(|v| escape_tsv(v)).collect();
+                writeln!(out, "{}", values.join("\t"))?;
+            }
+        }
+        RecordFormat::Table => {
+            let rows: Vec<Vec<String>> =
+                std::iter::once(R::FIELDS.iter().map(|field| field.to_uppercase()).collect())
+                    .chain(records.iter().map(Record::values))
+                    .collect();
+            let widths: Vec<usize> = (0..R::FIELDS.len())
+                .map(|column| {
+                    rows.iter()
+                        .map(|row| row[column].chars().count())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+
+            for row in rows {
+                let line: Vec<String> = row
+                    .iter()
+                    .zip(&widths)
+                    .map(|(value, width)| format!("{value:<width$}"))
+                    .collect();
+                writeln!(out, "{}", line.join("  ").trim_end())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Tabs and newlines would break up the rows and columns, so they're escaped like
+/// `\t` and `\n` (and backslashes are doubled so that it can be undone).
+fn escape_tsv(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+/// Something the user asked for doesn't make sense, like a todo number that isn't in the list.
+#[derive(Debug)]
+pub struct InvalidInput(pub String);
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for InvalidInput {}
+
+/// Added as context to errors from the Todoist server (or from trying to reach it).
+#[derive(Debug)]
+pub struct SyncFailed;
+
+impl fmt::Display for SyncFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Could not sync with the Todoist server.")
+    }
+}
+
+/// The exit codes that scripts can rely on. (Arguments that can't be parsed at all exit
+/// with `InvalidInput` as well.)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    Success = 0,
+    Failure = 1,
+    InvalidInput = 2,
+    SyncFailed = 3,
+}
+
+impl ExitStatus {
+    #[must_use]
+    pub fn of(err: &anyhow::Error) -> Self {
+        if err.downcast_ref::<InvalidInput>().is_some() {
+            Self::InvalidInput
+        } else if err.downcast_ref::<SyncFailed>().is_some() {
+            Self::SyncFailed
+        } else {
+            Self::Failure
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitCode {
+    fn from(status: ExitStatus) -> Self {
+        Self::from(status as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::model::{due_date::Due, project::Project};
+    use chrono::NaiveDate;
+
+    fn record(content: &str) -> ItemRecord {
+        ItemRecord {
+            id: "123".to_string(),
+            content: content.to_string(),
+            project: "Inbox".to_string(),
+            section: None,
+            due: Some("2021-10-07".to_string()),
+            priority: 4,
+            checked: false,
+            parent_id: None,
+            labels: vec!["errands".to_string(), "phone".to_string()],
+        }
+    }
+
+    fn write(format: RecordFormat, records: &[ItemRecord], single: bool) -> String {
+        let mut out = vec![];
+        write_records(&mut out, format, records, single).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn due_time_in_iso_format() {
+        let work = Project::new("Work");
+        let item = Item::new("Call Bob", &work.id).due(Some(Due {
+            date: DueDate::DateTime(
+                NaiveDate::from_ymd_opt(2021, 10, 7)
+                    .unwrap()
+                    .and_hms_opt(9, 30, 0)
+                    .unwrap(),
+            ),
+        }));
+        let model = Model {
+            projects: vec![work],
+            ..Default::default()
+        };
+
+        let record = ItemRecord::new(&item, &model);
+
+        assert_eq!(record.due.as_deref(), Some("2021-10-07T09:30:00"));
+        assert_eq!(record.project, "Work");
+    }
+
+    #[test]
+    fn write_json_and_ndjson() {
+        let records = [record("One"), record("Two")];
+
+        let json: serde_json::Value =
+            serde_json::from_str(&write(RecordFormat::Json, &records, false)).unwrap();
+        assert_eq!(json[1]["content"], "Two");
+        assert_eq!(json[0]["section"], serde_json::Value::Null);
+
+        let single: serde_json::Value =
+            serde_json::from_str(&write(RecordFormat::Json, &records[..1], true)).unwrap();
+        assert_eq!(single["priority"], 4);
+
+        let ndjson = write(RecordFormat::Ndjson, &records, false);
+        assert_eq!(ndjson.lines().count(), 2);
+        assert!(ndjson.starts_with(r#"{"id":"123","content":"One","#));
+    }
+
+    #[test]
+    fn write_tsv_with_escapes() {
+        let tsv = write(RecordFormat::Tsv, &[record("Tab\there")], false);
+
+        assert_eq!(
+            tsv,
+            "id\tcontent\tproject\tsection\tdue\tpriority\tchecked\tparent_id\tlabels\n\
+             123\tTab\\there\tInbox\t\t2021-10-07\t4\tfalse\t\terrands,phone\n"
+        );
+    }
+
+    #[test]
+    fn write_table_in_columns() {
+        let table = write(RecordFormat::Table, &[record("A longer todo")], false);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(
+            lines[0],
+            "ID   CONTENT        PROJECT  SECTION  DUE         PRIORITY  CHECKED  PARENT_ID  LABELS"
+        );
+        assert_eq!(
+            lines[1],
+            "123  A longer todo  Inbox             2021-10-07  4         false               errands,phone"
+        );
+    }
+}
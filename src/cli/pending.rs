@@ -0,0 +1,170 @@
+use super::output::{self, InvalidInput, OutputFormat, Record};
+use crate::{
+    model::{
+        command::{
+            AddSectionArgs, Args, Command, CompleteItemArgs, UncompleteItemArgs, UpdateItemArgs,
+            UpdateProjectArgs,
+        },
+        item, project, Model,
+    },
+    storage::model_store::ModelStore,
+};
+use anyhow::Result;
+use clap::Subcommand;
+use serde::Serialize;
+
+#[derive(Subcommand, Clone)]
+pub enum PendingCommand {
+    /// Discard a change that hasn't been synced yet, and undo it here
+    #[command(name = "drop")]
+    Drop {
+        /// The change's number in the `pending` list
+        number: usize,
+    },
+}
+
+/// A change that hasn't been synced yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PendingRecord {
+    /// Its place in the queue, starting from 1
+    pub number: usize,
+
+    /// What the Sync API calls it, eg. `item_add`
+    #[serde(rename = "type")]
+    pub request_type: String,
+
+    pub description: String,
+}
+
+impl PendingRecord {
+    #[must_use]
+    pub fn new(index: usize, command: &Command, model: &Model) -> Self {
+        Self {
+            number: index + 1,
+            request_type: command.request_type.clone(),
+            description: describe(command, model),
+        }
+    }
+}
+
+impl Record for PendingRecord {
+    const FIELDS: &'static [&'static str] = &["number", "type", "description"];
+
+    fn values(&self) -> Vec<String> {
+        vec![
+            self.number.to_string(),
+            self.request_type.clone(),
+            self.description.clone(),
+        ]
+    }
+}
+
+/// Lists the changes that haven't been synced yet, or drops one of them.
+///
+/// # Errors
+///
+/// Returns an `InvalidInput` error if there's no change with the number to drop, or an
+/// error if the model can't be read or written.
+pub fn handle_pending_command(
+    command: Option<&PendingCommand>,
+    format: OutputFormat,
+    model_store: &dyn ModelStore,
+) -> Result<()> {
+    let Some(PendingCommand::Drop { number }) = command else {
+        let model = model_store.read_model()?;
+        return print_pending(format, &model);
+    };
+
+    let _lock = model_store.lock()?;
+    let mut model = model_store.read_model()?;
+    let record = number
+        .checked_sub(1)
+        .and_then(|index| {
+            Some(PendingRecord::new(
+                index,
+                model.commands.get(index)?,
+                &model,
+            ))
+        })
+        .ok_or_else(|| {
+            InvalidInput(format!(
+                "There's no pending change number {number}. `todoist-tui pending` lists them."
+            ))
+        })?;
+    model.drop_command(record.number - 1);
+    model_store.write_model(&model)?;
+
+    if let Some(format) = format.records() {
+        output::print_record(format, record)
+    } else {
+        println!("Dropped: {}.", record.description);
+        Ok(())
+    }
+}
+
+fn print_pending(format: OutputFormat, model: &Model) -> Result<()> {
+    let records: Vec<PendingRecord> = model
+        .commands
+        .iter()
+        .enumerate()
+        .map(|(index, command)| PendingRecord::new(index, command, model))
+        .collect();
+
+    if let Some(format) = format.records() {
+        return output::print_records(format, &records);
+    }
+    if records.is_empty() {
+        println!("There are no changes waiting to be synced.");
+    }
+    for record in records {
+        println!("[{}] {}", record.number, record.description);
+    }
+    Ok(())
+}
+
+/// Says what a command does, eg. "Complete 'Do a barrel roll!'".
+#[must_use]
+pub fn describe(command: &Command, model: &Model) -> String {
+    let expand_or_collapse = |collapsed: bool| if collapsed { "Collapse" } else { "Expand" };
+    match &command.args {
+        Args::AddItemCommandArgs(args) => format!(
+            "Add '{}' to {}",
+            args.content,
+            project_name(&args.project_id, model)
+        ),
+        Args::AddSectionCommandArgs(AddSectionArgs { name, project_id }) => format!(
+            "Add the section '{name}' to {}",
+            project_name(project_id, model)
+        ),
+        Args::CompleteItemCommandArgs(CompleteItemArgs { id }) => {
+            format!("Complete '{}'", item_content(id, model))
+        }
+        Args::UncompleteItemCommandArgs(UncompleteItemArgs { id }) => {
+            format!("Uncomplete '{}'", item_content(id, model))
+        }
+        Args::UpdateItemCommandArgs(UpdateItemArgs { id, collapsed }) => format!(
+            "{} the subtasks of '{}'",
+            expand_or_collapse(*collapsed),
+            item_content(id, model)
+        ),
+        Args::UpdateProjectCommandArgs(UpdateProjectArgs { id, collapsed }) => format!(
+            "{} the subprojects of {}",
+            expand_or_collapse(*collapsed),
+            project_name(id, model)
+        ),
+    }
+}
+
+fn item_content(id: &item::Id, model: &Model) -> String {
+    model
+        .items
+        .iter()
+        .find(|item| item.id == *id)
+        .map_or_else(|| id.to_string(), |item| item.content.clone())
+}
+
+fn project_name(id: &project::Id, model: &Model) -> String {
+    model
+        .project_with_id(id)
+        .map_or_else(|| id.to_string(), |project| project.name.clone())
+}
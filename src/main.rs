@@ -2,10 +2,16 @@
 use anyhow::Result;
 use clap::Parser;
 use serde::{Deserialize, Serialize};
+use std::process::ExitCode;
 use todoist_tui::{
-    cli::{self, Args},
+    cli::{self, output::ExitStatus, Args, Command},
     storage::{
-        config_manager::ConfigManager, file_manager::FileManager, model_manager::ModelManager,
+        config_manager::{ConfigManager, StorageBackend},
+        file_manager::FileManager,
+        model_manager::ModelManager,
+        model_store::ModelStore,
+        profile_manager::{ProfileManager, DEFAULT_PROFILE},
+        sqlite_store::SqliteStore,
     },
     sync::client::Client,
     tui,
@@ -17,21 +23,72 @@ struct Config {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitStatus::Success.into(),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitStatus::of(&err).into()
+        }
+    }
+}
+
+async fn run() -> Result<()> {
     let args = Args::parse();
+    if let Some(Command::Completions { shell }) = &args.command {
+        return cli::completions::print_script(*shell);
+    }
 
-    let file_manager = FileManager::init(args.local_dir_override.as_deref())?;
-    let config_manager = ConfigManager::new(&file_manager);
-    let model_manager = ModelManager::new(&file_manager);
+    let data_file_manager = FileManager::init(args.local_dir_override.as_deref())?;
+    let profile_manager = ProfileManager::new(&data_file_manager);
+    if let Some(Command::Profile { command }) = &args.command {
+        return cli::handle_profile_command(command, &profile_manager);
+    }
+
+    let profile = match args.profile.clone() {
+        Some(profile) => profile,
+        None => profile_manager.default_profile()?,
+    };
+    let file_manager = profile_manager.open(&profile)?;
+    let config_manager = ConfigManager::new(&file_manager, &profile)
+        .check_with(tui::check_config)
+        .default_keys_with(tui::default_keys);
+    if let Some(Command::Config { command }) = &args.command {
+        return cli::handle_config_command(command, &config_manager);
+    }
+
+    let mut config = config_manager.read_config()?;
+    if let Some(sync_interval) = args.sync_interval {
+        config.sync_interval = sync_interval;
+    }
+    let model_store: Box<dyn ModelStore> = match config.storage {
+        StorageBackend::Json => Box::new(ModelManager::new(&file_manager)),
+        StorageBackend::Sqlite => Box::new(SqliteStore::open(&file_manager)?),
+    };
 
     let client = config_manager
         .get_api_token()
         .map(|token| Client::new(&token, args.sync_url_override.as_deref()));
 
     if let Some(ref command) = args.command.clone() {
-        cli::handle_command(command, args, model_manager, client, config_manager).await?;
+        cli::handle_command(
+            command,
+            args,
+            model_store.as_ref(),
+            client,
+            config_manager,
+            &config,
+        )
+        .await?;
     } else {
-        tui::run(model_manager, client).await?;
+        // there's no need to point out the profile to people who only have the one
+        tui::run(
+            model_store.as_ref(),
+            client,
+            config,
+            (profile != DEFAULT_PROFILE).then_some(profile.as_str()),
+        )
+        .await?;
     }
 
     Ok(())
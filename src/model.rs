@@ -1,5 +1,8 @@
 use self::{
-    command::{AddItemArgs, Args, Command, CompleteItemArgs},
+    command::{
+        AddItemArgs, AddSectionArgs, Args, Command, CompleteItemArgs, UncompleteItemArgs,
+        UpdateItemArgs, UpdateProjectArgs,
+    },
     due_date::Due,
     item::Item,
     project::Project,
@@ -8,12 +11,14 @@ use self::{
 };
 use crate::sync::{Response, Status};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use uuid::Uuid;
 
 pub mod command;
 pub mod due_date;
 pub mod item;
 pub mod project;
+pub mod quick_add;
 pub mod section;
 pub mod user;
 
@@ -25,31 +30,71 @@ pub struct Model {
     pub sections: Vec<Section>,
     pub user: User,
     pub commands: Vec<Command>,
+
+    /// The uuids of the commands that were sent with a sync that hasn't finished yet, which
+    /// can't be taken back anymore
+    #[serde(skip)]
+    pub commands_in_flight: HashSet<Uuid>,
 }
 
 impl Model {
-    pub fn add_item(&mut self, item: &str, project_id: project::Id, due_date: Option<Due>) {
-        let new_item = Item::new(item, &project_id).due(due_date.clone());
+    /// Adds a new item, along with the command that adds it in Todoist. The item's id is
+    /// used as the temporary id until the server gives it a real one. Like in Todoist, it
+    /// goes after the items it's next to.
+    pub fn add_item(&mut self, mut new_item: Item) {
+        new_item.child_order = self
+            .items
+            .iter()
+            .filter(|item| {
+                item.project_id == new_item.project_id
+                    && item.section_id == new_item.section_id
+                    && item.parent_id == new_item.parent_id
+            })
+            .map(|item| item.child_order + 1)
+            .max()
+            .unwrap_or(new_item.child_order);
 
         self.commands.push(command::Command {
             request_type: "item_add".to_string(),
             temp_id: Some(new_item.id.to_string()),
             uuid: Uuid::new_v4(),
             args: Args::AddItemCommandArgs(AddItemArgs {
-                project_id,
-                content: item.to_string(),
-                due: due_date,
+                project_id: new_item.project_id.clone(),
+                content: new_item.content.clone(),
+                due: new_item.due.clone(),
+                section_id: new_item.section_id.clone(),
+                parent_id: new_item.parent_id.clone(),
+                priority: new_item.priority,
+                labels: new_item.labels.clone(),
             }),
         });
         self.items.push(new_item);
     }
 
+    /// Adds a new section, along with the command that adds it in Todoist. Like with items,
+    /// the section's id is used as the temporary id.
+    pub fn add_section(&mut self, new_section: Section) {
+        self.commands.push(command::Command {
+            request_type: "section_add".to_string(),
+            temp_id: Some(new_section.id.to_string()),
+            uuid: Uuid::new_v4(),
+            args: Args::AddSectionCommandArgs(AddSectionArgs {
+                name: new_section.name.clone(),
+                project_id: new_section.project_id.clone(),
+            }),
+        });
+        self.sections.push(new_section);
+    }
+
     pub fn add_item_to_inbox(&mut self, item: &str, due_date: Option<Due>) {
         let project_id = self.user.inbox_project_id.clone();
-        self.add_item(item, project_id, due_date);
+        self.add_item(Item::new(item, project_id).due(due_date));
     }
 
-    /// Marks an item as complete (or uncomplete) and creates (removes) a corresponding command
+    /// Marks an item as complete (or uncomplete) and creates a corresponding command, unless
+    /// that undoes a pending command that did the opposite, in which case that one is removed.
+    /// A command that's already on its way to the server is left alone, so that it gets undone
+    /// by the new one.
     ///
     /// # Note
     /// This no-ops if an item with the given id does not exist, so check before calling.
@@ -61,29 +106,171 @@ impl Model {
 
         item.mark_complete(complete);
 
-        if complete {
-            // Add a new command
-            self.commands.push(Command {
-                request_type: "item_complete".to_owned(),
-                temp_id: None,
-                uuid: Uuid::new_v4(),
-                args: Args::CompleteItemCommandArgs(CompleteItemArgs {
-                    id: item.id.clone(),
-                }),
-            });
+        let opposite_index = self.commands.iter().position(|command| {
+            let is_opposite = match &command.args {
+                Args::CompleteItemCommandArgs(CompleteItemArgs { id }) => {
+                    !complete && id == item_id
+                }
+                Args::UncompleteItemCommandArgs(UncompleteItemArgs { id }) => {
+                    complete && id == item_id
+                }
+                _ => false,
+            };
+            is_opposite && !self.commands_in_flight.contains(&command.uuid)
+        });
+        if let Some(index) = opposite_index {
+            self.commands.remove(index);
+            return;
+        }
+
+        let id = item_id.clone();
+        let (request_type, args) = if complete {
+            (
+                "item_complete",
+                Args::CompleteItemCommandArgs(CompleteItemArgs { id }),
+            )
         } else {
-            // If there was a pending command to mark this item completed, remove it
-            let cmd_index = self.commands.iter().position(|command| {
-                if let Args::CompleteItemCommandArgs(CompleteItemArgs { ref id }) = command.args {
-                    id == &item.id
-                } else {
-                    false
+            (
+                "item_uncomplete",
+                Args::UncompleteItemCommandArgs(UncompleteItemArgs { id }),
+            )
+        };
+        self.commands.push(Command {
+            request_type: request_type.to_owned(),
+            temp_id: None,
+            uuid: Uuid::new_v4(),
+            args,
+        });
+    }
+
+    /// Sets whether an item's subtasks are collapsed and creates a corresponding command,
+    /// replacing any pending command that did the same.
+    ///
+    /// # Note
+    /// This no-ops if an item with the given id does not exist, so check before calling.
+    pub fn set_item_collapsed(&mut self, item_id: &item::Id, collapsed: bool) {
+        let Some(item) = self.items.iter_mut().find(|item| &item.id == item_id) else {
+            return;
+        };
+        item.collapsed = collapsed;
+
+        self.commands.retain(|command| {
+            if let Args::UpdateItemCommandArgs(UpdateItemArgs { ref id, .. }) = command.args {
+                id != item_id
+            } else {
+                true
+            }
+        });
+        self.commands.push(Command {
+            request_type: "item_update".to_owned(),
+            temp_id: None,
+            uuid: Uuid::new_v4(),
+            args: Args::UpdateItemCommandArgs(UpdateItemArgs {
+                id: item_id.clone(),
+                collapsed,
+            }),
+        });
+    }
+
+    /// Sets whether a project's subprojects are collapsed and creates a corresponding command,
+    /// replacing any pending command that did the same.
+    ///
+    /// # Note
+    /// This no-ops if a project with the given id does not exist, so check before calling.
+    pub fn set_project_collapsed(&mut self, project_id: &project::Id, collapsed: bool) {
+        let Some(project) = self
+            .projects
+            .iter_mut()
+            .find(|project| &project.id == project_id)
+        else {
+            return;
+        };
+        project.collapsed = collapsed;
+
+        self.commands.retain(|command| {
+            if let Args::UpdateProjectCommandArgs(UpdateProjectArgs { ref id, .. }) = command.args {
+                id != project_id
+            } else {
+                true
+            }
+        });
+        self.commands.push(Command {
+            request_type: "project_update".to_owned(),
+            temp_id: None,
+            uuid: Uuid::new_v4(),
+            args: Args::UpdateProjectCommandArgs(UpdateProjectArgs {
+                id: project_id.clone(),
+                collapsed,
+            }),
+        });
+    }
+
+    /// Removes a pending command and undoes its local change as far as that can be done
+    /// here: a todo or section that was added is removed again (along with the todos in it or
+    /// under it, and the pending commands for any of them), and a todo that was completed is
+    /// marked incomplete. Other changes are put right by the next full sync. Returns the
+    /// command, if there was one.
+    pub fn drop_command(&mut self, index: usize) -> Option<Command> {
+        if index >= self.commands.len() {
+            return None;
+        }
+        let command = self.commands.remove(index);
+
+        match (&command.args, &command.temp_id) {
+            (Args::AddItemCommandArgs(_), Some(temp_id)) => {
+                self.drop_items(HashSet::from([item::Id::from(temp_id.as_str())]));
+            }
+            (Args::AddSectionCommandArgs(_), Some(temp_id)) => {
+                let section_id = section::Id::from(temp_id.as_str());
+                self.sections.retain(|section| section.id != section_id);
+                let item_ids = self
+                    .items
+                    .iter()
+                    .filter(|item| item.section_id.as_ref() == Some(&section_id))
+                    .map(|item| item.id.clone())
+                    .collect();
+                self.drop_items(item_ids);
+            }
+            (Args::CompleteItemCommandArgs(CompleteItemArgs { id }), _) => {
+                if let Some(item) = self.items.iter_mut().find(|item| item.id == *id) {
+                    item.mark_complete(false);
+                }
+            }
+            (Args::UncompleteItemCommandArgs(UncompleteItemArgs { id }), _) => {
+                if let Some(item) = self.items.iter_mut().find(|item| item.id == *id) {
+                    item.mark_complete(true);
                 }
-            });
-            if let Some(cmd_index) = cmd_index {
-                self.commands.remove(cmd_index);
             }
+            _ => {}
         }
+
+        Some(command)
+    }
+
+    /// Removes todos along with their subtasks, and the pending commands for any of them.
+    fn drop_items(&mut self, mut dropped_ids: HashSet<item::Id>) {
+        loop {
+            let subtask_ids: Vec<item::Id> = self
+                .items
+                .iter()
+                .filter(|item| {
+                    !dropped_ids.contains(&item.id)
+                        && item
+                            .parent_id
+                            .as_ref()
+                            .is_some_and(|parent_id| dropped_ids.contains(parent_id))
+                })
+                .map(|item| item.id.clone())
+                .collect();
+            if subtask_ids.is_empty() {
+                break;
+            }
+            dropped_ids.extend(subtask_ids);
+        }
+
+        self.items.retain(|item| !dropped_ids.contains(&item.id));
+        self.commands
+            .retain(|command| !is_for_items(command, &dropped_ids));
     }
 
     // TODO: test
@@ -137,6 +324,20 @@ impl Model {
             .collect()
     }
 
+    /// Finds a project by its id, or failing that, by its name (ignoring case).
+    #[must_use]
+    pub fn find_project(&self, name_or_id: &str) -> Option<&Project> {
+        let id = project::Id::from(name_or_id);
+        self.projects
+            .iter()
+            .find(|project| project.id == id)
+            .or_else(|| {
+                self.projects
+                    .iter()
+                    .find(|project| project.name.eq_ignore_ascii_case(name_or_id))
+            })
+    }
+
     // TODO: test
     #[must_use]
     pub fn projects(&self) -> Vec<&Project> {
@@ -159,72 +360,251 @@ impl Model {
         self.projects.iter().find(|project| project.id == *id)
     }
 
-    pub fn update(&mut self, response: Response) {
-        self.sync_token = response.sync_token;
-
-        if let Some(user) = response.user {
-            self.user = user;
+    /// Merges changes that another process made to the stored copy of the model into this one.
+    ///
+    /// Whichever model has the newer sync state becomes the base, and the commands that only
+    /// exist in the other one are re-applied on top of it. The stored model is newer if the
+    /// other process synced since `previous_sync_token` (the stored sync token the last time
+    /// this model was read or merged) and this one hasn't; otherwise this model is kept.
+    ///
+    /// `previously_stored` holds the uuids of the commands that were in the stored model the
+    /// last time this one was read or merged; if one of those is no longer stored, the other
+    /// process already sent it to the server, so it gets dropped.
+    pub fn merge_stored(
+        &mut self,
+        stored: Model,
+        previously_stored: &HashSet<Uuid>,
+        previous_sync_token: &str,
+    ) {
+        if stored.sync_token != previous_sync_token && self.sync_token == previous_sync_token {
+            let mut local = std::mem::replace(self, stored);
+            self.commands_in_flight = std::mem::take(&mut local.commands_in_flight);
+            self.add_commands_from(local, previously_stored);
+            return;
         }
 
-        // replace the list of projects with the list from the response.
-        // FIXME: we need a more nuanced algorithm to update the projects.
-        // just replacing `self.projects` with `response.projects` is no good
-        // because we don't always query all projects
-        // so for now, only replace projects if the incoming projects
-        // list is nonempty
-        if !response.projects.is_empty() {
-            self.projects = response.projects;
+        // the todos and sections that the other process already sent come back with their
+        // real ids on the next sync
+        let (sent, commands): (Vec<Command>, Vec<Command>) = std::mem::take(&mut self.commands)
+            .into_iter()
+            .partition(|command| {
+                previously_stored.contains(&command.uuid)
+                    && !stored
+                        .commands
+                        .iter()
+                        .any(|stored| stored.uuid == command.uuid)
+            });
+        self.commands = commands;
+        for command in sent {
+            let Some(temp_id) = command.temp_id else {
+                continue;
+            };
+            match command.args {
+                Args::AddItemCommandArgs(_) => {
+                    self.items.retain(|item| item.id.to_string() != temp_id);
+                }
+                Args::AddSectionCommandArgs(_) => {
+                    self.sections
+                        .retain(|section| section.id.to_string() != temp_id);
+                }
+                _ => {}
+            }
         }
 
-        // same thing (and same FIXME) with sections
-        if !response.sections.is_empty() {
-            self.sections = response.sections;
-        }
+        self.add_commands_from(stored, previously_stored);
+    }
 
-        if response.full_sync {
-            // if this was a full sync, just replace the set of items
-            self.items = response.items;
-        } else {
-            // use the id mapping from the response to update the ids of the existing items
-            response
-                .temp_id_mapping
-                .into_iter()
-                .for_each(|(temp_id, real_id)| {
-                    // HACK: should we do something else if we don't find a match?
-                    if let Some(matching_item) = self
+    /// Adds the commands from another copy of the model that this one doesn't have yet, along
+    /// with their changes, skipping the ones in `previously_stored`.
+    fn add_commands_from(&mut self, other: Model, previously_stored: &HashSet<Uuid>) {
+        for command in other.commands {
+            if previously_stored.contains(&command.uuid)
+                || self
+                    .commands
+                    .iter()
+                    .any(|stored| stored.uuid == command.uuid)
+            {
+                continue;
+            }
+
+            match command.args {
+                Args::AddItemCommandArgs(_) => {
+                    let local_item = other
                         .items
-                        .iter_mut()
-                        .find(|item| item.id == temp_id.clone().into())
+                        .iter()
+                        .find(|item| command.temp_id.as_ref() == Some(&item.id.to_string()));
+                    if let Some(item) = local_item {
+                        self.items.push(item.clone());
+                    }
+                }
+                Args::AddSectionCommandArgs(_) => {
+                    let local_section = other
+                        .sections
+                        .iter()
+                        .find(|section| command.temp_id.as_ref() == Some(&section.id.to_string()));
+                    if let Some(section) = local_section {
+                        self.sections.push(section.clone());
+                    }
+                }
+                Args::CompleteItemCommandArgs(CompleteItemArgs { ref id }) => {
+                    if let Some(item) = self.items.iter_mut().find(|item| item.id == *id) {
+                        item.mark_complete(true);
+                    }
+                }
+                Args::UncompleteItemCommandArgs(UncompleteItemArgs { ref id }) => {
+                    if let Some(item) = self.items.iter_mut().find(|item| item.id == *id) {
+                        item.mark_complete(false);
+                    }
+                }
+                Args::UpdateItemCommandArgs(UpdateItemArgs { ref id, collapsed }) => {
+                    if let Some(item) = self.items.iter_mut().find(|item| item.id == *id) {
+                        item.collapsed = collapsed;
+                    }
+                }
+                Args::UpdateProjectCommandArgs(UpdateProjectArgs { ref id, collapsed }) => {
+                    if let Some(project) =
+                        self.projects.iter_mut().find(|project| project.id == *id)
                     {
-                        matching_item.id = real_id.into();
+                        project.collapsed = collapsed;
                     }
+                }
+            }
+            self.commands.push(command);
+        }
+    }
+
+    /// Removes the subprojects of removed projects (and theirs), and the sections in removed
+    /// projects, then the items that belong to any of those.
+    fn purge_orphans(
+        &mut self,
+        mut removed_project_ids: Vec<project::Id>,
+        mut removed_section_ids: Vec<section::Id>,
+        removed_item_ids: Vec<item::Id>,
+    ) {
+        // removing a project might orphan its subprojects, so keep going until nothing changes
+        loop {
+            let (orphans, projects): (Vec<Project>, Vec<Project>) =
+                std::mem::take(&mut self.projects)
+                    .into_iter()
+                    .partition(|project| {
+                        project
+                            .parent_id
+                            .as_ref()
+                            .is_some_and(|id| removed_project_ids.contains(id))
+                    });
+            self.projects = projects;
+
+            if orphans.is_empty() {
+                break;
+            }
+            removed_project_ids.extend(orphans.into_iter().map(|project| project.id));
+        }
+
+        let (orphans, sections): (Vec<Section>, Vec<Section>) = std::mem::take(&mut self.sections)
+            .into_iter()
+            .partition(|section| removed_project_ids.contains(&section.project_id));
+        self.sections = sections;
+        removed_section_ids.extend(orphans.into_iter().map(|section| section.id));
+
+        self.purge_orphaned_items(&removed_project_ids, &removed_section_ids, removed_item_ids);
+    }
+
+    /// Removes the items that belong to a project or section that was removed, along with
+    /// all of the descendants of removed items.
+    fn purge_orphaned_items(
+        &mut self,
+        removed_project_ids: &[project::Id],
+        removed_section_ids: &[section::Id],
+        mut removed_item_ids: Vec<item::Id>,
+    ) {
+        // removing an item might orphan its children, so keep going until nothing changes
+        loop {
+            let (orphans, items): (Vec<Item>, Vec<Item>) = std::mem::take(&mut self.items)
+                .into_iter()
+                .partition(|item| {
+                    removed_project_ids.contains(&item.project_id)
+                        || item
+                            .section_id
+                            .as_ref()
+                            .is_some_and(|id| removed_section_ids.contains(id))
+                        || item
+                            .parent_id
+                            .as_ref()
+                            .is_some_and(|id| removed_item_ids.contains(id))
                 });
+            self.items = items;
 
-            // look through the list of items that we received -- add any new items
-            // and update any newly checked items
-            response.items.into_iter().for_each(|incoming_item| {
-                // if the incoming item is checked, see if there's a matching item in the model
-                // and remove it
-                // if the incoming item is unchecked, do the same, but if nothing is found, add this
-                // item to the model
-                let matching_index = self
+            if orphans.is_empty() {
+                break;
+            }
+            removed_item_ids.extend(orphans.into_iter().map(|item| item.id));
+        }
+    }
+
+    pub fn update(&mut self, response: Response) {
+        self.sync_token = response.sync_token;
+        self.commands_in_flight.clear();
+
+        if let Some(user) = response.user {
+            self.user = user;
+        }
+
+        // use the id mapping from the response to update the ids of the existing items
+        // (this needs to happen before merging so that the incoming items match up)
+        response
+            .temp_id_mapping
+            .clone()
+            .into_iter()
+            .for_each(|(temp_id, real_id)| {
+                // HACK: should we do something else if we don't find a match?
+                if let Some(matching_item) = self
                     .items
-                    .iter()
-                    .position(|item| item.id == incoming_item.id);
-
-                match matching_index {
-                    Some(index) => {
-                        if incoming_item.checked {
-                            self.items.remove(index);
-                        } else {
-                            self.items[index] = incoming_item;
-                        }
-                    }
-                    None => self.items.push(incoming_item),
+                    .iter_mut()
+                    .find(|item| item.id == temp_id.clone().into())
+                {
+                    matching_item.id = real_id.into();
+                } else if let Some(matching_section) = self
+                    .sections
+                    .iter_mut()
+                    .find(|section| section.id == temp_id.clone().into())
+                {
+                    matching_section.id = real_id.into();
                 }
             });
+
+        // the server only knows the temporary ids within the request that used them, so the
+        // todos under the ones that were just added, and the commands that haven't been sent
+        // yet, need the real ids too
+        for item in &mut self.items {
+            if let Some(parent_id) = &mut item.parent_id {
+                command::replace_temp_id(parent_id, &response.temp_id_mapping);
+            }
+            if let Some(section_id) = &mut item.section_id {
+                command::replace_temp_id(section_id, &response.temp_id_mapping);
+            }
+        }
+        for command in &mut self.commands {
+            command.args.replace_temp_ids(&response.temp_id_mapping);
         }
 
+        // each resource type is handled separately, since a response won't necessarily
+        // contain all of them
+        let removed_project_ids = response.projects.map_or(vec![], |projects| {
+            merge_resources(&mut self.projects, projects, response.full_sync)
+        });
+
+        let removed_section_ids = response.sections.map_or(vec![], |sections| {
+            merge_resources(&mut self.sections, sections, response.full_sync)
+        });
+
+        let removed_item_ids = response.items.map_or(vec![], |items| {
+            merge_resources(&mut self.items, items, response.full_sync)
+        });
+
+        // the server doesn't necessarily tell us about everything that went away along with
+        // the things above, so clean those up too
+        self.purge_orphans(removed_project_ids, removed_section_ids, removed_item_ids);
+
         // update the command list by removing the commands that succeeded
         if let Some(ref status_map) = response.sync_status {
             self.commands.retain(|command| {
@@ -236,9 +616,103 @@ impl Model {
     }
 }
 
+/// Something that is synced with the Todoist API and can be merged into the model by id.
+trait Resource {
+    type Id: PartialEq + Clone;
+
+    fn id(&self) -> &Self::Id;
+
+    /// Whether the resource should no longer be kept in the model,
+    /// eg. because it was deleted or archived.
+    fn is_removed(&self) -> bool;
+}
+
+impl Resource for Project {
+    type Id = project::Id;
+
+    fn id(&self) -> &Self::Id {
+        &self.id
+    }
+
+    fn is_removed(&self) -> bool {
+        self.is_deleted || self.is_archived
+    }
+}
+
+impl Resource for Section {
+    type Id = section::Id;
+
+    fn id(&self) -> &Self::Id {
+        &self.id
+    }
+
+    fn is_removed(&self) -> bool {
+        self.is_deleted || self.is_archived
+    }
+}
+
+impl Resource for Item {
+    type Id = item::Id;
+
+    fn id(&self) -> &Self::Id {
+        &self.id
+    }
+
+    fn is_removed(&self) -> bool {
+        self.is_deleted || self.checked
+    }
+}
+
+/// Merges resources received from the server into a list of existing resources.
+///
+/// If this was a full sync, the incoming list replaces the existing one. Otherwise the incoming
+/// resources replace the existing ones with matching ids (or get added if they're new), and any
+/// resource that was deleted or archived is removed.
+///
+/// Returns the ids of the incoming resources that were flagged as removed.
+fn merge_resources<R: Resource>(
+    existing: &mut Vec<R>,
+    incoming: Vec<R>,
+    full_sync: bool,
+) -> Vec<R::Id> {
+    let removed_ids = incoming
+        .iter()
+        .filter(|resource| resource.is_removed())
+        .map(|resource| resource.id().clone())
+        .collect();
+
+    if full_sync {
+        *existing = incoming
+            .into_iter()
+            .filter(|resource| !resource.is_removed())
+            .collect();
+        return removed_ids;
+    }
+
+    for resource in incoming {
+        let matching_index = existing
+            .iter()
+            .position(|existing| existing.id() == resource.id());
+
+        match (matching_index, resource.is_removed()) {
+            (Some(index), true) => {
+                existing.remove(index);
+            }
+            (Some(index), false) => existing[index] = resource,
+            (None, false) => existing.push(resource),
+            (None, true) => {}
+        }
+    }
+
+    removed_ids
+}
+
 impl Default for Model {
     fn default() -> Self {
-        let inbox = Project::new("Inbox");
+        let inbox = Project {
+            inbox_project: true,
+            ..Project::new("Inbox")
+        };
         let user = User {
             inbox_project_id: inbox.id.clone(),
             ..Default::default()
@@ -250,16 +724,57 @@ impl Default for Model {
             sections: vec![],
             user,
             commands: vec![],
+            commands_in_flight: HashSet::new(),
         }
     }
 }
 
+/// Whether a command adds or changes one of the given todos.
+fn is_for_items(command: &Command, ids: &HashSet<item::Id>) -> bool {
+    match &command.args {
+        Args::AddItemCommandArgs(_) => command
+            .temp_id
+            .as_ref()
+            .is_some_and(|temp_id| ids.contains(&temp_id.as_str().into())),
+        Args::CompleteItemCommandArgs(CompleteItemArgs { id })
+        | Args::UncompleteItemCommandArgs(UncompleteItemArgs { id })
+        | Args::UpdateItemCommandArgs(UpdateItemArgs { id, .. }) => ids.contains(id),
+        Args::UpdateProjectCommandArgs(_) | Args::AddSectionCommandArgs(_) => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
 
     use super::*;
 
+    #[test]
+    fn read_project_from_todoist() {
+        // trimmed down from the Sync API docs
+        let project: Project = serde_json::from_str(
+            r#"{
+                "id": "6Jf8VQXxpwv56VQ7",
+                "name": "Shopping List",
+                "color": "lime_green",
+                "parent_id": null,
+                "child_order": 1,
+                "collapsed": false,
+                "shared": false,
+                "is_deleted": false,
+                "is_archived": false,
+                "is_favorite": true,
+                "view_style": "board"
+            }"#,
+        )
+        .expect("should parse");
+
+        assert_eq!(project.color, "lime_green");
+        assert!(project.is_favorite);
+        assert_eq!(project.view_style, "board");
+        assert!(!project.inbox_project);
+    }
+
     #[test]
     fn add_item_to_inbox() {
         let mut model = Model::default();
@@ -274,11 +789,60 @@ mod tests {
             Args::AddItemCommandArgs(AddItemArgs {
                 project_id: "INBOX_ID".into(),
                 content: "New item!".to_string(),
-                due: None
+                ..Default::default()
             })
         );
     }
 
+    #[test]
+    fn add_item_after_its_siblings() {
+        let mut model = Model::default();
+        let parent = Item::new("Parent", "INBOX_ID").child_order(3);
+        model.items = vec![
+            Item::new("First", "INBOX_ID").child_order(1),
+            Item::new("Subtask", "INBOX_ID")
+                .parent_id(&parent.id)
+                .child_order(7),
+            Item::new("Elsewhere", "OTHER_ID").child_order(9),
+            parent,
+        ];
+
+        model.add_item(Item::new("New item!", "INBOX_ID"));
+        model.add_item(Item::new("Newer item!", "INBOX_ID"));
+        model.add_item(Item::new("New project!", "NEW_ID"));
+
+        assert_eq!(model.items[4].child_order, 4);
+        assert_eq!(model.items[5].child_order, 5);
+        assert_eq!(model.items[6].child_order, 0);
+    }
+
+    #[test]
+    fn drop_pending_commands() {
+        let mut model = Model::default();
+        let synced = Item::new("Synced", "INBOX_ID");
+        model.items.push(synced.clone());
+        model.mark_item(&synced.id, true);
+        let parent = Item::new("Parent", "INBOX_ID");
+        let subtask = Item::new("Subtask", "INBOX_ID").parent_id(&parent.id);
+        let subtask_id = subtask.id.clone();
+        model.add_item(parent);
+        model.add_item(subtask);
+        model.mark_item(&subtask_id, true);
+
+        // dropping the parent drops everything that was done to its subtask too
+        let dropped = model
+            .drop_command(1)
+            .expect("there should be a command to drop");
+        assert_eq!(dropped.request_type, "item_add");
+        assert_eq!(model.items.len(), 1);
+        assert_eq!(model.commands.len(), 1);
+
+        model.drop_command(0);
+        assert!(!model.items[0].checked);
+        assert!(model.commands.is_empty());
+        assert!(model.drop_command(0).is_none());
+    }
+
     #[test]
     fn mark_item_completed() {
         let mut model = Model::default();
@@ -295,6 +859,181 @@ mod tests {
         );
     }
 
+    #[test]
+    fn uncomplete_item_while_completing_it_is_in_flight() {
+        let mut model = Model::default();
+        let item = Item::new("Item!", "INBOX_ID");
+        let item_id = item.id.clone();
+        model.items.push(item);
+        model.mark_item(&item_id, true);
+        model.commands_in_flight = HashSet::from([model.commands[0].uuid]);
+
+        // the server is going to complete it anyway, so that has to be undone
+        model.mark_item(&item_id, false);
+        assert!(!model.items[0].checked);
+        assert_eq!(model.commands.len(), 2);
+        assert_eq!(model.commands[1].request_type, "item_uncomplete");
+    }
+
+    #[test]
+    fn mark_synced_item_uncompleted() {
+        let mut model = Model::default();
+        let item = Item::new("Item!", "INBOX_ID").checked(true);
+        let item_id = item.id.clone();
+        model.items.push(item);
+
+        model.mark_item(&item_id, false);
+        assert!(!model.items[0].checked);
+        assert_eq!(model.commands[0].request_type, "item_uncomplete");
+        assert_eq!(
+            model.commands[0].args,
+            Args::UncompleteItemCommandArgs(UncompleteItemArgs {
+                id: item_id.clone()
+            })
+        );
+
+        // completing it again just drops the pending command
+        model.mark_item(&item_id, true);
+        assert!(model.items[0].checked);
+        assert!(model.commands.is_empty());
+
+        let json = serde_json::to_string(&Command {
+            request_type: "item_uncomplete".to_owned(),
+            temp_id: None,
+            uuid: Uuid::new_v4(),
+            args: Args::UncompleteItemCommandArgs(UncompleteItemArgs {
+                id: item_id.clone(),
+            }),
+        })
+        .expect("serialize command");
+        let command: Command = serde_json::from_str(&json).expect("deserialize command");
+        assert_eq!(
+            command.args,
+            Args::UncompleteItemCommandArgs(UncompleteItemArgs { id: item_id })
+        );
+    }
+
+    #[test]
+    fn set_item_collapsed_replaces_pending_command() {
+        let mut model = Model::default();
+        let item = Item::new("Item!", "INBOX_ID");
+        let item_id = item.id.clone();
+        model.items.push(item);
+
+        model.set_item_collapsed(&item_id, true);
+        model.set_item_collapsed(&item_id, false);
+
+        assert!(!model.items[0].collapsed);
+        assert_eq!(model.commands.len(), 1);
+        assert_eq!(model.commands[0].request_type, "item_update");
+        assert_eq!(
+            model.commands[0].args,
+            Args::UpdateItemCommandArgs(UpdateItemArgs {
+                id: item_id,
+                collapsed: false
+            })
+        );
+    }
+
+    #[test]
+    fn update_commands_survive_serialization() {
+        let mut model = Model::default();
+        let project_id = model.user.inbox_project_id.clone();
+        let item = Item::new("Item!", &project_id);
+        let item_id = item.id.clone();
+        model.items.push(item);
+
+        model.set_project_collapsed(&project_id, true);
+        model.set_item_collapsed(&item_id, true);
+
+        let json = serde_json::to_string(&model).expect("serialize model");
+        let model: Model = serde_json::from_str(&json).expect("deserialize model");
+        assert_eq!(
+            model.commands[0].args,
+            Args::UpdateProjectCommandArgs(UpdateProjectArgs {
+                id: project_id,
+                collapsed: true
+            })
+        );
+        assert_eq!(
+            model.commands[1].args,
+            Args::UpdateItemCommandArgs(UpdateItemArgs {
+                id: item_id,
+                collapsed: true
+            })
+        );
+    }
+
+    #[test]
+    fn merge_stored_model() {
+        let mut stored = Model::default();
+        stored.add_item_to_inbox("Already sent", None);
+        stored.add_item_to_inbox("Added elsewhere", None);
+        let already_sent = stored.commands.remove(0);
+        stored.sync_token = "synced elsewhere".to_string();
+
+        // this model was loaded before the other process did anything
+        let mut model = Model {
+            items: vec![stored.items[0].clone()],
+            commands: vec![already_sent.clone()],
+            ..Model::default()
+        };
+        let previously_stored = HashSet::from([already_sent.uuid]);
+        model.add_item_to_inbox("Added locally", None);
+
+        model.merge_stored(stored, &previously_stored, "*");
+
+        let contents: Vec<_> = model
+            .items
+            .iter()
+            .map(|item| item.content.as_str())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["Already sent", "Added elsewhere", "Added locally"]
+        );
+        let added: Vec<_> = model
+            .commands
+            .iter()
+            .filter_map(|command| match command.args {
+                Args::AddItemCommandArgs(AddItemArgs { ref content, .. }) => Some(content.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(added, vec!["Added elsewhere", "Added locally"]);
+    }
+
+    #[test]
+    fn merge_stored_model_after_syncing() {
+        let mut stored = Model::default();
+        stored.add_item_to_inbox("Sent elsewhere", None);
+        let sent_elsewhere = stored.commands.remove(0);
+        stored.add_item_to_inbox("Added elsewhere", None);
+
+        // this model synced (without the other process's todos) after it was loaded
+        let mut model = Model {
+            items: vec![
+                stored.items[0].clone(),
+                Item::new("Synced here", "INBOX_ID"),
+            ],
+            commands: vec![sent_elsewhere.clone()],
+            sync_token: "synced here".to_string(),
+            ..Model::default()
+        };
+        let previously_stored = HashSet::from([sent_elsewhere.uuid]);
+
+        model.merge_stored(stored, &previously_stored, "*");
+
+        assert_eq!(model.sync_token, "synced here");
+        let contents: Vec<_> = model
+            .items
+            .iter()
+            .map(|item| item.content.as_str())
+            .collect();
+        assert_eq!(contents, vec!["Synced here", "Added elsewhere"]);
+        assert_eq!(model.commands.len(), 1);
+    }
+
     #[test]
     fn incremental_update_with_updated_todos() {
         let mut model = Model::default();
@@ -313,7 +1052,7 @@ mod tests {
         model.items.push(item1);
         model.items.push(item2);
         let response = Response {
-            items: vec![item1_updated, item2_updated],
+            items: Some(vec![item1_updated, item2_updated]),
             full_sync: false,
             ..Default::default()
         };
@@ -329,7 +1068,7 @@ mod tests {
         let item = Item::new("Item!", "INBOX_ID");
 
         let response = Response {
-            items: vec![item],
+            items: Some(vec![item]),
             full_sync: false,
             ..Default::default()
         };
@@ -351,7 +1090,7 @@ mod tests {
         model.items.push(item);
 
         let response = Response {
-            items: vec![item_updated],
+            items: Some(vec![item_updated]),
             full_sync: false,
             temp_id_mapping: HashMap::from([(item_id.to_string(), "NEW_ITEM_ID".into())]),
             ..Default::default()
@@ -361,4 +1100,235 @@ mod tests {
         assert_eq!(model.items.len(), 1);
         assert_eq!(model.items[0].content, "Item!");
     }
+
+    #[test]
+    fn incremental_update_with_commands_queued_while_syncing() {
+        let mut model = Model::default();
+        let parent = Item::new("Parent", "INBOX_ID");
+        let parent_id = parent.id.clone();
+        model.add_item(parent);
+        let sent = model.commands[0].uuid;
+
+        // these were queued while the todo was being added, so the server hasn't seen them
+        let subtask = Item::new("Subtask", "INBOX_ID").parent_id(&parent_id);
+        let subtask_id = subtask.id.clone();
+        model.add_item(subtask);
+        model.mark_item(&parent_id, true);
+
+        let response = Response {
+            items: Some(vec![Item {
+                id: "REAL_PARENT_ID".into(),
+                ..Item::new("Parent", "INBOX_ID")
+            }]),
+            full_sync: false,
+            sync_status: Some(HashMap::from([(sent, Status::Ok)])),
+            temp_id_mapping: HashMap::from([(parent_id.to_string(), "REAL_PARENT_ID".into())]),
+            ..Default::default()
+        };
+
+        model.update(response);
+        let subtask = model
+            .items
+            .iter()
+            .find(|item| item.id == subtask_id)
+            .expect("the subtask should still be there");
+        assert_eq!(subtask.parent_id, Some("REAL_PARENT_ID".into()));
+        assert_eq!(model.commands.len(), 2);
+        assert!(matches!(
+            model.commands[0].args,
+            Args::AddItemCommandArgs(AddItemArgs { ref parent_id, .. })
+                if *parent_id == Some("REAL_PARENT_ID".into())
+        ));
+        assert_eq!(
+            model.commands[1].args,
+            Args::CompleteItemCommandArgs(CompleteItemArgs {
+                id: "REAL_PARENT_ID".into()
+            })
+        );
+    }
+
+    #[test]
+    fn incremental_update_after_adding_local_section() {
+        let mut model = Model::default();
+        let section = Section::new("Section!", "INBOX_ID");
+        let section_id = section.id.clone();
+        model.add_section(section.clone());
+        assert_eq!(model.commands[0].request_type, "section_add");
+
+        let response = Response {
+            sections: Some(vec![Section {
+                id: "NEW_SECTION_ID".into(),
+                ..section
+            }]),
+            full_sync: false,
+            temp_id_mapping: HashMap::from([(section_id.to_string(), "NEW_SECTION_ID".into())]),
+            ..Default::default()
+        };
+
+        model.update(response);
+        assert_eq!(model.sections.len(), 1);
+        assert_eq!(model.sections[0].id, "NEW_SECTION_ID".into());
+    }
+
+    #[test]
+    fn incremental_update_merges_projects() {
+        let mut model = Model::default();
+        let project1 = Project::new("Project One");
+        let project2 = Project::new("Project Two");
+        let project2_updated = Project {
+            name: "Project Two with a new name".into(),
+            ..project2.clone()
+        };
+        let project3 = Project::new("Project Three");
+        model.projects.push(project1);
+        model.projects.push(project2);
+
+        let response = Response {
+            projects: Some(vec![project2_updated, project3]),
+            full_sync: false,
+            ..Default::default()
+        };
+
+        model.update(response);
+        let names: Vec<_> = model.projects.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Inbox",
+                "Project One",
+                "Project Two with a new name",
+                "Project Three"
+            ]
+        );
+    }
+
+    #[test]
+    fn incremental_update_removes_deleted_and_archived_resources() {
+        let mut model = Model::default();
+        let project1 = Project::new("Project One");
+        let project2 = Project::new("Project Two");
+        let section = Section::new("Section", &project1.id);
+        let item = Item::new("Item!", &project1.id);
+
+        let response = Response {
+            projects: Some(vec![
+                Project {
+                    is_archived: true,
+                    ..project1.clone()
+                },
+                Project {
+                    is_deleted: true,
+                    ..project2.clone()
+                },
+            ]),
+            sections: Some(vec![Section {
+                is_deleted: true,
+                ..section.clone()
+            }]),
+            items: Some(vec![Item {
+                is_deleted: true,
+                ..item.clone()
+            }]),
+            full_sync: false,
+            ..Default::default()
+        };
+
+        model.projects.push(project1);
+        model.projects.push(project2);
+        model.sections.push(section);
+        model.items.push(item);
+
+        model.update(response);
+        assert_eq!(model.projects.len(), 1);
+        assert_eq!(model.projects[0].name, "Inbox");
+        assert!(model.sections.is_empty());
+        assert!(model.items.is_empty());
+    }
+
+    #[test]
+    fn full_sync_only_replaces_included_resource_types() {
+        let mut model = Model::default();
+        let project = Project::new("Project");
+        let section = Section::new("Section", &project.id);
+        model.projects.push(project.clone());
+        model.sections.push(section);
+        model.items.push(Item::new("Old item", &project.id));
+
+        let response = Response {
+            items: Some(vec![Item::new("New item", &project.id)]),
+            sections: Some(vec![]),
+            full_sync: true,
+            ..Default::default()
+        };
+
+        model.update(response);
+        assert_eq!(model.projects.len(), 2);
+        assert!(model.sections.is_empty());
+        assert_eq!(model.items.len(), 1);
+        assert_eq!(model.items[0].content, "New item");
+    }
+
+    #[test]
+    fn incremental_update_removes_descendants_of_deleted_items() {
+        let mut model = Model::default();
+        let parent = Item::new("Parent", "INBOX_ID");
+        let child = Item::new("Child", "INBOX_ID").parent_id(&parent.id);
+        let grandchild = Item::new("Grandchild", "INBOX_ID").parent_id(&child.id);
+        let other = Item::new("Other", "INBOX_ID");
+
+        let response = Response {
+            items: Some(vec![Item {
+                is_deleted: true,
+                ..parent.clone()
+            }]),
+            full_sync: false,
+            ..Default::default()
+        };
+
+        model.items.push(parent);
+        model.items.push(child);
+        model.items.push(grandchild);
+        model.items.push(other);
+
+        model.update(response);
+        assert_eq!(model.items.len(), 1);
+        assert_eq!(model.items[0].content, "Other");
+    }
+
+    #[test]
+    fn incremental_update_removes_items_in_archived_projects_and_deleted_sections() {
+        let mut model = Model::default();
+        let project = Project::new("Project");
+        let other_project = Project::new("Other Project");
+        let section = Section::new("Section", &other_project.id);
+        let item_in_project = Item::new("In project", &project.id);
+        let item_in_section = Item::new("In section", &other_project.id).section_id(&section.id);
+        let child_in_section = Item::new("Child", &other_project.id).parent_id(&item_in_section.id);
+        let other_item = Item::new("Other", &other_project.id);
+
+        let response = Response {
+            projects: Some(vec![Project {
+                is_archived: true,
+                ..project.clone()
+            }]),
+            sections: Some(vec![Section {
+                is_deleted: true,
+                ..section.clone()
+            }]),
+            full_sync: false,
+            ..Default::default()
+        };
+
+        model.projects.push(project);
+        model.projects.push(other_project);
+        model.sections.push(section);
+        model.items.push(item_in_project);
+        model.items.push(item_in_section);
+        model.items.push(child_in_section);
+        model.items.push(other_item);
+
+        model.update(response);
+        assert_eq!(model.items.len(), 1);
+        assert_eq!(model.items[0].content, "Other");
+    }
 }
@@ -1,8 +1,9 @@
-use super::{due_date::Due, item, project};
-use serde::{Deserialize, Serialize};
+use super::{due_date::Due, item, project, section};
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use std::{collections::HashMap, fmt::Display};
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Clone)]
 pub struct Command {
     // TODO: enumify this
     #[serde(rename = "type")]
@@ -12,11 +13,91 @@ pub struct Command {
     pub args: Args,
 }
 
+impl<'de> Deserialize<'de> for Command {
+    /// Some commands' args have the same shape (eg. `item_update` and `project_update`), so
+    /// the command type is used to figure out which kind of args to expect.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct RawCommand {
+            #[serde(rename = "type")]
+            request_type: String,
+            uuid: Uuid,
+            temp_id: Option<String>,
+            args: serde_json::Value,
+        }
+
+        let raw = RawCommand::deserialize(deserializer)?;
+        let args = match raw.request_type.as_str() {
+            "item_update" => serde_json::from_value(raw.args).map(Args::UpdateItemCommandArgs),
+            "project_update" => {
+                serde_json::from_value(raw.args).map(Args::UpdateProjectCommandArgs)
+            }
+            "section_add" => serde_json::from_value(raw.args).map(Args::AddSectionCommandArgs),
+            "item_uncomplete" => {
+                serde_json::from_value(raw.args).map(Args::UncompleteItemCommandArgs)
+            }
+            _ => serde_json::from_value(raw.args),
+        }
+        .map_err(D::Error::custom)?;
+
+        Ok(Self {
+            request_type: raw.request_type,
+            uuid: raw.uuid,
+            temp_id: raw.temp_id,
+            args,
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum Args {
     AddItemCommandArgs(AddItemArgs),
+    UpdateItemCommandArgs(UpdateItemArgs),
+    UpdateProjectCommandArgs(UpdateProjectArgs),
     CompleteItemCommandArgs(CompleteItemArgs),
+    UncompleteItemCommandArgs(UncompleteItemArgs),
+    AddSectionCommandArgs(AddSectionArgs),
+}
+
+impl Args {
+    /// Replaces the temporary ids of todos and sections that the server has given real
+    /// ids, by their temporary ids.
+    pub fn replace_temp_ids(&mut self, temp_id_mapping: &HashMap<String, String>) {
+        match self {
+            Args::AddItemCommandArgs(AddItemArgs {
+                section_id,
+                parent_id,
+                ..
+            }) => {
+                if let Some(section_id) = section_id {
+                    replace_temp_id(section_id, temp_id_mapping);
+                }
+                if let Some(parent_id) = parent_id {
+                    replace_temp_id(parent_id, temp_id_mapping);
+                }
+            }
+            Args::UpdateItemCommandArgs(UpdateItemArgs { id, .. })
+            | Args::CompleteItemCommandArgs(CompleteItemArgs { id })
+            | Args::UncompleteItemCommandArgs(UncompleteItemArgs { id }) => {
+                replace_temp_id(id, temp_id_mapping);
+            }
+            Args::UpdateProjectCommandArgs(_) | Args::AddSectionCommandArgs(_) => {}
+        }
+    }
+}
+
+/// Replaces an id with the real one that the server gave it, if it's a temporary id.
+pub(super) fn replace_temp_id<Id>(id: &mut Id, temp_id_mapping: &HashMap<String, String>)
+where
+    Id: Display + From<String>,
+{
+    if let Some(real_id) = temp_id_mapping.get(&id.to_string()) {
+        *id = real_id.clone().into();
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -24,6 +105,52 @@ pub struct AddItemArgs {
     pub project_id: project::Id,
     pub content: String,
     pub due: Option<Due>,
+
+    // commands queued by older versions don't have the rest of these
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub section_id: Option<section::Id>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<item::Id>,
+    #[serde(default = "default_priority")]
+    pub priority: u8,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+}
+
+impl Default for AddItemArgs {
+    fn default() -> Self {
+        Self {
+            project_id: project::Id::default(),
+            content: String::new(),
+            due: None,
+            section_id: None,
+            parent_id: None,
+            priority: default_priority(),
+            labels: vec![],
+        }
+    }
+}
+
+fn default_priority() -> u8 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct UpdateItemArgs {
+    pub id: item::Id,
+    pub collapsed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct UpdateProjectArgs {
+    pub id: project::Id,
+    pub collapsed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AddSectionArgs {
+    pub name: String,
+    pub project_id: project::Id,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -32,3 +159,8 @@ pub struct CompleteItemArgs {
     // TODO:
     // pub completed_date: ????,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct UncompleteItemArgs {
+    pub id: item::Id,
+}
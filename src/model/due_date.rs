@@ -1,8 +1,9 @@
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use serde::Deserialize;
 use serde::Serialize;
 use smart_date::FlexibleDate;
 use smart_date::Parsed;
+use std::cmp::Ordering;
 use std::fmt::Display;
 use std::ops::Range;
 
@@ -13,6 +14,28 @@ pub enum DueDate {
     DateTime(NaiveDateTime),
 }
 
+impl DueDate {
+    /// Due dates without a time come before the ones on the same day that have a time.
+    fn sort_key(&self) -> (NaiveDate, Option<NaiveTime>) {
+        match self {
+            DueDate::Date(date) => (*date, None),
+            DueDate::DateTime(datetime) => (datetime.date(), Some(datetime.time())),
+        }
+    }
+}
+
+impl Ord for DueDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sort_key().cmp(&other.sort_key())
+    }
+}
+
+impl PartialOrd for DueDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Due {
     pub date: DueDate,
@@ -34,6 +57,22 @@ impl Due {
     }
 }
 
+impl Due {
+    /// Formats the due date with `strftime`-style formats for the date and the time
+    /// (which is only shown if there is one).
+    #[must_use]
+    pub fn format(&self, date_format: &str, time_format: &str) -> String {
+        match self.date {
+            DueDate::Date(date) => date.format(date_format).to_string(),
+            DueDate::DateTime(datetime) => format!(
+                "{} {}",
+                datetime.format(date_format),
+                datetime.format(time_format)
+            ),
+        }
+    }
+}
+
 impl Display for Due {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.date {
@@ -39,10 +39,15 @@ pub struct Item {
     pub content: String,
     pub checked: bool,
     pub due: Option<Due>,
+
+    /// From 1 (normal) to 4 (urgent), which Todoist shows as p4 to p1
+    pub priority: u8,
+    pub labels: Vec<String>,
     pub parent_id: Option<Id>,
     pub child_order: i32,
     pub section_id: Option<section::Id>,
     pub collapsed: bool,
+    pub is_deleted: bool,
 }
 
 impl Default for Item {
@@ -53,10 +58,13 @@ impl Default for Item {
             content: String::new(),
             checked: false,
             due: None,
+            priority: 1,
+            labels: vec![],
             parent_id: None,
             child_order: 0,
             section_id: None,
             collapsed: false,
+            is_deleted: false,
         }
     }
 }
@@ -87,6 +95,18 @@ impl Item {
         self
     }
 
+    #[must_use]
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    #[must_use]
+    pub fn labels(mut self, labels: Vec<String>) -> Self {
+        self.labels = labels;
+        self
+    }
+
     #[must_use]
     pub fn parent_id(mut self, parent_id: impl Into<Id>) -> Self {
         self.parent_id = Some(parent_id.into());
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use uuid::Uuid;
 
 /// An id for a project, which is really just `String`.
@@ -23,7 +24,15 @@ impl From<&Id> for Id {
     }
 }
 
+impl Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Represents a todoist project.
+// the flags mirror the fields of Todoist's projects
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
     pub id: Id,
@@ -31,6 +40,19 @@ pub struct Project {
     pub parent_id: Option<Id>,
     pub child_order: i32,
     pub collapsed: bool,
+    pub is_deleted: bool,
+    pub is_archived: bool,
+
+    /// The name of one of Todoist's project colours, eg. `berry_red`
+    pub color: String,
+    pub is_favorite: bool,
+
+    /// How Todoist shows the project: `list`, `board`, or `calendar`
+    pub view_style: String,
+
+    /// Todoist only sends this for the inbox, so it's false unless it's there.
+    #[serde(default)]
+    pub inbox_project: bool,
 }
 
 impl Project {
@@ -61,6 +83,22 @@ impl Project {
         self.child_order = child_order;
         self
     }
+
+    /// Sets the name of a project's colour.
+    /// This consumes the project and returns a new one.
+    #[must_use]
+    pub fn color(mut self, color: impl Into<String>) -> Self {
+        self.color = color.into();
+        self
+    }
+
+    /// Marks a project as a favorite.
+    /// This consumes the project and returns a new one.
+    #[must_use]
+    pub fn favorite(mut self) -> Self {
+        self.is_favorite = true;
+        self
+    }
 }
 
 impl Default for Project {
@@ -71,6 +109,12 @@ impl Default for Project {
             parent_id: None,
             child_order: 0,
             collapsed: false,
+            is_deleted: false,
+            is_archived: false,
+            color: "charcoal".to_string(),
+            is_favorite: false,
+            view_style: "list".to_string(),
+            inbox_project: false,
         }
     }
 }
@@ -0,0 +1,197 @@
+use super::{due_date::Due, item::Item, project, Model};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use std::ops::Range;
+
+/// A new todo as it's typed with Todoist's quick-add syntax, eg.
+/// `Call Bob tomorrow #Work /Calls p2 @phone`. Words starting with `#` name a project,
+/// `/` a section, and `@` a label; `p1` to `p4` set the priority; and a date anywhere
+/// in the text sets the due date.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuickAdd {
+    /// What's left of the text once everything above is taken out
+    pub content: String,
+    pub due: Option<Due>,
+    pub project: Option<String>,
+    pub section: Option<String>,
+    pub labels: Vec<String>,
+
+    /// The priority as Todoist's API has it, where 4 is urgent (which is `p1` when typed)
+    pub priority: Option<u8>,
+
+    /// Where the recognized parts are in the input, so that they can be highlighted
+    pub ranges: Vec<Range<usize>>,
+}
+
+impl QuickAdd {
+    /// Picks apart quick-add input. Dates are only looked for if there's a `today` to
+    /// understand them relative to.
+    #[must_use]
+    pub fn parse(input: &str, today: Option<NaiveDate>) -> Self {
+        let mut quick_add = Self::default();
+
+        // blank out the words that are recognized, so that the date parser doesn't see them
+        // but everything stays where it was in the input
+        let mut remaining = input.to_string();
+        for (range, word) in words(input) {
+            let recognized = match word.split_at(1) {
+                ("#", name) if !name.is_empty() => {
+                    quick_add.project = Some(name.to_string());
+                    true
+                }
+                ("/", name) if !name.is_empty() => {
+                    quick_add.section = Some(name.to_string());
+                    true
+                }
+                ("@", name) if !name.is_empty() => {
+                    quick_add.labels.push(name.to_string());
+                    true
+                }
+                _ => match parse_priority(word) {
+                    Some(priority) => {
+                        quick_add.priority = Some(priority);
+                        true
+                    }
+                    None => false,
+                },
+            };
+            if recognized {
+                remaining.replace_range(range.clone(), &" ".repeat(range.len()));
+                quick_add.ranges.push(range);
+            }
+        }
+
+        if let Some((due, range)) = today.and_then(|today| Due::parse_from_str(&remaining, today)) {
+            quick_add.due = Some(due);
+            remaining.replace_range(range.clone(), &" ".repeat(range.len()));
+            quick_add.ranges.push(range);
+            quick_add.ranges.sort_by_key(|range| range.start);
+        }
+
+        quick_add.content = remaining.split_whitespace().collect::<Vec<_>>().join(" ");
+        quick_add
+    }
+
+    /// Makes the new todo, in the project and section that were typed (if they were),
+    /// or else in `project_id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the project or section that was typed doesn't exist.
+    pub fn to_item(&self, model: &Model, project_id: &project::Id) -> Result<Item> {
+        let project_id = match &self.project {
+            Some(name) => model
+                .find_project(name)
+                .ok_or_else(|| anyhow!("There's no project named '{name}'."))?
+                .id
+                .clone(),
+            None => project_id.clone(),
+        };
+
+        let mut item = Item::new(&self.content, &project_id)
+            .due(self.due.clone())
+            .priority(self.priority.unwrap_or(1))
+            .labels(self.labels.clone());
+        if let Some(name) = &self.section {
+            let section = model
+                .sections
+                .iter()
+                .filter(|section| section.project_id == project_id)
+                .find(|section| {
+                    section.id.to_string() == *name || section.name.eq_ignore_ascii_case(name)
+                })
+                .ok_or_else(|| anyhow!("There's no section named '{name}' in that project."))?;
+            item = item.section_id(&section.id);
+        }
+
+        Ok(item)
+    }
+}
+
+/// Parses a priority the way Todoist shows it (`p1` is urgent, `p4` is normal), with or
+/// without the `p`, into the way its API has it (4 is urgent, 1 is normal).
+#[must_use]
+pub fn parse_priority(input: &str) -> Option<u8> {
+    let number = input
+        .strip_prefix(['p', 'P'])
+        .unwrap_or(input)
+        .parse::<u8>()
+        .ok()?;
+    (1..=4).contains(&number).then(|| 5 - number)
+}
+
+/// The words in the input, along with where they are. Only words that look like quick-add
+/// syntax (or a priority) can be recognized, so that eg. `1` isn't taken as a priority.
+fn words(input: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    input
+        .split_whitespace()
+        .map(move |word| {
+            let start = word.as_ptr() as usize - input.as_ptr() as usize;
+            (start..start + word.len(), word)
+        })
+        .filter(|(_, word)| {
+            word.starts_with(['#', '/', '@']) || (word.len() == 2 && word.starts_with(['p', 'P']))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::model::{due_date::DueDate, project::Project, section::Section};
+
+    #[test]
+    fn parse_quick_add_tokens() {
+        let today = NaiveDate::from_ymd_opt(2021, 10, 6).unwrap();
+        let input = "Call Bob tomorrow #Work /Calls p2 @phone @errands";
+
+        let quick_add = QuickAdd::parse(input, Some(today));
+
+        assert_eq!(quick_add.content, "Call Bob");
+        assert_eq!(
+            quick_add.due.unwrap().date,
+            DueDate::Date(NaiveDate::from_ymd_opt(2021, 10, 7).unwrap())
+        );
+        assert_eq!(quick_add.project.as_deref(), Some("Work"));
+        assert_eq!(quick_add.section.as_deref(), Some("Calls"));
+        assert_eq!(quick_add.labels, ["phone", "errands"]);
+        assert_eq!(quick_add.priority, Some(3));
+        let highlighted: Vec<&str> = quick_add.ranges.iter().map(|r| &input[r.clone()]).collect();
+        assert_eq!(
+            highlighted,
+            ["tomorrow", "#Work", "/Calls", "p2", "@phone", "@errands"]
+        );
+    }
+
+    #[test]
+    fn leave_other_text_alone() {
+        let quick_add = QuickAdd::parse("Email bob@example.com and/or p5 # and 1", None);
+
+        assert_eq!(quick_add.content, "Email bob@example.com and/or p5 # and 1");
+        assert_eq!(quick_add, QuickAdd::parse(&quick_add.content, None));
+        assert!(quick_add.ranges.is_empty());
+    }
+
+    #[test]
+    fn find_project_and_section_for_new_item() {
+        let work = Project::new("Work");
+        let calls = Section::new("Calls", &work.id);
+        let model = Model {
+            projects: vec![work.clone()],
+            sections: vec![calls.clone()],
+            ..Default::default()
+        };
+
+        let item = QuickAdd::parse("Call Bob #work /calls", None)
+            .to_item(&model, &"INBOX".into())
+            .unwrap();
+        assert_eq!(item.project_id, work.id);
+        assert_eq!(item.section_id, Some(calls.id));
+
+        let err = QuickAdd::parse("Call Bob /calls", None)
+            .to_item(&model, &"INBOX".into())
+            .unwrap_err();
+        assert!(err.to_string().contains("no section named 'calls'"));
+    }
+}
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use uuid::Uuid;
 
 use super::project;
@@ -26,6 +27,12 @@ impl From<&Id> for Id {
     }
 }
 
+impl Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// Represents a section inside a project.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub struct Section {
@@ -33,6 +40,8 @@ pub struct Section {
     pub name: String,
     pub project_id: project::Id,
     pub section_order: i32,
+    pub is_deleted: bool,
+    pub is_archived: bool,
 }
 
 impl Section {
@@ -65,6 +74,8 @@ impl Default for Section {
             name: String::new(),
             project_id: "".into(),
             section_order: 0,
+            is_deleted: false,
+            is_archived: false,
         }
     }
 }
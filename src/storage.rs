@@ -1,3 +1,7 @@
 pub mod config_manager;
 pub mod file_manager;
 pub mod model_manager;
+pub mod model_store;
+pub mod profile_manager;
+pub mod schema;
+pub mod sqlite_store;
@@ -1,22 +1,260 @@
 use super::file_manager::FileManager;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveDate, NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fmt::Write, path::PathBuf, process};
+use toml::Value;
+use toml_edit::Document;
 
 const AUTH_FILE_NAME: &str = "client_auth.toml";
+const CONFIG_FILE_NAME: &str = "config.toml";
 
-#[derive(Serialize, Deserialize)]
+/// If this is set, its value is used as the API token instead of any stored token.
+pub const API_TOKEN_ENV_VAR: &str = "TODOIST_API_TOKEN";
+
+#[cfg(not(feature = "keyring"))]
+const NO_KEYRING_MESSAGE: &str =
+    "This version of todoist-tui was built without keyring support. Reinstall it with '--features keyring'.";
+
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "todoist-tui";
+
+const MISSING_TOKEN_MESSAGE: &str = concat!(
+    "Could not find an API token. ",
+    "Go to https://todoist.com/app/settings/integrations/developer to get yours, ",
+    "then re-run with command 'set-token <TOKEN>'."
+);
+
+/// Where to find the API token. Only one of these should be set.
+#[derive(Default, Serialize, Deserialize)]
 pub struct Auth {
-    pub api_token: String,
+    /// The token itself, in plain text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_token: Option<String>,
+
+    /// A command that prints the token, eg. `pass show todoist`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+
+    /// Whether the token is kept in the OS keyring
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub keyring: bool,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// The user's settings for the app. Any setting that's missing from the config file
+/// gets its default value.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// How the model is stored between runs of the app
+    pub storage: StorageBackend,
+
+    /// The name or id of the project that `add` puts new todos in (the inbox if empty)
+    pub default_project: String,
+
+    /// How due dates are displayed, as a `strftime`-style format
+    pub date_format: String,
+
+    /// How due times are displayed (after the date), as a `strftime`-style format
+    pub time_format: String,
+
+    /// The day that weeks start on
+    pub week_start: Weekday,
+
+    /// How often (in seconds) the TUI syncs with the Todoist server while it's open
+    pub sync_interval: u64,
+
+    /// What `add` and `complete` do about syncing their changes
+    pub sync_mode: SyncMode,
+
+    /// The name of the TUI's colour theme, either a built-in theme or one from `themes`
+    pub theme: String,
+
+    /// The user's own themes, by name
+    pub themes: BTreeMap<String, ThemeSettings>,
+
+    /// Keys for the TUI's actions, by the name of the action (eg. `move_down = "down j"`)
+    pub keybindings: BTreeMap<String, String>,
+
+    /// Whether `list` shows todos that have been completed (but not yet synced)
+    pub show_completed: bool,
+
+    /// The order that `list` shows todos in
+    pub sort_order: SortOrder,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            storage: StorageBackend::default(),
+            default_project: String::new(),
+            date_format: "%Y-%m-%d".to_string(),
+            time_format: "%H:%M".to_string(),
+            week_start: Weekday::Mon,
+            sync_interval: 60,
+            sync_mode: SyncMode::default(),
+            theme: "default".to_string(),
+            themes: BTreeMap::new(),
+            keybindings: BTreeMap::new(),
+            show_completed: false,
+            sort_order: SortOrder::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Checks the settings that can have the right type but still be no good.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error that names the first bad setting.
+    pub fn validate(&self) -> Result<()> {
+        // a format that parses can still ask for something that a date (or a time) doesn't
+        // have, like the hour of a date, which only shows up when it's used
+        let date = NaiveDate::default();
+        let time = NaiveTime::default();
+        for (key, format, formatted) in [
+            (
+                "date_format",
+                &self.date_format,
+                write!(String::new(), "{}", date.format(&self.date_format)),
+            ),
+            (
+                "time_format",
+                &self.time_format,
+                write!(String::new(), "{}", time.format(&self.time_format)),
+            ),
+        ] {
+            if formatted.is_err() {
+                bail!("Invalid value for '{key}': '{format}' isn't a valid date/time format.");
+            }
+        }
+
+        if self.sync_interval == 0 {
+            bail!("Invalid value for 'sync_interval': it must be at least 1 (second).");
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// The order they're in in Todoist
+    #[default]
+    Manual,
+
+    /// By due date, with todos that aren't due last
+    Due,
+
+    /// By their content, alphabetically
+    Alphabetical,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncMode {
+    /// Sync straight away, and fail (without saving the change) if that doesn't work
+    #[default]
+    Immediate,
+
+    /// Save the change first, then try to sync, and only warn if that doesn't work (the
+    /// change is sent with the next sync that does)
+    Deferred,
+}
+
+/// A theme of the user's own, made by changing some of the colours of a built-in theme.
+/// Colours are names like `"blue"` or `"lightred"`, hex codes like `"#ff8800"`, or
+/// numbers from the terminal's 256-colour palette.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeSettings {
+    /// The built-in theme to start from (`default` if not given)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focused_border: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unfocused_border: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_fg: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub selected_bg: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub muted: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_highlight: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// A JSON file, `sync.json`
+    #[default]
+    Json,
+
+    /// A `SQLite` database, `sync.db`
+    Sqlite,
 }
 
 pub struct ConfigManager<'a> {
     file_manager: &'a FileManager,
+
+    /// The name of the open profile, which the token is stored under in the keyring
+    #[cfg_attr(not(feature = "keyring"), allow(dead_code))]
+    profile: &'a str,
+
+    /// Checks the settings that other parts of the app make sense of, like the keybindings
+    check: fn(&Config) -> Result<()>,
+
+    /// Gets the default keys of a TUI action by its name, for the keybindings that aren't set
+    default_keys: fn(&str) -> Result<String>,
 }
 
 impl<'a> ConfigManager<'a> {
     #[must_use]
-    pub fn new(file_manager: &'a FileManager) -> Self {
-        Self { file_manager }
+    pub fn new(file_manager: &'a FileManager, profile: &'a str) -> Self {
+        Self {
+            file_manager,
+            profile,
+            check: |_| Ok(()),
+            default_keys: |action| bail!("There's no setting named 'keybindings.{action}'."),
+        }
+    }
+
+    /// Also checks the settings with `check` whenever they're read or changed, so that
+    /// settings that only make sense to another part of the app (eg. the TUI's keybindings)
+    /// are pointed out the same way as the rest.
+    #[must_use]
+    pub fn check_with(mut self, check: fn(&Config) -> Result<()>) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Uses `default_keys` to get the value of keybindings that aren't set, since only the
+    /// TUI knows what they are.
+    #[must_use]
+    pub fn default_keys_with(mut self, default_keys: fn(&str) -> Result<String>) -> Self {
+        self.default_keys = default_keys;
+        self
     }
 
     /// # Errors
@@ -27,32 +265,261 @@ impl<'a> ConfigManager<'a> {
         let file = self
             .file_manager
             .read_data(AUTH_FILE_NAME.into())
-            .context(concat!(
-                "Could not find an API token. ",
-                "Go to https://todoist.com/app/settings/integrations/developer to get yours, ",
-                "then re-run with command 'set-token <TOKEN>'."
-            ))?;
+            .context(MISSING_TOKEN_MESSAGE)?;
         let config: Auth = toml::from_str(file.as_str())
             .with_context(|| format!("Could not parse config file '{AUTH_FILE_NAME}'"))?;
         Ok(config)
     }
 
+    /// Gets the API token from the environment if it's set there, and otherwise from
+    /// wherever the auth config file says it is.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the config file cannot be found or read, or if the file
-    /// can be read but isn't in the correct format.
+    /// Returns an error if the config file cannot be found or read, if the file
+    /// can be read but isn't in the correct format, or if the token can't be retrieved.
     pub fn get_api_token(&self) -> Result<String> {
+        if let Some(token) = env::var(API_TOKEN_ENV_VAR)
+            .ok()
+            .filter(|token| !token.is_empty())
+        {
+            return Ok(token);
+        }
+
         let config = self.read_auth_config()?;
-        Ok(config.api_token)
+        if let Some(command) = config.token_command {
+            run_token_command(&command)
+        } else if config.keyring {
+            self.read_keyring_token()
+        } else {
+            config
+                .api_token
+                .ok_or_else(|| anyhow!(MISSING_TOKEN_MESSAGE))
+        }
     }
 
+    /// Writes the auth config file, which only the current user is allowed to read on unix.
+    ///
     /// # Errors
     ///
     /// Returns an error if something goes wrong while writing to the file.
     pub fn write_auth_config(&self, config: &Auth) -> Result<()> {
         let contents = toml::to_string_pretty(config)?;
         self.file_manager
-            .write_data(AUTH_FILE_NAME.into(), &contents)?;
-        Ok(())
+            .write_private_data(AUTH_FILE_NAME.into(), &contents)
+    }
+
+    #[must_use]
+    pub fn auth_file_path(&self) -> PathBuf {
+        self.file_manager.data_path(AUTH_FILE_NAME.into())
+    }
+
+    #[cfg(feature = "keyring")]
+    fn keyring_entry(&self) -> Result<keyring::Entry> {
+        Ok(keyring::Entry::new(KEYRING_SERVICE, self.profile)?)
+    }
+
+    #[cfg(feature = "keyring")]
+    fn read_keyring_token(&self) -> Result<String> {
+        self.keyring_entry()?
+            .get_password()
+            .context("Could not get the API token from the keyring.")
+    }
+
+    #[cfg(not(feature = "keyring"))]
+    #[allow(clippy::unused_self)]
+    fn read_keyring_token(&self) -> Result<String> {
+        bail!(NO_KEYRING_MESSAGE)
+    }
+
+    /// Stores the API token in the OS keyring.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token can't be stored, or if the app was built without
+    /// keyring support.
+    #[cfg(feature = "keyring")]
+    pub fn write_keyring_token(&self, token: &str) -> Result<()> {
+        self.keyring_entry()?
+            .set_password(token)
+            .context("Could not store the API token in the keyring.")
+    }
+
+    /// Stores the API token in the OS keyring.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token can't be stored, or if the app was built without
+    /// keyring support.
+    #[cfg(not(feature = "keyring"))]
+    #[allow(clippy::unused_self)]
+    pub fn write_keyring_token(&self, _token: &str) -> Result<()> {
+        bail!(NO_KEYRING_MESSAGE)
+    }
+
+    #[must_use]
+    pub fn config_file_path(&self) -> PathBuf {
+        self.file_manager.data_path(CONFIG_FILE_NAME.into())
+    }
+
+    /// Reads the user's settings, or the default settings if there's no config file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config file can be read but isn't in the correct format,
+    /// or if one of the settings isn't valid.
+    pub fn read_config(&self) -> Result<Config> {
+        if !self.file_manager.has_data_file(CONFIG_FILE_NAME.into()) {
+            return Ok(Config::default());
+        }
+
+        let file = self.file_manager.read_data(CONFIG_FILE_NAME.into())?;
+        self.parse_config(&file)
+    }
+
+    /// Turns the contents of the config file into settings, with an error that points at
+    /// the problem if that doesn't work out.
+    fn parse_config(&self, contents: &str) -> Result<Config> {
+        let config: Config = toml::from_str(contents)
+            .with_context(|| format!("Invalid config file '{CONFIG_FILE_NAME}'"))?;
+        config
+            .validate()
+            .and_then(|()| (self.check)(&config))
+            .with_context(|| format!("Invalid config file '{CONFIG_FILE_NAME}'"))?;
+        Ok(config)
+    }
+
+    /// Reads the config file as it is, with its comments and formatting, and without
+    /// filling in any defaults.
+    fn read_config_document(&self) -> Result<Document> {
+        if !self.file_manager.has_data_file(CONFIG_FILE_NAME.into()) {
+            return Ok(Document::new());
+        }
+
+        let file = self.file_manager.read_data(CONFIG_FILE_NAME.into())?;
+        file.parse()
+            .with_context(|| format!("Could not parse config file '{CONFIG_FILE_NAME}'"))
+    }
+
+    /// Returns all of the settings (including the defaults for those that aren't set),
+    /// with the keys of nested settings joined by dots.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config file can't be read.
+    pub fn list_settings(&self) -> Result<Vec<(String, Value)>> {
+        let config = Value::try_from(self.read_config()?)?;
+        let mut settings = vec![];
+        flatten_settings(None, config, &mut settings);
+        Ok(settings)
+    }
+
+    /// Returns the value of a setting (which is its default value if it isn't set).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config file can't be read, or if there's no such setting.
+    pub fn get_setting(&self, key: &str) -> Result<Value> {
+        let config = Value::try_from(self.read_config()?)?;
+        if let Some(value) = setting(&config, key) {
+            return Ok(value.clone());
+        }
+
+        match key.strip_prefix("keybindings.") {
+            Some(action) => (self.default_keys)(action).map(Value::String),
+            None => bail!("There's no setting named '{key}'."),
+        }
+    }
+
+    /// Changes a setting in the config file. Numbers (eg. `30`) and `true`/`false` are
+    /// only written as such for settings that are numbers or booleans, and everything else
+    /// is written as a string. The rest of the file, including comments, is left the way
+    /// it was.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no such setting, if the value isn't valid for it, or if
+    /// the config file can't be read or written.
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        let defaults = Value::try_from(Config::default())?;
+        let mut value = match setting(&defaults, key) {
+            Some(Value::Integer(_)) => value.parse::<i64>().ok().map(toml_edit::Value::from),
+            Some(Value::Boolean(_)) => value.parse::<bool>().ok().map(toml_edit::Value::from),
+            _ => None,
+        }
+        // a value that doesn't fit is still written, so that it's reported like other bad values
+        .unwrap_or_else(|| value.into());
+
+        let mut document = self.read_config_document()?;
+        let mut parts: Vec<&str> = key.split('.').collect();
+        let last = parts.pop().unwrap_or_default();
+        let mut parent = document.as_table_mut() as &mut dyn toml_edit::TableLike;
+        for part in parts {
+            parent = parent
+                .entry(part)
+                .or_insert(toml_edit::table())
+                .as_table_like_mut()
+                .ok_or_else(|| anyhow!("There's no setting named '{key}'."))?;
+        }
+
+        // a value that's being replaced keeps its key, spacing, and comments
+        if let Some(toml_edit::Item::Value(previous)) = parent.get_mut(last) {
+            *value.decor_mut() = previous.decor().clone();
+            *previous = value;
+        } else {
+            value.decor_mut().clear();
+            parent.insert(last, toml_edit::Item::Value(value));
+        }
+
+        // make sure that the new setting makes sense before saving it
+        let contents = document.to_string();
+        self.parse_config(&contents)?;
+
+        self.file_manager
+            .write_data(CONFIG_FILE_NAME.into(), &contents)
+    }
+}
+
+/// Finds a setting by its key, where the keys of nested settings are joined by dots.
+fn setting<'v>(settings: &'v Value, key: &str) -> Option<&'v Value> {
+    key.split('.')
+        .try_fold(settings, |value, part| value.get(part))
+}
+
+fn flatten_settings(prefix: Option<&str>, value: Value, settings: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let key = prefix.map_or(key.clone(), |prefix| format!("{prefix}.{key}"));
+                flatten_settings(Some(&key), value, settings);
+            }
+        }
+        value => settings.push((prefix.unwrap_or_default().to_string(), value)),
+    }
+}
+
+/// Runs the command (in a shell) and uses what it prints as the API token.
+fn run_token_command(command: &str) -> Result<String> {
+    let output = if cfg!(windows) {
+        process::Command::new("cmd").args(["/C", command]).output()
+    } else {
+        process::Command::new("sh").args(["-c", command]).output()
+    }
+    .with_context(|| format!("Could not run token command '{command}'"))?;
+
+    if !output.status.success() {
+        bail!(
+            "Token command '{command}' failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let token = String::from_utf8(output.stdout)
+        .with_context(|| format!("Token command '{command}' printed something that isn't text"))?
+        .trim()
+        .to_string();
+    if token.is_empty() {
+        bail!("Token command '{command}' didn't print a token.");
     }
+    Ok(token)
 }
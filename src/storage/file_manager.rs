@@ -1,11 +1,38 @@
 use anyhow::{bail, Result};
 use std::{
-    fs,
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
     path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
 };
 
 const APP_DATA_DIR_NAME: &str = "todoist-tui";
+const LOCK_FILE_NAME: &str = "data.lock";
 
+/// Held by the process that's removing a stale lock, so that only one process does it at a time
+const BREAKING_LOCK_FILE_NAME: &str = "data.lock.breaking";
+
+/// How long to wait for another process to release the lock on the data directory
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A lock file older than this is assumed to have been left behind by a process that crashed,
+/// when there's no way to check whether the process that made it is still running
+const STALE_LOCK_AGE: Duration = Duration::from_mins(10);
+
+/// An advisory lock on the data directory, which is released when this is dropped.
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        // if this fails, the lock will eventually be detected as stale
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+#[derive(Clone)]
 pub struct FileManager {
     data_dir: PathBuf,
 }
@@ -40,18 +67,289 @@ impl FileManager {
         Ok(file)
     }
 
+    /// Writes the data to a temporary file first and then moves it into place, so that
+    /// the existing file is left intact if something goes wrong partway through.
+    ///
     /// # Errors
     ///
     /// Returns an error if the file cannot be opened or if an error occurs while writing.
     pub fn write_data(&self, path_from_data_dir: PathBuf, data: &str) -> Result<()> {
+        self.write_file(path_from_data_dir, data, false)
+    }
+
+    /// Like `write_data`, but the file is only readable and writable by the current user
+    /// (on platforms with unix permissions) from the moment it's created, for secrets.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or if an error occurs while writing.
+    pub fn write_private_data(&self, path_from_data_dir: PathBuf, data: &str) -> Result<()> {
+        self.write_file(path_from_data_dir, data, true)
+    }
+
+    fn write_file(&self, path_from_data_dir: PathBuf, data: &str, private: bool) -> Result<()> {
         let file_path = Path::new(&self.data_dir).join(path_from_data_dir);
-        fs::write(file_path, data)?;
+        let mut temp_path = file_path.clone().into_os_string();
+        temp_path.push(".tmp");
+
+        // a temporary file left behind by a crash keeps its permissions if it's reused, so
+        // it's replaced with a new one
+        remove_if_exists(Path::new(&temp_path))?;
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        #[cfg(not(unix))]
+        let _ = private;
+
+        let mut temp_file = options.open(&temp_path)?;
+        temp_file.write_all(data.as_bytes())?;
+        temp_file.sync_all()?;
+
+        fs::rename(&temp_path, &file_path)?;
+
+        // make sure the rename itself makes it to disk
+        #[cfg(unix)]
+        File::open(&self.data_dir)?.sync_all()?;
+
         Ok(())
     }
 
+    /// # Errors
+    ///
+    /// Returns an error if the source file cannot be read or the destination cannot be written.
+    pub fn copy_data(&self, from_path: PathBuf, to_path: PathBuf) -> Result<()> {
+        let data = self.read_data(from_path)?;
+        self.write_data(to_path, &data)
+    }
+
+    /// The full path of a file in the data directory.
+    #[must_use]
+    pub fn data_path(&self, path_from_data_dir: PathBuf) -> PathBuf {
+        Path::new(&self.data_dir).join(path_from_data_dir)
+    }
+
+    /// A file manager for a directory inside the data directory, which might not exist yet.
+    #[must_use]
+    pub fn subdirectory(&self, path_from_data_dir: PathBuf) -> Self {
+        Self {
+            data_dir: self.data_path(path_from_data_dir),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn create_dir(&self, path_from_data_dir: PathBuf) -> Result<()> {
+        fs::create_dir_all(self.data_path(path_from_data_dir))?;
+        Ok(())
+    }
+
+    /// Removes a directory inside the data directory, along with everything in it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be removed.
+    pub fn remove_dir(&self, path_from_data_dir: PathBuf) -> Result<()> {
+        fs::remove_dir_all(self.data_path(path_from_data_dir))?;
+        Ok(())
+    }
+
+    /// Returns the (sorted) names of the directories inside a directory, which is treated
+    /// as empty if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory exists but cannot be read.
+    pub fn list_dirs(&self, path_from_data_dir: PathBuf) -> Result<Vec<String>> {
+        let dir_path = self.data_path(path_from_data_dir);
+        if !dir_path.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut names = vec![];
+        for entry in fs::read_dir(dir_path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
     #[must_use]
     pub fn has_data_file(&self, path_from_data_dir: PathBuf) -> bool {
         let file_path = Path::new(&self.data_dir).join(path_from_data_dir);
         file_path.exists()
     }
+
+    /// Returns when a file was last modified, or `None` if that can't be determined.
+    #[must_use]
+    pub fn modified(&self, path_from_data_dir: PathBuf) -> Option<SystemTime> {
+        let file_path = Path::new(&self.data_dir).join(path_from_data_dir);
+        fs::metadata(file_path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Locks the data directory so that other instances of the app don't modify its
+    /// files at the same time as us, waiting for a little while if it's already locked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock file cannot be created, or if another process
+    /// holds the lock for too long.
+    pub fn lock(&self) -> Result<Lock> {
+        let start = SystemTime::now();
+        loop {
+            if let Some(lock) = self.try_lock()? {
+                return Ok(lock);
+            }
+
+            if start.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                let owner = self
+                    .read_data(LOCK_FILE_NAME.into())
+                    .map(|pid| format!(" (PID {})", pid.trim()))
+                    .unwrap_or_default();
+                bail!(
+                    "Another todoist-tui process{owner} is using the app's data directory. \
+                    Try again once it's finished."
+                );
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// Locks the data directory if it isn't already locked by another process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock file cannot be created or removed.
+    pub fn try_lock(&self) -> Result<Option<Lock>> {
+        let lock_path = self.data_dir.join(LOCK_FILE_NAME);
+
+        // try twice, in case the first attempt finds a stale lock
+        for _ in 0..2 {
+            if let Some(lock) = create_lock_file(&lock_path)? {
+                return Ok(Some(lock));
+            }
+            if !self.remove_stale_lock(&lock_path)? {
+                return Ok(None);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Removes the lock file if it was left behind by a process that's no longer around, and
+    /// returns whether it's gone. Only one process at a time gets to do this, and it checks the
+    /// lock again once it has the go-ahead, so it can't remove a lock that another process has
+    /// just created in place of the stale one.
+    fn remove_stale_lock(&self, lock_path: &Path) -> Result<bool> {
+        if !is_stale_lock(lock_path) {
+            return Ok(false);
+        }
+
+        let breaking_lock_path = self.data_dir.join(BREAKING_LOCK_FILE_NAME);
+        let Some(_breaking_lock) = create_lock_file(&breaking_lock_path)? else {
+            // a process that died while removing a stale lock leaves this behind
+            if is_stale_lock(&breaking_lock_path) {
+                remove_if_exists(&breaking_lock_path)?;
+            }
+            return Ok(false);
+        };
+
+        if lock_path.exists() && !is_stale_lock(lock_path) {
+            return Ok(false);
+        }
+        remove_if_exists(lock_path)?;
+        Ok(true)
+    }
+}
+
+/// Creates a lock file with this process's id in it, or returns `None` if it already exists.
+fn create_lock_file(path: &Path) -> Result<Option<Lock>> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(mut file) => {
+            let lock = Lock {
+                path: path.to_path_buf(),
+            };
+            write!(file, "{}", std::process::id())?;
+            Ok(Some(lock))
+        }
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Whether the lock file at the given path was left behind by a process that's no longer around.
+fn is_stale_lock(lock_path: &Path) -> bool {
+    let too_old = fs::metadata(lock_path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > STALE_LOCK_AGE);
+
+    let pid = fs::read_to_string(lock_path)
+        .ok()
+        .and_then(|contents| contents.trim().parse::<u32>().ok());
+
+    // a process that's still running might just be slow (eg. syncing over a bad connection),
+    // so the age of the lock only counts when there's no way to check on it
+    match pid.and_then(process_is_running) {
+        Some(running) => !running,
+        None => too_old,
+    }
+}
+
+/// Whether the process with the given id is running, or `None` if that can't be checked.
+#[cfg(target_os = "linux")]
+#[allow(clippy::unnecessary_wraps)]
+fn process_is_running(pid: u32) -> Option<bool> {
+    Some(Path::new("/proc").join(pid.to_string()).exists())
+}
+
+// there isn't a cheap way to check on other platforms
+#[cfg(not(target_os = "linux"))]
+fn process_is_running(_pid: u32) -> Option<bool> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn only_break_old_locks_of_processes_that_are_gone() {
+        let lock_path =
+            std::env::temp_dir().join(format!("todoist-tui-{}.lock", uuid::Uuid::new_v4()));
+        let long_ago = SystemTime::now() - STALE_LOCK_AGE * 2;
+
+        // this process might just be taking a while
+        fs::write(&lock_path, std::process::id().to_string()).unwrap();
+        File::options()
+            .write(true)
+            .open(&lock_path)
+            .unwrap()
+            .set_modified(long_ago)
+            .unwrap();
+        assert!(!is_stale_lock(&lock_path));
+
+        fs::write(&lock_path, u32::MAX.to_string()).unwrap();
+        assert!(is_stale_lock(&lock_path));
+
+        fs::remove_file(&lock_path).unwrap();
+    }
 }
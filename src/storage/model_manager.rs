@@ -1,45 +1,123 @@
-use super::file_manager::FileManager;
+use super::{
+    file_manager::{FileManager, Lock},
+    model_store::ModelStore,
+    schema::{self, UnsupportedVersion},
+};
 use crate::model::Model;
 use anyhow::{Context, Result};
+use std::{cell::Cell, time::SystemTime};
 
 const MODEL_FILE_NAME: &str = "sync.json";
+const MODEL_BACKUP_FILE_NAME: &str = "sync.json.bak";
 
+/// Stores the model as JSON in the data directory.
 pub struct ModelManager<'a> {
     file_manager: &'a FileManager,
+
+    /// Whether the data file has been read (or written) successfully by this process, which
+    /// means it's worth keeping as a backup when it's replaced
+    data_file_is_good: Cell<bool>,
 }
 
 impl<'a> ModelManager<'a> {
     #[must_use]
     pub fn new(file_manager: &'a FileManager) -> Self {
-        Self { file_manager }
+        Self {
+            file_manager,
+            data_file_is_good: Cell::new(false),
+        }
+    }
+
+    fn read_model_file(&self, file_name: &str) -> Result<Model> {
+        let file = self
+            .file_manager
+            .read_data(file_name.into())
+            .context("Could not read from the app's data storage.")?;
+        let model = schema::from_stored(&file)
+            .with_context(|| format!("Could not parse model file '{file_name}'"))?;
+        Ok(model)
     }
+}
 
+impl ModelStore for ModelManager<'_> {
+    /// Reads the model from the data file. If that file can't be read or parsed,
+    /// falls back to the backup from the previous write (if there is one). Files stored
+    /// with an older schema version are migrated.
+    ///
     /// # Errors
     ///
     /// Returns an error if the data file cannot be found or read, or if the file
-    /// can be read but isn't in the correct format.
-    pub fn read_model(&self) -> Result<Model> {
-        if self.file_manager.has_data_file(MODEL_FILE_NAME.into()) {
-            let file = self
-                .file_manager
-                .read_data(MODEL_FILE_NAME.into())
-                .context("Could not read from the app's data storage.")?;
-            let model = serde_json::from_str(&file)
-                .with_context(|| format!("Could not parse model file '{MODEL_FILE_NAME}'"))?;
-            Ok(model)
-        } else {
-            Ok(Model::default())
+    /// can be read but isn't in the correct format (and neither is the backup), or if it
+    /// was written by a newer version of the app.
+    fn read_model(&self) -> Result<Model> {
+        if !self.file_manager.has_data_file(MODEL_FILE_NAME.into()) {
+            return Ok(Model::default());
+        }
+
+        match self.read_model_file(MODEL_FILE_NAME) {
+            Ok(model) => {
+                self.data_file_is_good.set(true);
+                Ok(model)
+            }
+            Err(err)
+                if !err.is::<UnsupportedVersion>()
+                    && self
+                        .file_manager
+                        .has_data_file(MODEL_BACKUP_FILE_NAME.into()) =>
+            {
+                eprintln!(
+                    "Warning: {err:#}. Using the backup in '{MODEL_BACKUP_FILE_NAME}' instead."
+                );
+                self.read_model_file(MODEL_BACKUP_FILE_NAME)
+            }
+            Err(err) => Err(err),
         }
     }
 
+    /// Writes the model to the data file, keeping the previous version of the file as a backup.
+    /// The data file is only backed up if it's been read (or written) without any problems,
+    /// so that a file that can't be read doesn't replace the backup that `read_model` fell
+    /// back to. That way it doesn't have to be parsed again here.
+    ///
     /// # Errors
     ///
     /// Returns an error if something goes wrong while writing to the file.
-    pub fn write_model(&self, model: &Model) -> Result<()> {
-        let contents = serde_json::to_string_pretty(model)?;
+    fn write_model(&self, model: &Model) -> Result<()> {
+        if self.data_file_is_good.get() && self.file_manager.has_data_file(MODEL_FILE_NAME.into()) {
+            self.file_manager
+                .copy_data(MODEL_FILE_NAME.into(), MODEL_BACKUP_FILE_NAME.into())
+                .context("Could not back up the model file.")?;
+        }
+
+        let contents = schema::to_stored(model)?;
         self.file_manager
             .write_data(MODEL_FILE_NAME.into(), &contents)
             .context("Could not write to command storage file.")?;
+        self.data_file_is_good.set(true);
         Ok(())
     }
+
+    /// Locks the data directory so that no other process changes the model until the
+    /// returned lock is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock can't be acquired.
+    fn lock(&self) -> Result<Lock> {
+        self.file_manager.lock()
+    }
+
+    /// Like `lock`, but returns `None` instead of waiting if another process holds the lock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if something goes wrong while creating the lock.
+    fn try_lock(&self) -> Result<Option<Lock>> {
+        self.file_manager.try_lock()
+    }
+
+    /// When the model file was last written to, or `None` if there isn't one.
+    fn last_modified(&self) -> Option<SystemTime> {
+        self.file_manager.modified(MODEL_FILE_NAME.into())
+    }
 }
@@ -0,0 +1,107 @@
+use super::file_manager::Lock;
+use crate::model::{
+    item::{Id as ItemId, Item},
+    project::{Id as ProjectId, Project},
+    section::{Id as SectionId, Section},
+    user::User,
+    Model,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use std::time::SystemTime;
+
+/// Which todos `read_model_with_items` reads. Each of the fields that's set narrows them down.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItemFilter {
+    /// Only the todos in these projects
+    pub project_ids: Option<Vec<ProjectId>>,
+
+    /// Only the todos in these sections
+    pub section_ids: Option<Vec<SectionId>>,
+
+    /// Only the todos due on or after this day
+    pub due_from: Option<NaiveDate>,
+
+    /// Only the todos due on or before this day
+    pub due_until: Option<NaiveDate>,
+}
+
+/// What `read_model_with_items` reads: everything that todos are listed by, but only some of
+/// the todos. It isn't a `Model`, so that it can't be written back to the store in place of
+/// the todos that weren't read.
+#[derive(Debug, Default)]
+pub struct ItemSelection {
+    pub user: User,
+    pub projects: Vec<Project>,
+    pub sections: Vec<Section>,
+
+    /// The todos that the filter picked out, along with their ancestors
+    pub items: Vec<Item>,
+
+    /// The ids of all of the stored todos, since short ids depend on all of them
+    pub item_ids: Vec<ItemId>,
+}
+
+impl From<Model> for ItemSelection {
+    fn from(model: Model) -> Self {
+        let item_ids = model.items.iter().map(|item| item.id.clone()).collect();
+        Self {
+            user: model.user,
+            projects: model.projects,
+            sections: model.sections,
+            items: model.items,
+            item_ids,
+        }
+    }
+}
+
+/// Somewhere the model is kept between runs of the app.
+pub trait ModelStore {
+    /// Reads the stored model, or returns the default model if nothing has been stored yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored model can't be read.
+    fn read_model(&self) -> Result<Model>;
+
+    /// Reads the stored model with only the todos that a filter picks out, along with their
+    /// ancestors so that subtasks can still be put in a tree. The filter is worked out from
+    /// the model without any todos, so that projects and sections can be found by name.
+    ///
+    /// Stores that can't look todos up read the whole model.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stored model can't be read.
+    fn read_model_with_items(
+        &self,
+        _filter: &dyn Fn(&Model) -> ItemFilter,
+    ) -> Result<ItemSelection> {
+        Ok(self.read_model()?.into())
+    }
+
+    /// Replaces the stored model with the given one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if something goes wrong while writing the model.
+    fn write_model(&self, model: &Model) -> Result<()>;
+
+    /// Locks the store so that no other process changes the model until the
+    /// returned lock is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock can't be acquired.
+    fn lock(&self) -> Result<Lock>;
+
+    /// Like `lock`, but returns `None` instead of waiting if another process holds the lock.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if something goes wrong while creating the lock.
+    fn try_lock(&self) -> Result<Option<Lock>>;
+
+    /// When the stored model was last written to, or `None` if nothing has been stored yet.
+    fn last_modified(&self) -> Option<SystemTime>;
+}
@@ -0,0 +1,161 @@
+use super::file_manager::FileManager;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// The profile that keeps its files directly in the data directory, which is where
+/// everything was stored before there were profiles.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Other profiles each get a directory in here.
+const PROFILES_DIR_NAME: &str = "profiles";
+const PROFILES_FILE_NAME: &str = "profiles.toml";
+
+#[derive(Default, Serialize, Deserialize)]
+struct Profiles {
+    /// The profile to use when none is given
+    default: Option<String>,
+}
+
+/// Manages named profiles, each with its own API token, configuration, and model,
+/// so that the app can be used with more than one Todoist account.
+pub struct ProfileManager<'a> {
+    file_manager: &'a FileManager,
+}
+
+impl<'a> ProfileManager<'a> {
+    #[must_use]
+    pub fn new(file_manager: &'a FileManager) -> Self {
+        Self { file_manager }
+    }
+
+    fn profile_path(name: &str) -> PathBuf {
+        PathBuf::from(PROFILES_DIR_NAME).join(name)
+    }
+
+    /// Profile names become directory names, so anything that could point outside the
+    /// profiles directory (like `..`, an absolute path, or an empty name) isn't allowed.
+    fn validate_name(name: &str) -> Result<()> {
+        let is_valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_valid {
+            bail!("'{name}' isn't a valid profile name. Use only letters, numbers, '-' and '_'.");
+        }
+        Ok(())
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        Self::validate_name(name)?;
+        Ok(name == DEFAULT_PROFILE || self.file_manager.has_data_file(Self::profile_path(name)))
+    }
+
+    fn read_profiles(&self) -> Result<Profiles> {
+        if !self.file_manager.has_data_file(PROFILES_FILE_NAME.into()) {
+            return Ok(Profiles::default());
+        }
+
+        let file = self.file_manager.read_data(PROFILES_FILE_NAME.into())?;
+        let profiles = toml::from_str(file.as_str())
+            .with_context(|| format!("Could not parse profiles file '{PROFILES_FILE_NAME}'"))?;
+        Ok(profiles)
+    }
+
+    fn write_profiles(&self, profiles: &Profiles) -> Result<()> {
+        let contents = toml::to_string_pretty(profiles)?;
+        self.file_manager
+            .write_data(PROFILES_FILE_NAME.into(), &contents)
+    }
+
+    /// Returns a file manager for the given profile's files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name isn't valid or the profile doesn't exist.
+    pub fn open(&self, name: &str) -> Result<FileManager> {
+        if !self.exists(name)? {
+            bail!("There's no profile named '{name}'. Create it with 'profile add {name}'.");
+        }
+
+        if name == DEFAULT_PROFILE {
+            Ok(self.file_manager.clone())
+        } else {
+            Ok(self.file_manager.subdirectory(Self::profile_path(name)))
+        }
+    }
+
+    /// Returns the names of all profiles, starting with the default profile.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profiles directory can't be read.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let mut names = vec![DEFAULT_PROFILE.to_string()];
+        names.extend(self.file_manager.list_dirs(PROFILES_DIR_NAME.into())?);
+        Ok(names)
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the name isn't valid, if the profile already exists,
+    /// or if its directory can't be created.
+    pub fn add(&self, name: &str) -> Result<()> {
+        if self.exists(name)? {
+            bail!("There's already a profile named '{name}'.");
+        }
+
+        self.file_manager.create_dir(Self::profile_path(name))
+    }
+
+    /// Removes a profile along with all of its data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name isn't valid, if the profile is the default profile,
+    /// doesn't exist, or if its directory can't be removed.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        Self::validate_name(name)?;
+        if name == DEFAULT_PROFILE {
+            bail!("The '{DEFAULT_PROFILE}' profile can't be removed.");
+        }
+        if !self.exists(name)? {
+            bail!("There's no profile named '{name}'.");
+        }
+
+        self.file_manager.remove_dir(Self::profile_path(name))?;
+
+        let mut profiles = self.read_profiles()?;
+        if profiles.default.as_deref() == Some(name) {
+            profiles.default = None;
+            self.write_profiles(&profiles)?;
+        }
+        Ok(())
+    }
+
+    /// The profile to use when none is given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the profiles file can't be read.
+    pub fn default_profile(&self) -> Result<String> {
+        let profiles = self.read_profiles()?;
+        Ok(profiles
+            .default
+            .unwrap_or_else(|| DEFAULT_PROFILE.to_string()))
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the name isn't valid, the profile doesn't exist, or the
+    /// profiles file can't be written.
+    pub fn set_default_profile(&self, name: &str) -> Result<()> {
+        if !self.exists(name)? {
+            bail!("There's no profile named '{name}'.");
+        }
+
+        let mut profiles = self.read_profiles()?;
+        profiles.default = Some(name.to_string());
+        self.write_profiles(&profiles)
+    }
+}
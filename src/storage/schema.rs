@@ -0,0 +1,226 @@
+use crate::model::Model;
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// The version of the format the model is stored in. Bump this and add a migration to
+/// `MIGRATIONS` whenever a change to `Model` would stop older files from parsing.
+pub const SCHEMA_VERSION: u64 = 5;
+
+/// The key the schema version is stored under, next to the model's own fields.
+pub const VERSION_KEY: &str = "schema_version";
+
+/// Files written before the schema was versioned don't have a version key.
+const UNVERSIONED: u64 = 1;
+
+/// `MIGRATIONS[n]` migrates a model from version `n + 1` to version `n + 2`.
+const MIGRATIONS: [fn(&mut Map<String, Value>); 4] = [
+    add_removal_flags,
+    add_project_display_fields,
+    add_item_priorities,
+    add_item_labels,
+];
+
+/// The stored model was written by a newer version of the app.
+#[derive(Debug)]
+pub struct UnsupportedVersion {
+    pub found: u64,
+}
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The data file was written by a newer version of todoist-tui (schema version {}, but \
+             this version only supports up to {SCHEMA_VERSION}). Please upgrade todoist-tui.",
+            self.found
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+/// Parses a stored model, migrating it from older schema versions if needed.
+///
+/// # Errors
+///
+/// Returns an `UnsupportedVersion` error if the model was stored with a newer schema
+/// version than this version of the app knows about, or an error if it can't be parsed.
+pub fn from_stored(contents: &str) -> Result<Model> {
+    from_value(serde_json::from_str(contents)?)
+}
+
+/// Like `from_stored`, but for a model that has already been parsed as JSON.
+///
+/// # Errors
+///
+/// Returns an `UnsupportedVersion` error if the model was stored with a newer schema
+/// version than this version of the app knows about, or an error if it can't be parsed.
+pub fn from_value(mut value: Value) -> Result<Model> {
+    let fields = value
+        .as_object_mut()
+        .context("The stored model is not a JSON object")?;
+
+    let version = match fields.remove(VERSION_KEY) {
+        Some(version) => version
+            .as_u64()
+            .context("The stored schema version is not a number")?,
+        None => UNVERSIONED,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(UnsupportedVersion { found: version }.into());
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .skip(version.saturating_sub(1).try_into()?)
+    {
+        migration(fields);
+    }
+
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Serializes the model along with the current schema version.
+///
+/// # Errors
+///
+/// Returns an error if the model can't be serialized.
+pub fn to_stored(model: &Model) -> Result<String> {
+    let mut value = serde_json::to_value(model)?;
+    if let Some(fields) = value.as_object_mut() {
+        fields.insert(VERSION_KEY.to_owned(), SCHEMA_VERSION.into());
+    }
+    Ok(serde_json::to_string_pretty(&value)?)
+}
+
+/// Version 1 didn't store whether projects, sections, and items were deleted or archived,
+/// since those were never kept locally.
+fn add_removal_flags(model: &mut Map<String, Value>) {
+    let flags: [(&str, &[&str]); 3] = [
+        ("projects", &["is_deleted", "is_archived"]),
+        ("sections", &["is_deleted", "is_archived"]),
+        ("items", &["is_deleted"]),
+    ];
+
+    for (key, flags) in flags {
+        let Some(resources) = model.get_mut(key).and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for resource in resources.iter_mut().filter_map(Value::as_object_mut) {
+            for flag in flags {
+                resource.entry(*flag).or_insert(Value::Bool(false));
+            }
+        }
+    }
+}
+
+/// Version 2 didn't store how projects are shown in Todoist, so they get Todoist's defaults.
+fn add_project_display_fields(model: &mut Map<String, Value>) {
+    let inbox_id = model
+        .get("user")
+        .and_then(|user| user.get("inbox_project_id"))
+        .cloned();
+    let Some(projects) = model.get_mut("projects").and_then(Value::as_array_mut) else {
+        return;
+    };
+
+    for project in projects.iter_mut().filter_map(Value::as_object_mut) {
+        let is_inbox = inbox_id.is_some() && project.get("id") == inbox_id.as_ref();
+        project.entry("color").or_insert("charcoal".into());
+        project.entry("is_favorite").or_insert(false.into());
+        project.entry("view_style").or_insert("list".into());
+        project.entry("inbox_project").or_insert(is_inbox.into());
+    }
+}
+
+/// Version 3 didn't store the priorities of items, so they get Todoist's default.
+fn add_item_priorities(model: &mut Map<String, Value>) {
+    let Some(items) = model.get_mut("items").and_then(Value::as_array_mut) else {
+        return;
+    };
+
+    for item in items.iter_mut().filter_map(Value::as_object_mut) {
+        item.entry("priority").or_insert(1.into());
+    }
+}
+
+/// Version 4 didn't store the labels on items.
+fn add_item_labels(model: &mut Map<String, Value>) {
+    let Some(items) = model.get_mut("items").and_then(Value::as_array_mut) else {
+        return;
+    };
+
+    for item in items.iter_mut().filter_map(Value::as_object_mut) {
+        item.entry("labels").or_insert(Value::Array(vec![]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+    use crate::model::{item::Item, project::Project};
+
+    #[test]
+    fn migrate_unversioned_model() {
+        let model = Model {
+            projects: vec![Project::new("Project")],
+            items: vec![Item::new("Item", "MOCK_PROJECT_ID")],
+            ..Default::default()
+        };
+        let mut value = serde_json::to_value(&model).unwrap();
+        for key in ["projects", "items"] {
+            let resource = value[key][0].as_object_mut().unwrap();
+            for field in [
+                "is_deleted",
+                "is_archived",
+                "color",
+                "is_favorite",
+                "view_style",
+                "inbox_project",
+                "priority",
+                "labels",
+            ] {
+                resource.remove(field);
+            }
+        }
+
+        let migrated = from_stored(&value.to_string()).unwrap();
+
+        assert_eq!(migrated.projects[0].name, "Project");
+        assert!(!migrated.projects[0].is_archived);
+        assert_eq!(migrated.projects[0].color, "charcoal");
+        assert!(!migrated.projects[0].is_favorite);
+        assert_eq!(migrated.items[0].content, "Item");
+        assert!(!migrated.items[0].is_deleted);
+        assert_eq!(migrated.items[0].priority, 1);
+        assert!(migrated.items[0].labels.is_empty());
+    }
+
+    #[test]
+    fn round_trip_current_version() {
+        let model = Model {
+            items: vec![Item::new("Item", "MOCK_PROJECT_ID")],
+            ..Default::default()
+        };
+
+        let stored = to_stored(&model).unwrap();
+        assert!(stored.contains(&format!("\"schema_version\": {SCHEMA_VERSION}")));
+
+        let read = from_stored(&stored).unwrap();
+        assert_eq!(read.items[0].id, model.items[0].id);
+    }
+
+    #[test]
+    fn reject_future_version() {
+        let mut value = serde_json::to_value(Model::default()).unwrap();
+        value[VERSION_KEY] = (SCHEMA_VERSION + 1).into();
+
+        let err = from_stored(&value.to_string()).unwrap_err();
+
+        assert!(err.is::<UnsupportedVersion>());
+        assert!(err.to_string().contains("newer version of todoist-tui"));
+    }
+}
@@ -0,0 +1,374 @@
+use super::{
+    file_manager::{FileManager, Lock},
+    model_manager::ModelManager,
+    model_store::{ItemFilter, ItemSelection, ModelStore},
+    schema::{self, SCHEMA_VERSION, VERSION_KEY},
+};
+use crate::model::{
+    item::{Id as ItemId, Item},
+    Model,
+};
+use anyhow::{Context, Result};
+use rusqlite::{params, params_from_iter, Connection, Transaction};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{collections::HashSet, time::SystemTime};
+
+const DATABASE_FILE_NAME: &str = "sync.db";
+
+/// Each resource is stored as JSON, keyed by its id, and `position` keeps the resources
+/// in the same order as in the model.
+const CREATE_TABLES: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS projects (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS sections (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS items (
+        id TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS commands (
+        uuid TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+";
+
+/// Columns that are worked out from the JSON, so that todos can be looked up by them
+/// without parsing every row. They're added to the tables after they're created, so
+/// that databases from before there were any get them too.
+const LOOKUP_COLUMNS: [(&str, &str, &str); 5] = [
+    ("sections", "project_id", "$.project_id"),
+    ("items", "project_id", "$.project_id"),
+    ("items", "section_id", "$.section_id"),
+    ("items", "parent_id", "$.parent_id"),
+    ("items", "due", "$.due.date"),
+];
+
+const CREATE_INDEXES: &str = "
+    CREATE INDEX IF NOT EXISTS sections_by_project ON sections (project_id);
+    CREATE INDEX IF NOT EXISTS items_by_project ON items (project_id, section_id);
+    CREATE INDEX IF NOT EXISTS items_by_section ON items (section_id);
+    CREATE INDEX IF NOT EXISTS items_by_parent ON items (parent_id);
+    CREATE INDEX IF NOT EXISTS items_by_due ON items (due);
+";
+
+/// The tables that hold lists of resources, named after the corresponding fields of `Model`,
+/// and the column that each one is keyed by.
+const RESOURCE_TABLES: [(&str, &str); 4] = [
+    ("projects", "id"),
+    ("sections", "id"),
+    ("items", "id"),
+    ("commands", "uuid"),
+];
+
+/// Stores the model in a `SQLite` database in the data directory.
+pub struct SqliteStore<'a> {
+    file_manager: &'a FileManager,
+}
+
+impl<'a> SqliteStore<'a> {
+    /// Opens the database, creating it if needed. If the database doesn't have a model in it
+    /// yet but there's a model stored as JSON, that model is copied into the database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be opened or the JSON model can't be migrated.
+    pub fn open(file_manager: &'a FileManager) -> Result<Self> {
+        let store = Self { file_manager };
+        let connection = store.connect()?;
+        connection.execute_batch(CREATE_TABLES)?;
+        add_lookup_columns(&connection)?;
+        connection.execute_batch(CREATE_INDEXES)?;
+
+        let json_store = ModelManager::new(file_manager);
+        if json_store.last_modified().is_some() {
+            let _lock = store.lock()?;
+            if !store.has_model()? {
+                let model = json_store
+                    .read_model()
+                    .context("Could not migrate the stored model to SQLite.")?;
+                store.write_model(&model)?;
+            }
+        }
+
+        Ok(store)
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        Connection::open(self.file_manager.data_path(DATABASE_FILE_NAME.into()))
+            .context("Could not open the model database.")
+    }
+
+    fn has_model(&self) -> Result<bool> {
+        let count: usize = self
+            .connect()?
+            .query_row("SELECT COUNT(*) FROM meta", [], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+}
+
+impl ModelStore for SqliteStore<'_> {
+    /// Reads the model from the database. Models stored with an older schema version
+    /// are migrated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read, or if it was written by a newer
+    /// version of the app.
+    fn read_model(&self) -> Result<Model> {
+        let connection = self.connect()?;
+        let mut fields = read_meta(&connection)?;
+        if fields.is_empty() {
+            return Ok(Model::default());
+        }
+
+        for (table, _) in RESOURCE_TABLES {
+            fields.insert(table.to_owned(), read_table(&connection, table)?);
+        }
+
+        schema::from_value(Value::Object(fields)).context("Could not parse the model database.")
+    }
+
+    /// Reads everything but the todos as usual, then only reads the todos that the filter
+    /// picks out (and their ancestors), using the lookup columns.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database can't be read, or if it was written by a newer
+    /// version of the app.
+    fn read_model_with_items(
+        &self,
+        filter: &dyn Fn(&Model) -> ItemFilter,
+    ) -> Result<ItemSelection> {
+        let connection = self.connect()?;
+        let mut fields = read_meta(&connection)?;
+
+        // todos stored with an older schema have to be migrated along with everything else
+        if fields.get(VERSION_KEY).and_then(Value::as_u64) != Some(SCHEMA_VERSION) {
+            return Ok(self.read_model()?.into());
+        }
+
+        for (table, _) in RESOURCE_TABLES {
+            let resources = if table == "items" {
+                Value::Array(vec![])
+            } else {
+                read_table(&connection, table)?
+            };
+            fields.insert(table.to_owned(), resources);
+        }
+        let model: Model = schema::from_value(Value::Object(fields))
+            .context("Could not parse the model database.")?;
+
+        let (conditions, values) = filter_conditions(&filter(&model));
+        let mut select = connection.prepare(&format!(
+            "WITH RECURSIVE listed (id) AS ( \
+                 SELECT id FROM items WHERE {conditions} \
+                 UNION \
+                 SELECT items.parent_id FROM items JOIN listed ON items.id = listed.id \
+                 WHERE items.parent_id IS NOT NULL \
+             ) \
+             SELECT data FROM items WHERE id IN listed ORDER BY position"
+        ))?;
+        let items = select
+            .query_map(params_from_iter(values), |row| row.get::<_, String>(0))?
+            .map(|data| Ok(serde_json::from_str::<Item>(&data?)?))
+            .collect::<Result<Vec<Item>>>()
+            .context("Could not parse the model database.")?;
+
+        let item_ids = connection
+            .prepare("SELECT id FROM items")?
+            .query_map([], |row| Ok(row.get::<_, String>(0)?.as_str().into()))?
+            .collect::<rusqlite::Result<Vec<ItemId>>>()?;
+
+        Ok(ItemSelection {
+            user: model.user,
+            projects: model.projects,
+            sections: model.sections,
+            items,
+            item_ids,
+        })
+    }
+
+    /// Replaces the model in the database, only writing the rows that changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if something goes wrong while writing to the database, in which
+    /// case the previously stored model is left as it was.
+    fn write_model(&self, model: &Model) -> Result<()> {
+        let mut connection = self.connect()?;
+        let transaction = connection.transaction()?;
+
+        let mut upsert_meta = transaction.prepare(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2) \
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value \
+             WHERE value != excluded.value",
+        )?;
+        upsert_meta.execute(params![VERSION_KEY, SCHEMA_VERSION.to_string()])?;
+        upsert_meta.execute(params![
+            "sync_token",
+            serde_json::to_string(&model.sync_token)?
+        ])?;
+        upsert_meta.execute(params!["user", serde_json::to_string(&model.user)?])?;
+        drop(upsert_meta);
+
+        let rows = [
+            rows(&model.projects, |project| project.id.to_string())?,
+            rows(&model.sections, |section| section.id.to_string())?,
+            rows(&model.items, |item| item.id.to_string())?,
+            rows(&model.commands, |command| command.uuid.to_string())?,
+        ];
+        for ((table, key), rows) in RESOURCE_TABLES.into_iter().zip(rows) {
+            write_rows(&transaction, table, key, &rows)?;
+        }
+
+        transaction
+            .commit()
+            .context("Could not write to the model database.")?;
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<Lock> {
+        self.file_manager.lock()
+    }
+
+    fn try_lock(&self) -> Result<Option<Lock>> {
+        self.file_manager.try_lock()
+    }
+
+    /// When the database was last written to, or `None` if there isn't one.
+    fn last_modified(&self) -> Option<SystemTime> {
+        self.file_manager.modified(DATABASE_FILE_NAME.into())
+    }
+}
+
+/// Adds the lookup columns that a table doesn't have yet. They're generated from the JSON
+/// by `SQLite`, so they can't disagree with it.
+fn add_lookup_columns(connection: &Connection) -> Result<()> {
+    for (table, column, path) in LOOKUP_COLUMNS {
+        // generated columns are hidden from `table_info`, but not from `table_xinfo`
+        let exists: bool = connection.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_xinfo(?1) WHERE name = ?2",
+            params![table, column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            connection.execute_batch(&format!(
+                "ALTER TABLE {table} ADD COLUMN {column} TEXT \
+                 GENERATED ALWAYS AS (json_extract(data, '{path}')) VIRTUAL"
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+/// The stored fields of the model that aren't lists of resources, like the sync token.
+fn read_meta(connection: &Connection) -> Result<Map<String, Value>> {
+    let mut fields = Map::new();
+    let mut select_meta = connection.prepare("SELECT key, value FROM meta")?;
+    let meta = select_meta.query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?;
+    for row in meta {
+        let (key, value) = row?;
+        fields.insert(key, serde_json::from_str(&value)?);
+    }
+    Ok(fields)
+}
+
+/// All of the resources in a table, in order, as a JSON array.
+fn read_table(connection: &Connection, table: &str) -> Result<Value> {
+    let mut select = connection.prepare(&format!("SELECT data FROM {table} ORDER BY position"))?;
+    let resources = select
+        .query_map([], |row| row.get::<_, String>(0))?
+        .map(|data| Ok(serde_json::from_str(&data?)?))
+        .collect::<Result<Vec<Value>>>()?;
+    Ok(Value::Array(resources))
+}
+
+/// The `WHERE` conditions for the todos that a filter picks out, and the values for
+/// their parameters.
+fn filter_conditions(filter: &ItemFilter) -> (String, Vec<String>) {
+    let mut conditions = vec!["1".to_owned()];
+    let mut values = vec![];
+
+    let id_lists = [
+        ("project_id", filter.project_ids.as_deref().map(to_strings)),
+        ("section_id", filter.section_ids.as_deref().map(to_strings)),
+    ];
+    for (column, ids) in id_lists {
+        if let Some(ids) = ids {
+            let placeholders = vec!["?"; ids.len()].join(", ");
+            conditions.push(format!("{column} IN ({placeholders})"));
+            values.extend(ids);
+        }
+    }
+
+    // a due time still starts with its date, so dates and times compare as text
+    if let Some(due_from) = filter.due_from {
+        conditions.push("due >= ?".to_owned());
+        values.push(due_from.to_string());
+    }
+    if let Some(day_after) = filter.due_until.and_then(|due_until| due_until.succ_opt()) {
+        conditions.push("due < ?".to_owned());
+        values.push(day_after.to_string());
+    }
+
+    (conditions.join(" AND "), values)
+}
+
+fn to_strings<T: ToString>(ids: &[T]) -> Vec<String> {
+    ids.iter().map(ToString::to_string).collect()
+}
+
+/// Each resource's key, paired with the resource as JSON.
+fn rows<T: Serialize>(
+    resources: &[T],
+    key: impl Fn(&T) -> String,
+) -> Result<Vec<(String, String)>> {
+    resources
+        .iter()
+        .map(|resource| Ok((key(resource), serde_json::to_string(resource)?)))
+        .collect()
+}
+
+/// Makes a table hold exactly the given rows, in order, leaving the rows that are already
+/// stored as they are alone.
+fn write_rows(
+    transaction: &Transaction<'_>,
+    table: &str,
+    key: &str,
+    rows: &[(String, String)],
+) -> Result<()> {
+    let keys: HashSet<&str> = rows.iter().map(|(key, _)| key.as_str()).collect();
+    let stored_keys = transaction
+        .prepare(&format!("SELECT {key} FROM {table}"))?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    let mut delete = transaction.prepare(&format!("DELETE FROM {table} WHERE {key} = ?1"))?;
+    for stored_key in stored_keys {
+        if !keys.contains(stored_key.as_str()) {
+            delete.execute(params![stored_key])?;
+        }
+    }
+
+    let mut upsert = transaction.prepare(&format!(
+        "INSERT INTO {table} ({key}, position, data) VALUES (?1, ?2, ?3) \
+         ON CONFLICT ({key}) DO UPDATE SET position = excluded.position, data = excluded.data \
+         WHERE position != excluded.position OR data != excluded.data"
+    ))?;
+    for (position, (key, data)) in rows.iter().enumerate() {
+        upsert.execute(params![key, position, data])?;
+    }
+    Ok(())
+}
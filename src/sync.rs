@@ -10,14 +10,13 @@ pub mod client;
 pub struct Response {
     pub sync_token: String,
 
-    #[serde(default)]
-    pub projects: Vec<Project>,
+    // the resource lists are `None` when that resource type wasn't included in the response,
+    // which is not the same as the server sending back an empty list
+    pub projects: Option<Vec<Project>>,
 
-    #[serde(default)]
-    pub items: Vec<Item>,
+    pub items: Option<Vec<Item>>,
 
-    #[serde(default)]
-    pub sections: Vec<Section>,
+    pub sections: Option<Vec<Section>>,
 
     pub user: Option<User>,
 
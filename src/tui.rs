@@ -1,10 +1,17 @@
-use self::{app::App, app_state::Mode};
+use self::{
+    app::App,
+    app_state::Mode,
+    background_sync::BackgroundSync,
+    keymap::{Action, Keymap},
+    stored_model::StoredModelWatcher,
+    theme::Theme,
+};
 use crate::{
-    cli,
-    storage::model_manager::ModelManager,
-    sync::{client::Client, Request, ResourceType, Response},
+    cli::{self, output::OutputFormat},
+    storage::{config_manager::Config, model_store::ModelStore},
+    sync::client::Client,
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use crossterm::{
     event::{self, poll, Event},
     execute,
@@ -13,58 +20,85 @@ use crossterm::{
 use ratatui::{prelude::CrosstermBackend, Terminal};
 use std::{
     io::{self, Stdout},
-    sync::mpsc,
     time::Duration,
 };
 
 pub mod app;
 pub mod app_state;
+mod background_sync;
 mod item_input;
+pub mod keymap;
+mod stored_model;
+pub mod theme;
 mod ui;
 mod widgets;
 
+/// Checks the settings that only the TUI uses: the keybindings and the theme.
+///
 /// # Errors
-/// Returns an error if something goes wrong during the TUI setup, execution, or teardown.
+/// Returns an error that names the first bad setting.
+pub fn check_config(config: &Config) -> Result<()> {
+    Keymap::from_bindings(&config.keybindings).context("Invalid value for 'keybindings'")?;
+    Theme::named(&config.theme, &config.themes).context("Invalid value for 'theme'")?;
+    Ok(())
+}
+
+/// The default keys of a TUI action, by its name, the way they'd be written in the
+/// `keybindings` setting.
 ///
-/// # Panics
-/// This spawn a thread that can panic if something goes wrong while retreving data from the API.
-pub async fn run(model_manager: ModelManager<'_>, client: Result<Client>) -> Result<()> {
-    let mut model = model_manager.read_model()?;
-    let (sender, receiver) = mpsc::channel::<Response>();
-    if let Ok(ref client) = client {
-        let client = (*client).clone();
-        let commands = model.commands.clone();
-        let sync_token = model.sync_token.clone();
-        tokio::spawn(async move {
-            let request = Request {
-                sync_token,
-                resource_types: ResourceType::all(),
-                commands,
-            };
-
-            // FIXME: Need a way (maybe another channel) to communicate to the UI
-            // that the sync failed
-            let response = client
-                .make_request(&request)
-                .await
-                .expect("Error occurred during full sync.");
-            sender
-                .send(response)
-                .expect("Error occurred while processing server response.");
-        });
-    }
+/// # Errors
+/// Returns an error if there's no action with that name.
+pub fn default_keys(action: &str) -> Result<String> {
+    let action: Action = action.parse()?;
+    Ok(action.default_keys().join(" "))
+}
 
-    let receiver = &receiver;
-    let mut app = App::new(&mut model);
+/// # Errors
+/// Returns an error if something goes wrong during the TUI setup, execution, or teardown.
+pub async fn run(
+    model_store: &dyn ModelStore,
+    client: Result<Client>,
+    config: Config,
+    profile: Option<&str>,
+) -> Result<()> {
+    let sync_interval = Duration::from_secs(config.sync_interval);
+    let (mut watcher, mut model) = StoredModelWatcher::read(model_store)?;
+    let mut background_sync = client
+        .as_ref()
+        .ok()
+        .map(|client| BackgroundSync::spawn(client.clone()));
+
+    let mut app = App::new(&mut model).config(config);
+    if let Some(profile) = profile {
+        app = app.profile(profile);
+    }
 
     let mut terminal = setup_terminal()?;
-    run_main_loop(&mut terminal, &mut app, receiver)?;
+    run_main_loop(
+        &mut terminal,
+        &mut app,
+        model_store,
+        &mut watcher,
+        background_sync.as_mut(),
+        sync_interval,
+    )?;
     restore_terminal(&mut terminal)?;
 
+    // save everything first, so that nothing is lost if the sync doesn't work out (this
+    // doesn't clobber anything that another process saved while we were running)
+    let _lock = model_store.lock()?;
+    watcher.write_model(model_store, &mut model)?;
+
     if !model.commands.is_empty() {
-        cli::sync(&mut model, &client?, true).await?;
+        let synced = match client {
+            Ok(client) => cli::sync(&mut model, &client, true, OutputFormat::Text).await,
+            Err(err) => Err(err),
+        };
+        match synced {
+            Ok(_) => model_store.write_model(&model)?,
+            Err(err) => cli::warn_sync_failed(&err, &model),
+        }
     }
-    model_manager.write_model(&model)?;
     Ok(())
 }
 
@@ -92,7 +126,10 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result
 fn run_main_loop(
     terminal: &mut Terminal<CrosstermBackend<Stdout>>,
     app: &mut App<'_>,
-    receiver: &mpsc::Receiver<Response>,
+    model_store: &dyn ModelStore,
+    watcher: &mut StoredModelWatcher,
+    mut background_sync: Option<&mut BackgroundSync>,
+    sync_interval: Duration,
 ) -> Result<()> {
     loop {
         // render
@@ -107,12 +144,42 @@ fn run_main_loop(
                     return Ok(());
                 }
             }
-        } else {
-            // check if the receiver received anything
-            if let Ok(response) = receiver.try_recv() {
-                app.model.update(response);
-                app.update_state();
+            continue;
+        }
+
+        // merge in changes that another process made to the stored model. if that process
+        // is still busy with it, or the merge doesn't work out, we'll just try again later
+        if watcher.has_changed(model_store) {
+            if let Ok(Some(_lock)) = model_store.try_lock() {
+                if watcher.merge_changes(model_store, app.model).is_ok() {
+                    // the other process might have synced, in which case we'll want to catch up
+                    app.state.sync_requested = true;
+                }
+            }
+        }
+
+        if let Some(ref mut background_sync) = background_sync {
+            // check if the last sync finished. if it failed, the next one is still started at
+            // the usual interval
+            // (the UI state gets reconciled with the updated model during the next render)
+            match background_sync.try_recv() {
+                Some(Ok(response)) => {
+                    app.receive_sync(response);
+
+                    // save what the sync brought in. if another process has the data directory
+                    // locked, or the write doesn't work out, it's saved after the next one
+                    if let Ok(Some(_lock)) = model_store.try_lock() {
+                        let _ = watcher.write_model(model_store, app.model);
+                    }
+                }
+                Some(Err(err)) => app.sync_failed(&err),
+                None => {}
+            }
+
+            if app.state.sync_requested || background_sync.is_due(sync_interval) {
+                app.state.sync_requested = false;
+                background_sync.start(app.model);
             }
-        } // process input
+        }
     }
 }
@@ -1,12 +1,18 @@
 use super::{
     app_state::{AppState, Mode},
     item_input::ItemInput,
+    keymap::{Action, Keymap, PendingKeys},
+    theme::Theme,
     ui::centered_rect,
     widgets::{items, key_hints, projects},
 };
-use crate::model::{project::Project, Model};
+use crate::{
+    model::{project::Project, Model},
+    storage::config_manager::Config,
+    sync::Response,
+};
 use chrono::{Local, NaiveDate};
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::event::{self, Event};
 use ratatui::{
     prelude::{Constraint, Direction, Layout},
     Frame,
@@ -31,6 +37,14 @@ impl<'a> App<'a> {
             projects: projects_state,
             mode: Mode::SelectingItems,
             items: items_state,
+            sync_requested: false,
+            profile: None,
+            config: Config::default(),
+            keymap: Keymap::default(),
+            pending_keys: PendingKeys::default(),
+            theme: Theme::default(),
+            error: None,
+            sync_error: None,
         };
 
         Self {
@@ -48,6 +62,23 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Uses the user's settings instead of the defaults.
+    #[must_use]
+    pub fn config(mut self, config: Config) -> Self {
+        // the keybindings and theme were checked when the config was read
+        self.state.keymap = Keymap::from_bindings(&config.keybindings).unwrap_or_default();
+        self.state.theme = Theme::from_env(&config.theme, &config.themes).unwrap_or_default();
+        self.state.config = config;
+        self
+    }
+
+    /// Shows the name of the open profile.
+    #[must_use]
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.state.profile = Some(profile.into());
+        self
+    }
+
     fn selected_project(&self) -> Option<&Project> {
         self.state
             .projects
@@ -55,73 +86,113 @@ impl<'a> App<'a> {
             .and_then(|id| self.model.project_with_id(&id))
     }
 
-    /// Updates the inner state of model after the model changes.
-    pub fn update_state(&mut self) {
-        let item_state = items::State::default();
+    /// Updates the model with the response from a sync, keeping the same todos selected even
+    /// if the sync gave them their real ids.
+    pub fn receive_sync(&mut self, response: Response) {
+        // changes made while the sync was in flight (like undoing one of the changes it sent)
+        // go out right away instead of waiting for the next one
+        let queued_meanwhile = self
+            .model
+            .commands
+            .iter()
+            .any(|command| !self.model.commands_in_flight.contains(&command.uuid));
 
-        self.state.items = item_state;
+        self.state.items.replace_temp_ids(&response.temp_id_mapping);
+        self.model.update(response);
+        self.state.sync_error = None;
+        self.state.sync_requested |= queued_meanwhile;
+    }
+
+    /// Shows why a sync failed. The pending changes are still in the model, so they're sent
+    /// again with the next sync.
+    pub fn sync_failed(&mut self, error: &anyhow::Error) {
+        self.model.commands_in_flight.clear();
+        self.state.sync_error = Some(format!("Sync failed: {error:#}"));
     }
 
     /// Manages how the whole app reacts to an individual user keypress.
     // TODO: move into `app_state` module
     pub fn handle_key(&mut self, key: event::KeyEvent) {
+        let (action, count) =
+            match self
+                .state
+                .keymap
+                .action(&self.state.mode, &mut self.state.pending_keys, key)
+            {
+                Some((action, count)) => (Some(action), count),
+                None => (None, 1),
+            };
+
         match self.state.mode {
-            Mode::SelectingItems => match key.code {
-                KeyCode::Char('a') => {
+            Mode::SelectingItems => match action {
+                Some(Action::AddItem) => {
                     self.state.mode = Mode::AddingItem;
                 }
-                KeyCode::Char('q') => {
+                Some(Action::Quit) => {
                     self.state.mode = Mode::Exiting;
                 }
-                KeyCode::Tab => {
+                Some(Action::Sync) => {
+                    self.state.sync_requested = true;
+                }
+                Some(Action::SwitchFocus) => {
                     self.state.mode = Mode::SelectingProjects;
                 }
-                KeyCode::Char(' ') => {
+                Some(Action::ToggleComplete) => {
                     if let Some(item_id) = self.state.items.selected_item_id() {
-                        // FIXME: toggle, don't always set to true
-                        self.model.mark_item(&item_id.clone(), true);
-                        self.update_state();
+                        let checked = self
+                            .model
+                            .items
+                            .iter()
+                            .any(|item| item.id == item_id && item.checked);
+                        self.model.mark_item(&item_id, !checked);
                     }
                 }
-                _ => {
-                    self.state.items.handle_key(key);
-                }
+                Some(action) => self.handle_item_action(action, count),
+                None => {}
             },
-            Mode::SelectingProjects => {
-                match key.code {
-                    KeyCode::Char('a') => {
-                        self.state.mode = Mode::AddingItem;
-                    }
-                    KeyCode::Char('q') => {
-                        self.state.mode = Mode::Exiting;
-                    }
-                    KeyCode::Tab => {
-                        self.state.mode = Mode::SelectingItems;
-                    }
-                    _ => {
-                        self.state.projects.handle_key(key);
-                    }
+            Mode::SelectingProjects => match action {
+                Some(Action::AddItem) => {
+                    self.state.mode = Mode::AddingItem;
+                }
+                Some(Action::Quit) => {
+                    self.state.mode = Mode::Exiting;
+                }
+                Some(Action::Sync) => {
+                    self.state.sync_requested = true;
                 }
-                self.update_state();
-            }
-            Mode::AddingItem => match key.code {
-                KeyCode::Esc => {
+                Some(Action::SwitchFocus) => {
+                    self.state.mode = Mode::SelectingItems;
+                }
+                Some(action) => self.handle_project_action(action, count),
+                None => {}
+            },
+            Mode::AddingItem => match action {
+                Some(Action::Cancel) => {
                     self.state.mode = Mode::SelectingItems;
+                    self.state.error = None;
                     self.item_input.reset();
                 }
-                KeyCode::Enter => {
+                Some(Action::Confirm) => {
                     let project_id = self
                         .selected_project()
                         .unwrap_or_else(|| self.model.inbox_project())
                         .id
                         .clone();
 
-                    let (content, due_date) = self.item_input.get_new_item();
-
-                    self.model.add_item(content.trim(), project_id, due_date);
-                    self.update_state();
-                    self.state.mode = Mode::SelectingItems;
-                    self.item_input.reset();
+                    match self
+                        .item_input
+                        .get_new_item()
+                        .to_item(self.model, &project_id)
+                    {
+                        Ok(item) => {
+                            self.model.add_item(item);
+                            self.state.mode = Mode::SelectingItems;
+                            self.state.error = None;
+                            self.item_input.reset();
+                        }
+                        // the input stays open so that what was typed can be fixed
+                        Err(err) => self.state.error = Some(err.to_string()),
+                    }
                 }
                 _ => {
                     self.item_input.handle_event(&Event::Key(key));
@@ -131,6 +202,36 @@ impl<'a> App<'a> {
         }
     }
 
+    /// Moves around the todos (or expands and collapses them), saving the expanded/collapsed
+    /// state so that it persists (and shows up in Todoist).
+    fn handle_item_action(&mut self, action: Action, count: usize) {
+        let Some(project_id) = self.state.projects.selected_id() else {
+            return;
+        };
+        let collapse_changes = self.state.items.handle_action(
+            action,
+            count,
+            &project_id,
+            self.model,
+            &self.state.config,
+            &self.state.theme,
+        );
+        for (item_id, collapsed) in collapse_changes {
+            self.model.set_item_collapsed(&item_id, collapsed);
+        }
+    }
+
+    /// Like `handle_item_action`, but for the projects.
+    fn handle_project_action(&mut self, action: Action, count: usize) {
+        if let Some((project_id, collapsed)) =
+            self.state
+                .projects
+                .handle_action(action, count, self.model, &self.state.theme)
+        {
+            self.model.set_project_collapsed(&project_id, collapsed);
+        }
+    }
+
     /// Renders the app state into a terminal frame.
     pub fn render(&mut self, frame: &mut Frame<'_>) {
         let layout = Layout::default()
@@ -151,14 +252,14 @@ impl<'a> App<'a> {
         frame.render_stateful_widget(
             projects::Widget::default(),
             main_left,
-            &mut (&mut self.state, self.model),
+            &mut (&mut self.state, &*self.model),
         );
 
         // item list
         frame.render_stateful_widget(
             items::Widget::default(),
             main_right,
-            &mut (&mut self.state, self.model),
+            &mut (&mut self.state, &*self.model),
         );
 
         // key hints
@@ -167,7 +268,10 @@ impl<'a> App<'a> {
         // input bar (if adding something)
         if self.state.mode == Mode::AddingItem {
             let input_rect = centered_rect(frame.size(), 50, 3, Some(2));
-            frame.render_widget(self.item_input.clone(), input_rect);
+            frame.render_widget(
+                self.item_input.clone().theme(self.state.theme.clone()),
+                input_rect,
+            );
             let cursor_position = self.item_input.cursor_position(input_rect);
             frame.set_cursor(cursor_position.0, cursor_position.1);
         }
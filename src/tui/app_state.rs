@@ -1,4 +1,9 @@
-use super::widgets::{items, projects};
+use super::{
+    keymap::{Keymap, PendingKeys},
+    theme::Theme,
+    widgets::{items, projects},
+};
+use crate::storage::config_manager::Config;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum Mode {
@@ -12,4 +17,28 @@ pub struct AppState {
     pub mode: Mode,
     pub projects: projects::State,
     pub items: items::State,
+
+    /// Set when the user asks for a sync, which is started (and this is reset) by the main loop
+    pub sync_requested: bool,
+
+    /// The name of the profile that's open, if it should be shown
+    pub profile: Option<String>,
+
+    /// The user's settings
+    pub config: Config,
+
+    /// Which keys do what, from the user's settings
+    pub keymap: Keymap,
+
+    /// The start of a count or key sequence that's being typed
+    pub pending_keys: PendingKeys,
+
+    /// The styles to draw everything with
+    pub theme: Theme,
+
+    /// A problem to show next to the key hints, eg. why a todo couldn't be added
+    pub error: Option<String>,
+
+    /// Why the last background sync failed, which is shown until one works again
+    pub sync_error: Option<String>,
 }
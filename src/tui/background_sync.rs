@@ -0,0 +1,87 @@
+use crate::{
+    model::Model,
+    sync::{client::Client, Request, ResourceType, Response},
+};
+use anyhow::Result;
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc as async_mpsc;
+
+/// Runs syncs with the Todoist API on a separate task so that the UI doesn't block while
+/// waiting for the server.
+pub struct BackgroundSync {
+    request_sender: async_mpsc::UnboundedSender<Request>,
+    response_receiver: mpsc::Receiver<Result<Response>>,
+
+    /// Whether we're still waiting on a response to a sync that was started
+    in_flight: bool,
+
+    /// When the most recent sync was started, or `None` if there hasn't been one yet
+    last_sync: Option<Instant>,
+}
+
+impl BackgroundSync {
+    /// Spawns the task that makes requests to the API.
+    pub fn spawn(client: Client) -> Self {
+        let (request_sender, mut request_receiver) = async_mpsc::unbounded_channel::<Request>();
+        let (response_sender, response_receiver) = mpsc::channel::<Result<Response>>();
+
+        tokio::spawn(async move {
+            while let Some(request) = request_receiver.recv().await {
+                let response = client.make_request(&request).await;
+
+                // if the receiver is gone, the UI has shut down and we can stop too
+                if response_sender.send(response).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            request_sender,
+            response_receiver,
+            in_flight: false,
+            last_sync: None,
+        }
+    }
+
+    /// Whether it's been long enough since the last sync that we should start another.
+    pub fn is_due(&self, interval: Duration) -> bool {
+        match self.last_sync {
+            Some(last_sync) => last_sync.elapsed() >= interval,
+            None => true,
+        }
+    }
+
+    /// Starts an incremental sync that sends the model's pending commands,
+    /// unless there's already one in progress. The commands are marked as in flight
+    /// until the model is updated with the response.
+    pub fn start(&mut self, model: &mut Model) {
+        if self.in_flight {
+            return;
+        }
+
+        let request = Request {
+            sync_token: model.sync_token.clone(),
+            resource_types: ResourceType::all(),
+            commands: model.commands.clone(),
+        };
+
+        self.in_flight = self.request_sender.send(request).is_ok();
+        if self.in_flight {
+            model.commands_in_flight = model.commands.iter().map(|command| command.uuid).collect();
+        }
+        self.last_sync = Some(Instant::now());
+    }
+
+    /// Returns the result of the sync in progress, if it has finished.
+    pub fn try_recv(&mut self) -> Option<Result<Response>> {
+        let result = self.response_receiver.try_recv().ok();
+        if result.is_some() {
+            self.in_flight = false;
+        }
+        result
+    }
+}
@@ -1,9 +1,9 @@
-use crate::model::due_date::Due;
+use super::theme::Theme;
+use crate::model::quick_add::QuickAdd;
 use chrono::NaiveDate;
 use crossterm::event::Event;
 use ratatui::{
     prelude::Rect,
-    style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Clear, Paragraph, Widget},
 };
@@ -13,6 +13,7 @@ use tui_input::{backend::crossterm::EventHandler, Input};
 pub struct ItemInput {
     input: Input,
     today: NaiveDate,
+    theme: Theme,
 }
 
 impl ItemInput {
@@ -23,6 +24,13 @@ impl ItemInput {
         }
     }
 
+    /// Draws the input with the given theme.
+    #[must_use]
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
     pub fn reset(&mut self) {
         self.input.reset();
     }
@@ -40,48 +48,28 @@ impl ItemInput {
         )
     }
 
-    pub fn get_new_item(&self) -> (String, Option<Due>) {
-        // process the input to maybe find a date
-        let input = self.input.value();
-        let due_date = Due::parse_from_str(input, self.today);
-
-        // if a date was found, remove the matched string from the text content of the new item
-        let content = if let Some((_, ref range)) = due_date {
-            format!(
-                "{}{}",
-                &input[0..range.start],
-                &input[input.len().min(range.end + 1)..input.len()]
-            )
-        } else {
-            input.to_string()
-        };
-
-        let due = due_date.map(|(date, _)| date);
-        (content, due)
+    /// The new todo as it's been typed, with its date, project, etc. picked out.
+    pub fn get_new_item(&self) -> QuickAdd {
+        QuickAdd::parse(self.input.value(), Some(self.today))
     }
 }
 
 impl Widget for ItemInput {
     fn render(self, area: Rect, buf: &mut ratatui::prelude::Buffer) {
-        // preprocess the current input string to see if there's a date inside
-        let due_date = Due::parse_from_str(self.input.value(), self.today);
-        let input_widget = if let Some((_, range)) = due_date {
-            let (before, after) = self.input.value().split_at(range.start);
-            let (date, after) = after.split_at(range.end - before.len());
-            let line = Line::from(vec![
-                Span::styled(before, Style::default().fg(Color::White)),
-                Span::styled(
-                    date,
-                    Style::default()
-                        .fg(Color::Magenta)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                Span::styled(after, Style::default().fg(Color::White)),
-            ]);
-            Paragraph::new(line)
-        } else {
-            Paragraph::new(self.input.value())
-        };
+        // highlight the date and the other parts of the input that were recognized
+        let input = self.input.value();
+        let mut spans = vec![];
+        let mut end = 0;
+        for range in self.get_new_item().ranges {
+            spans.push(Span::styled(&input[end..range.start], self.theme.text));
+            spans.push(Span::styled(
+                &input[range.clone()],
+                self.theme.date_highlight,
+            ));
+            end = range.end;
+        }
+        spans.push(Span::styled(&input[end..], self.theme.text));
+        let input_widget = Paragraph::new(Line::from(spans));
 
         // figure the right amount to scroll the input by
         let input_scroll = self.input.visual_scroll(area.width as usize - 2);
@@ -89,7 +77,7 @@ impl Widget for ItemInput {
         let input_widget = input_widget.scroll((0, input_scroll as u16)).block(
             Block::default()
                 .title("New Todo")
-                .border_style(Style::default().fg(Color::Yellow))
+                .border_style(self.theme.focused_border)
                 .borders(Borders::ALL),
         );
 
@@ -0,0 +1,576 @@
+use super::app_state::Mode;
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+/// Something the user can do in the TUI by pressing a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    Quit,
+    AddItem,
+    ToggleComplete,
+    Sync,
+    SwitchFocus,
+    MoveUp,
+    MoveDown,
+    MoveToTop,
+    MoveToBottom,
+    HalfPageUp,
+    HalfPageDown,
+    Collapse,
+    Expand,
+    ToggleExpanded,
+    CollapseSubtree,
+    ExpandSubtree,
+    CollapseAll,
+    ExpandAll,
+    Confirm,
+    Cancel,
+}
+
+impl Action {
+    pub const ALL: [Action; 20] = [
+        Action::Quit,
+        Action::AddItem,
+        Action::ToggleComplete,
+        Action::Sync,
+        Action::SwitchFocus,
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveToTop,
+        Action::MoveToBottom,
+        Action::HalfPageUp,
+        Action::HalfPageDown,
+        Action::Collapse,
+        Action::Expand,
+        Action::ToggleExpanded,
+        Action::CollapseSubtree,
+        Action::ExpandSubtree,
+        Action::CollapseAll,
+        Action::ExpandAll,
+        Action::Confirm,
+        Action::Cancel,
+    ];
+
+    /// The name used for this action in the `keybindings` setting.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::AddItem => "add_item",
+            Action::ToggleComplete => "toggle_complete",
+            Action::Sync => "sync",
+            Action::SwitchFocus => "switch_focus",
+            Action::MoveUp => "move_up",
+            Action::MoveDown => "move_down",
+            Action::MoveToTop => "move_to_top",
+            Action::MoveToBottom => "move_to_bottom",
+            Action::HalfPageUp => "half_page_up",
+            Action::HalfPageDown => "half_page_down",
+            Action::Collapse => "collapse",
+            Action::Expand => "expand",
+            Action::ToggleExpanded => "toggle_expanded",
+            Action::CollapseSubtree => "collapse_subtree",
+            Action::ExpandSubtree => "expand_subtree",
+            Action::CollapseAll => "collapse_all",
+            Action::ExpandAll => "expand_all",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+        }
+    }
+
+    /// The keys for this action when the `keybindings` setting doesn't change them, written
+    /// the way they are in that setting.
+    #[must_use]
+    pub fn default_keys(self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["q"],
+            Action::AddItem => &["a"],
+            Action::ToggleComplete => &["space"],
+            Action::Sync => &["r"],
+            Action::SwitchFocus => &["tab"],
+            Action::MoveUp => &["up", "k"],
+            Action::MoveDown => &["down", "j"],
+            Action::MoveToTop => &["gg"],
+            Action::MoveToBottom => &["G"],
+            Action::HalfPageUp => &["ctrl-u"],
+            Action::HalfPageDown => &["ctrl-d"],
+            Action::Collapse => &["left", "h"],
+            Action::Expand => &["right", "l"],
+            Action::ToggleExpanded => &["enter", "space"],
+            Action::CollapseSubtree => &["zc"],
+            Action::ExpandSubtree => &["zo"],
+            Action::CollapseAll => &["zM"],
+            Action::ExpandAll => &["zR"],
+            Action::Confirm => &["enter"],
+            Action::Cancel => &["escape"],
+        }
+    }
+
+    /// The actions that can be taken in a mode, in the order they're checked against a key
+    /// press. That way one key can do different things in different modes (eg. space marks
+    /// a todo complete, but expands a project).
+    #[must_use]
+    pub fn in_mode(mode: &Mode) -> &'static [Action] {
+        match mode {
+            Mode::SelectingItems => &[
+                Action::Quit,
+                Action::AddItem,
+                Action::ToggleComplete,
+                Action::Sync,
+                Action::SwitchFocus,
+                Action::MoveUp,
+                Action::MoveDown,
+                Action::MoveToTop,
+                Action::MoveToBottom,
+                Action::HalfPageUp,
+                Action::HalfPageDown,
+                Action::Collapse,
+                Action::Expand,
+                Action::ToggleExpanded,
+                Action::CollapseSubtree,
+                Action::ExpandSubtree,
+                Action::CollapseAll,
+                Action::ExpandAll,
+            ],
+            Mode::SelectingProjects => &[
+                Action::Quit,
+                Action::AddItem,
+                Action::Sync,
+                Action::SwitchFocus,
+                Action::MoveUp,
+                Action::MoveDown,
+                Action::MoveToTop,
+                Action::MoveToBottom,
+                Action::Collapse,
+                Action::Expand,
+                Action::ToggleExpanded,
+            ],
+            Mode::AddingItem => &[Action::Confirm, Action::Cancel],
+            Mode::Exiting => &[],
+        }
+    }
+
+    /// Whether a count typed before this action's keys (eg. `5j`) repeats it.
+    fn repeats(self) -> bool {
+        matches!(
+            self,
+            Action::MoveUp | Action::MoveDown | Action::HalfPageUp | Action::HalfPageDown
+        )
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let Some(action) = Action::ALL.into_iter().find(|action| action.name() == name) else {
+            let names: Vec<&str> = Action::ALL.into_iter().map(Action::name).collect();
+            bail!(
+                "There's no action named '{name}'. The actions are: {}.",
+                names.join(", ")
+            );
+        };
+        Ok(action)
+    }
+}
+
+/// A key, along with any modifiers that need to be held down with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        // shift is already part of the character (eg. 'G'), so it doesn't need matching
+        let modifiers = match event.code {
+            KeyCode::Char(_) => event.modifiers - KeyModifiers::SHIFT,
+            _ => event.modifiers,
+        };
+        Self {
+            code: event.code,
+            modifiers,
+        }
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 15] = [
+    ("space", KeyCode::Char(' ')),
+    ("tab", KeyCode::Tab),
+    ("enter", KeyCode::Enter),
+    ("escape", KeyCode::Esc),
+    ("esc", KeyCode::Esc),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    /// Parses keys like `q`, `G`, `space`, `up`, or `ctrl-d`.
+    fn from_str(key: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = key;
+        loop {
+            if let Some(after) = rest.strip_prefix("ctrl-") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("alt-") {
+                modifiers |= KeyModifiers::ALT;
+                rest = after;
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match NAMED_KEYS.iter().find(|(name, _)| *name == rest) {
+                Some((_, code)) => *code,
+                None => bail!("'{key}' isn't a key that can be bound."),
+            },
+        };
+
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{name}"),
+                None => write!(f, "{code:?}"),
+            },
+        }
+    }
+}
+
+/// A key, or a sequence of keys pressed one after the other (like `gg`), that triggers an action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding(Vec<Key>);
+
+impl FromStr for Binding {
+    type Err = anyhow::Error;
+
+    /// Parses a single key (see `Key`), or a sequence of letters and numbers like `gg` or `zM`.
+    fn from_str(binding: &str) -> Result<Self> {
+        match binding.parse() {
+            Ok(key) => Ok(Self(vec![key])),
+            Err(_) if binding.chars().all(|c| c.is_ascii_alphanumeric()) => Ok(Self(
+                binding
+                    .chars()
+                    .map(|c| Key {
+                        code: KeyCode::Char(c),
+                        modifiers: KeyModifiers::NONE,
+                    })
+                    .collect(),
+            )),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|key| write!(f, "{key}"))
+    }
+}
+
+/// What's been typed so far of a count or key sequence that isn't finished yet.
+#[derive(Debug, Clone, Default)]
+pub struct PendingKeys {
+    count: Option<usize>,
+    keys: Vec<Key>,
+}
+
+/// Counts bigger than this are ignored, so that a typo can't freeze the app.
+const MAX_COUNT: usize = 9999;
+
+/// Which keys trigger which actions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keymap {
+    keys: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let keys = Action::ALL
+            .into_iter()
+            .map(|action| {
+                let keys = action
+                    .default_keys()
+                    .iter()
+                    .map(|binding| binding.parse().expect("default keys should be valid"))
+                    .collect();
+                (action, keys)
+            })
+            .collect();
+        Self { keys }
+    }
+}
+
+impl Keymap {
+    /// Builds a keymap from the `keybindings` setting, which maps action names to
+    /// whitespace-separated lists of keys. Actions that aren't in the setting keep
+    /// their default keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an action or key isn't recognized.
+    pub fn from_bindings(bindings: &BTreeMap<String, String>) -> Result<Self> {
+        let mut keymap = Self::default();
+        for (name, keys) in bindings {
+            let action: Action = name.parse()?;
+            let keys = keys
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<Binding>>>()?;
+            keymap.keys.insert(action, keys);
+        }
+        Ok(keymap)
+    }
+
+    /// The keys bound to an action, starting with the one shown in key hints.
+    #[must_use]
+    pub fn keys(&self, action: Action) -> &[Binding] {
+        self.keys.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Finds the action, if any, that a key press triggers in a mode, along with how many
+    /// times to do it. Key presses that start a count or a key sequence are kept in `pending`
+    /// until the action they're part of is finished.
+    pub fn action(
+        &self,
+        mode: &Mode,
+        pending: &mut PendingKeys,
+        event: KeyEvent,
+    ) -> Option<(Action, usize)> {
+        let key = Key::from(event);
+        let actions = Action::in_mode(mode);
+
+        // counts and sequences would get in the way of typing a new todo
+        if *mode == Mode::AddingItem {
+            *pending = PendingKeys::default();
+            return actions
+                .iter()
+                .find(|action| self.keys(**action).contains(&Binding(vec![key])))
+                .map(|action| (*action, 1));
+        }
+
+        let digit = match key.code {
+            KeyCode::Char(c) if key.modifiers.is_empty() && pending.keys.is_empty() => {
+                c.to_digit(10)
+            }
+            _ => None,
+        };
+        if let Some(digit) = digit {
+            // a zero on its own isn't a count
+            let continues_count = digit != 0 || pending.count.is_some();
+            if continues_count && !self.is_prefix(actions, &[key]) {
+                let count = pending.count.unwrap_or(0) * 10 + digit as usize;
+                pending.count = Some(count.min(MAX_COUNT));
+                return None;
+            }
+        }
+
+        pending.keys.push(key);
+        loop {
+            let matched = actions.iter().find(|action| {
+                self.keys(**action)
+                    .iter()
+                    .any(|binding| binding.0 == pending.keys)
+            });
+            if let Some(action) = matched {
+                let count = pending.count.unwrap_or(1);
+                *pending = PendingKeys::default();
+                return Some((*action, if action.repeats() { count } else { 1 }));
+            }
+            if self.is_prefix(actions, &pending.keys) {
+                return None;
+            }
+
+            // the sequence went nowhere, but the last key might start a new one
+            if pending.keys.len() == 1 {
+                *pending = PendingKeys::default();
+                return None;
+            }
+            *pending = PendingKeys {
+                count: None,
+                keys: vec![key],
+            };
+        }
+    }
+
+    /// Whether `keys` are the start of a longer binding for one of the actions.
+    fn is_prefix(&self, actions: &[Action], keys: &[Key]) -> bool {
+        actions.iter().any(|action| {
+            self.keys(*action)
+                .iter()
+                .any(|binding| binding.0.len() > keys.len() && binding.0.starts_with(keys))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    /// The action triggered by a single key press, with nothing typed before it.
+    fn action(keymap: &Keymap, mode: &Mode, event: KeyEvent) -> Option<Action> {
+        keymap
+            .action(mode, &mut PendingKeys::default(), event)
+            .map(|(action, _)| action)
+    }
+
+    /// The results of typing each character in `keys`.
+    fn type_keys(keymap: &Keymap, keys: &str) -> Vec<Option<(Action, usize)>> {
+        let mut pending = PendingKeys::default();
+        keys.chars()
+            .map(|c| {
+                keymap.action(
+                    &Mode::SelectingItems,
+                    &mut pending,
+                    press(KeyCode::Char(c), KeyModifiers::NONE),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_key_does_different_things_in_different_modes() {
+        let keymap = Keymap::default();
+        let space = press(KeyCode::Char(' '), KeyModifiers::NONE);
+
+        assert_eq!(
+            action(&keymap, &Mode::SelectingItems, space),
+            Some(Action::ToggleComplete)
+        );
+        assert_eq!(
+            action(&keymap, &Mode::SelectingProjects, space),
+            Some(Action::ToggleExpanded)
+        );
+        assert_eq!(action(&keymap, &Mode::AddingItem, space), None);
+    }
+
+    #[test]
+    fn override_default_keys() {
+        let bindings = BTreeMap::from([("move_down".to_string(), "ctrl-n pagedown".to_string())]);
+        let keymap = Keymap::from_bindings(&bindings).unwrap();
+        let mode = Mode::SelectingItems;
+
+        assert_eq!(
+            action(
+                &keymap,
+                &mode,
+                press(KeyCode::Char('n'), KeyModifiers::CONTROL)
+            ),
+            Some(Action::MoveDown)
+        );
+        assert_eq!(
+            action(&keymap, &mode, press(KeyCode::PageDown, KeyModifiers::NONE)),
+            Some(Action::MoveDown)
+        );
+        assert_eq!(
+            action(
+                &keymap,
+                &mode,
+                press(KeyCode::Char('j'), KeyModifiers::NONE)
+            ),
+            None
+        );
+        // other actions keep their defaults, and shift doesn't get in the way
+        assert_eq!(
+            action(
+                &keymap,
+                &mode,
+                press(KeyCode::Char('G'), KeyModifiers::SHIFT)
+            ),
+            Some(Action::MoveToBottom)
+        );
+        assert_eq!(keymap.keys(Action::MoveDown)[0].to_string(), "ctrl-n");
+    }
+
+    #[test]
+    fn reject_unknown_bindings() {
+        let bindings = BTreeMap::from([("fly".to_string(), "f".to_string())]);
+        let err = Keymap::from_bindings(&bindings).unwrap_err();
+        assert!(err.to_string().contains("no action named 'fly'"));
+
+        let bindings = BTreeMap::from([("quit".to_string(), "hyper-q".to_string())]);
+        let err = Keymap::from_bindings(&bindings).unwrap_err();
+        assert!(err.to_string().contains("'hyper-q' isn't a key"));
+    }
+
+    #[test]
+    fn wait_for_key_sequences_to_finish() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            type_keys(&keymap, "gg"),
+            [None, Some((Action::MoveToTop, 1))]
+        );
+        assert_eq!(
+            type_keys(&keymap, "zM"),
+            [None, Some((Action::CollapseAll, 1))]
+        );
+        // a sequence that goes nowhere is dropped, but the key that broke it still counts
+        assert_eq!(
+            type_keys(&keymap, "zxgj"),
+            [None, None, None, Some((Action::MoveDown, 1))]
+        );
+    }
+
+    #[test]
+    fn repeat_actions_with_counts() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            type_keys(&keymap, "12j"),
+            [None, None, Some((Action::MoveDown, 12))]
+        );
+        // the count is used up by the first action
+        assert_eq!(
+            type_keys(&keymap, "3kk"),
+            [None, Some((Action::MoveUp, 3)), Some((Action::MoveUp, 1))]
+        );
+        // and ignored by actions that don't repeat
+        assert_eq!(
+            type_keys(&keymap, "5 "),
+            [None, Some((Action::ToggleComplete, 1))]
+        );
+        assert_eq!(
+            type_keys(&keymap, "0j"),
+            [None, Some((Action::MoveDown, 1))]
+        );
+    }
+}
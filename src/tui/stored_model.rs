@@ -0,0 +1,72 @@
+use crate::{model::Model, storage::model_store::ModelStore};
+use anyhow::Result;
+use std::{collections::HashSet, time::SystemTime};
+use uuid::Uuid;
+
+/// Keeps an eye on the stored copy of the model for changes made by other processes
+/// (eg. running the CLI while the TUI is open), so that those changes can be merged into
+/// the TUI's model instead of being overwritten.
+pub struct StoredModelWatcher {
+    /// When the stored model was last modified as of the last time we read it
+    last_modified: Option<SystemTime>,
+
+    /// The uuids of the commands in the stored model as of the last time we read it
+    stored_command_uuids: HashSet<Uuid>,
+
+    /// The stored sync token as of the last time we read it
+    stored_sync_token: String,
+}
+
+impl StoredModelWatcher {
+    /// Reads the stored model and starts watching it for changes.
+    ///
+    /// # Errors
+    /// Returns an error if the stored model can't be read.
+    pub fn read(model_store: &dyn ModelStore) -> Result<(Self, Model)> {
+        let last_modified = model_store.last_modified();
+        let model = model_store.read_model()?;
+        let watcher = Self {
+            last_modified,
+            stored_command_uuids: model.commands.iter().map(|command| command.uuid).collect(),
+            stored_sync_token: model.sync_token.clone(),
+        };
+        Ok((watcher, model))
+    }
+
+    /// Whether the stored model has been modified since we last read it.
+    pub fn has_changed(&self, model_store: &dyn ModelStore) -> bool {
+        model_store.last_modified() != self.last_modified
+    }
+
+    /// Writes the model to the store, after merging in the changes that were stored since we
+    /// last read it, and carries on watching from there.
+    /// The data directory should be locked while this happens.
+    ///
+    /// # Errors
+    /// Returns an error if the stored model can't be read or written.
+    pub fn write_model(&mut self, model_store: &dyn ModelStore, model: &mut Model) -> Result<()> {
+        if self.has_changed(model_store) {
+            self.merge_changes(model_store, model)?;
+        }
+        model_store.write_model(model)?;
+
+        *self = Self {
+            last_modified: model_store.last_modified(),
+            stored_command_uuids: model.commands.iter().map(|command| command.uuid).collect(),
+            stored_sync_token: model.sync_token.clone(),
+        };
+        Ok(())
+    }
+
+    /// Reads the stored model and merges it into the given model.
+    /// The data directory should be locked while this happens.
+    ///
+    /// # Errors
+    /// Returns an error if the stored model can't be read.
+    pub fn merge_changes(&mut self, model_store: &dyn ModelStore, model: &mut Model) -> Result<()> {
+        let (watcher, stored) = Self::read(model_store)?;
+        model.merge_stored(stored, &self.stored_command_uuids, &self.stored_sync_token);
+        *self = watcher;
+        Ok(())
+    }
+}
@@ -0,0 +1,270 @@
+use crate::storage::config_manager::ThemeSettings;
+use anyhow::{anyhow, bail, Context, Result};
+use ratatui::style::{Color, Modifier, Style};
+use std::{collections::BTreeMap, env};
+
+/// If this is set to anything, the TUI doesn't use colours (see <https://no-color.org>).
+const NO_COLOR_ENV_VAR: &str = "NO_COLOR";
+
+/// The names of the themes that come with the app.
+pub const BUILT_IN_THEMES: [&str; 5] = ["default", "dark", "light", "high-contrast", "monochrome"];
+
+/// The styles that the TUI's widgets are drawn with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// The border around the focused pane
+    pub focused_border: Style,
+
+    /// The borders around the other panes
+    pub unfocused_border: Style,
+
+    /// The selected project or todo
+    pub selected: Style,
+
+    /// Todos that have been marked complete
+    pub completed: Style,
+
+    /// Less important text, like due dates and key hint descriptions
+    pub muted: Style,
+
+    /// Text being typed
+    pub text: Style,
+
+    /// The parts of a new todo that were recognized, like its due date or project
+    pub date_highlight: Style,
+
+    /// Whether projects are marked with their colours from Todoist
+    pub show_project_colours: bool,
+}
+
+impl Default for Theme {
+    /// Uses the terminal's own colours for text and selection, so it's readable on
+    /// both light and dark backgrounds.
+    fn default() -> Self {
+        Self {
+            focused_border: Style::default().fg(Color::Yellow),
+            unfocused_border: Style::default().fg(Color::Gray),
+            selected: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            completed: Style::default().fg(Color::Green),
+            muted: Style::default().fg(Color::Gray),
+            text: Style::default(),
+            date_highlight: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            show_project_colours: true,
+        }
+    }
+}
+
+impl Theme {
+    fn dark() -> Self {
+        Self {
+            selected: Style::default()
+                .bg(Color::White)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            text: Style::default().fg(Color::White),
+            ..Self::default()
+        }
+    }
+
+    fn light() -> Self {
+        Self {
+            focused_border: Style::default().fg(Color::Blue),
+            unfocused_border: Style::default().fg(Color::DarkGray),
+            selected: Style::default()
+                .bg(Color::Blue)
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            completed: Style::default().fg(Color::Green),
+            muted: Style::default().fg(Color::DarkGray),
+            text: Style::default().fg(Color::Black),
+            date_highlight: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            show_project_colours: true,
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Self {
+            focused_border: Style::default()
+                .fg(Color::LightYellow)
+                .add_modifier(Modifier::BOLD),
+            unfocused_border: Style::default().fg(Color::White),
+            selected: Style::default()
+                .bg(Color::LightYellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            completed: Style::default().fg(Color::LightGreen),
+            muted: Style::default().fg(Color::White),
+            text: Style::default().fg(Color::White),
+            date_highlight: Style::default()
+                .fg(Color::LightMagenta)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            show_project_colours: true,
+        }
+    }
+
+    fn monochrome() -> Self {
+        Self {
+            focused_border: Style::default().add_modifier(Modifier::BOLD),
+            unfocused_border: Style::default(),
+            selected: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            completed: Style::default().add_modifier(Modifier::DIM),
+            muted: Style::default(),
+            text: Style::default(),
+            date_highlight: Style::default().add_modifier(Modifier::UNDERLINED),
+            show_project_colours: false,
+        }
+    }
+
+    fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "monochrome" => Some(Self::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Finds a theme by name, either one of the built-in themes or one from the user's
+    /// `themes` setting.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there's no theme with that name, or if a user theme has a colour
+    /// that can't be parsed.
+    pub fn named(name: &str, themes: &BTreeMap<String, ThemeSettings>) -> Result<Self> {
+        if let Some(settings) = themes.get(name) {
+            return Self::from_settings(name, settings);
+        }
+
+        Self::built_in(name).ok_or_else(|| {
+            let mut names: Vec<&str> = BUILT_IN_THEMES.to_vec();
+            names.extend(themes.keys().map(String::as_str));
+            anyhow!(
+                "There's no theme named '{name}'. The themes are: {}.",
+                names.join(", ")
+            )
+        })
+    }
+
+    /// Like `named`, but without any colours if the `NO_COLOR` environment variable is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the theme can't be found.
+    pub fn from_env(name: &str, themes: &BTreeMap<String, ThemeSettings>) -> Result<Self> {
+        if env::var_os(NO_COLOR_ENV_VAR).is_some_and(|value| !value.is_empty()) {
+            return Ok(Self::monochrome());
+        }
+        Self::named(name, themes)
+    }
+
+    fn from_settings(name: &str, settings: &ThemeSettings) -> Result<Self> {
+        let base = settings.base.as_deref().unwrap_or("default");
+        let Some(mut theme) = Self::built_in(base) else {
+            bail!(
+                "Invalid value for 'themes.{name}.base': it must be one of the built-in themes ({}).",
+                BUILT_IN_THEMES.join(", ")
+            );
+        };
+
+        let colour = |key: &str, value: Option<&str>| -> Result<Option<Color>> {
+            value
+                .map(|value| {
+                    value.parse().with_context(|| {
+                        format!("Invalid value for 'themes.{name}.{key}': '{value}' isn't a colour")
+                    })
+                })
+                .transpose()
+        };
+
+        for (style, key, value) in [
+            (
+                &mut theme.focused_border,
+                "focused_border",
+                settings.focused_border.as_deref(),
+            ),
+            (
+                &mut theme.unfocused_border,
+                "unfocused_border",
+                settings.unfocused_border.as_deref(),
+            ),
+            (
+                &mut theme.completed,
+                "completed",
+                settings.completed.as_deref(),
+            ),
+            (&mut theme.muted, "muted", settings.muted.as_deref()),
+            (&mut theme.text, "text", settings.text.as_deref()),
+            (
+                &mut theme.date_highlight,
+                "date_highlight",
+                settings.date_highlight.as_deref(),
+            ),
+        ] {
+            if let Some(colour) = colour(key, value)? {
+                *style = style.fg(colour);
+            }
+        }
+        if let Some(colour) = colour("selected_fg", settings.selected_fg.as_deref())? {
+            theme.selected = theme.selected.fg(colour);
+        }
+        if let Some(colour) = colour("selected_bg", settings.selected_bg.as_deref())? {
+            // a background colour takes the place of reversing the colours
+            theme.selected = theme
+                .selected
+                .bg(colour)
+                .remove_modifier(Modifier::REVERSED);
+        }
+
+        Ok(theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn build_user_theme_on_built_in_theme() {
+        let themes = BTreeMap::from([(
+            "mine".to_string(),
+            ThemeSettings {
+                base: Some("light".to_string()),
+                focused_border: Some("#ff8800".to_string()),
+                selected_bg: Some("cyan".to_string()),
+                ..Default::default()
+            },
+        )]);
+
+        let theme = Theme::named("mine", &themes).unwrap();
+
+        assert_eq!(theme.focused_border.fg, Some(Color::Rgb(0xff, 0x88, 0x00)));
+        assert_eq!(theme.selected.bg, Some(Color::Cyan));
+        assert_eq!(theme.selected.fg, Theme::light().selected.fg);
+        assert_eq!(theme.text, Theme::light().text);
+    }
+
+    #[test]
+    fn reject_unknown_themes_and_colours() {
+        let err = Theme::named("neon", &BTreeMap::new()).unwrap_err();
+        assert!(err.to_string().contains("no theme named 'neon'"));
+
+        let themes = BTreeMap::from([(
+            "mine".to_string(),
+            ThemeSettings {
+                muted: Some("greyish".to_string()),
+                ..Default::default()
+            },
+        )]);
+        let err = Theme::named("mine", &themes).unwrap_err();
+        assert!(err.to_string().contains("'themes.mine.muted'"));
+    }
+}
@@ -1,4 +1,22 @@
+use std::hash::Hash;
+use tui_tree_widget::{TreeItem, TreeState};
+
 pub(super) mod items;
 pub(super) mod key_hints;
 pub(super) mod projects;
 pub(super) mod section;
+
+/// Whether the node at the end of `path` is expanded, ie. whether its children are visible.
+fn is_expanded<Identifier>(
+    tree: &TreeState<Identifier>,
+    tree_items: &[TreeItem<'_, Identifier>],
+    path: &[Identifier],
+) -> bool
+where
+    Identifier: Clone + PartialEq + Eq + Hash,
+{
+    !path.is_empty()
+        && tree.flatten(tree_items).iter().any(|flattened| {
+            flattened.identifier.len() == path.len() + 1 && flattened.identifier.starts_with(path)
+        })
+}
@@ -1,24 +1,32 @@
-use super::section::{State as SectionState, Widget as SectionWidget};
+use super::section::{nearest_remaining, State as SectionState, Widget as SectionWidget};
 use crate::{
     model::{
         item::{Id as ItemId, Item},
+        project::Id as ProjectId,
         section::{Id as SectionId, Section},
         Model,
     },
-    tui::app_state::{AppState, Mode},
+    storage::config_manager::Config,
+    tui::{
+        app_state::{AppState, Mode},
+        keymap::Action,
+        theme::Theme,
+    },
 };
-use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     layout::{Position, Size},
     prelude::{Buffer, Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
     widgets::{Block, Borders, StatefulWidget, Widget as RatatuiWidget},
 };
+use std::collections::HashMap;
 use tui_scrollview::{ScrollView, ScrollViewState};
 use tui_tree_widget::TreeItem;
 
 #[derive(Debug, Clone, Default)]
 pub struct State {
+    /// The project whose items this state describes
+    project_id: Option<ProjectId>,
+
     /// The states of the all of the sections in the currently-selected project
     section_states: Vec<SectionState>,
 
@@ -28,18 +36,80 @@ pub struct State {
     /// Stores where the widget is scrolled
     scroll: ScrollViewState,
 
-    /// When a key is pressed, we wait until the next render to process it.
-    /// It's stored here in between key press and processing.
-    pending_key_event: Option<KeyEvent>,
+    /// The height of the visible part of the widget (as of the last render), used for
+    /// half-page moves
+    page_height: u16,
 }
 
 impl State {
     pub fn selected_item_id(&self) -> Option<ItemId> {
-        let x = self
-            .current_section_state()
-            .and_then(SectionState::selected_item_id);
-        assert!(x.is_some());
-        x
+        self.current_section_state()
+            .and_then(SectionState::selected_item_id)
+    }
+
+    /// Updates the section states to match the current sections and items in the model,
+    /// preserving the selection (and what's expanded) as much as possible.
+    fn sync_with_model(
+        &mut self,
+        sections_and_items: &[(Option<&Section>, Vec<&Item>)],
+        config: &Config,
+        theme: &Theme,
+    ) {
+        let previous_section_ids: Vec<Option<SectionId>> = self
+            .section_states
+            .iter()
+            .map(|section_state| section_state.id.clone())
+            .collect();
+
+        let mut previous_states = std::mem::take(&mut self.section_states);
+        self.section_states = sections_and_items
+            .iter()
+            .map(|(section, items)| {
+                let id = section.map(|section| section.id.clone());
+                match previous_states.iter().position(|state| state.id == id) {
+                    Some(index) => {
+                        let mut section_state = previous_states.swap_remove(index);
+                        section_state.sync_with_items(*section, items, config, theme);
+                        section_state
+                    }
+                    None => SectionState::new(*section, items, config, theme),
+                }
+            })
+            .collect();
+
+        // if the selected section went away, select the one nearest to it instead
+        if !self
+            .section_states
+            .iter()
+            .any(|section_state| section_state.id == self.current_section_id)
+        {
+            let index = previous_section_ids
+                .iter()
+                .position(|id| *id == self.current_section_id)
+                .unwrap_or(0);
+            self.current_section_id = nearest_remaining(&previous_section_ids, index, |id| {
+                self.section_states
+                    .iter()
+                    .any(|section_state| section_state.id == *id)
+            })
+            .cloned()
+            .unwrap_or_default();
+        }
+    }
+
+    /// Swaps the temporary ids of new items and sections for the real ids that a sync
+    /// gave them, so that the selection stays where it was.
+    pub fn replace_temp_ids(&mut self, temp_id_mapping: &HashMap<String, String>) {
+        for section_state in &mut self.section_states {
+            section_state.replace_temp_ids(temp_id_mapping);
+        }
+        if let Some(real_id) = self
+            .current_section_id
+            .as_ref()
+            .and_then(|id| temp_id_mapping.get(&id.to_string()))
+        {
+            self.current_section_id = Some(real_id.as_str().into());
+        }
     }
 
     fn current_section_state(&self) -> Option<&SectionState> {
@@ -48,29 +118,39 @@ impl State {
             .find(|section_state| section_state.id == self.current_section_id)
     }
 
-    fn recompute_scroll_offset(
-        &mut self,
-        section_states_and_tree_items: &[(SectionState, Vec<TreeItem<'_, ItemId>>)],
-    ) {
-        let current_tree_items = section_states_and_tree_items
+    fn current_section_index(&self) -> Option<usize> {
+        self.section_states
             .iter()
-            .find_map(|(s, t)| {
-                if s.id == self.current_section_id {
-                    Some(t)
-                } else {
-                    None
-                }
-            })
-            .expect("currently-selected section id should always match an existing section");
+            .position(|section_state| section_state.id == self.current_section_id)
+    }
+
+    /// The tree items to show for each section, in the same order as `section_states`.
+    fn tree_items<'b>(
+        &self,
+        sections_and_items: &[(Option<&Section>, Vec<&Item>)],
+        config: &Config,
+        theme: &Theme,
+    ) -> Vec<Vec<TreeItem<'b, ItemId>>> {
+        self.section_states
+            .iter()
+            .zip(sections_and_items)
+            .map(|(section_state, (_, items))| section_state.tree_items(items, config, theme))
+            .collect()
+    }
+
+    fn recompute_scroll_offset(&mut self, tree_items: &[Vec<TreeItem<'_, ItemId>>]) {
+        let Some(current_index) = self.current_section_index() else {
+            return;
+        };
 
         #[allow(clippy::cast_possible_truncation)]
-        let offset = self
-            .current_section_state()
-            .map_or(0, |s| s.offset(current_tree_items) as u16);
+        let offset = self.section_states[current_index].offset(&tree_items[current_index]) as u16;
 
-        let mut offset = section_states_and_tree_items
+        let mut offset = self
+            .section_states
             .iter()
-            .take_while(|(section, _)| section.id != self.current_section_id)
+            .zip(tree_items)
+            .take(current_index)
             .map(|(section_state, tree_items)| section_state.height(tree_items))
             .sum::<u16>()
             + offset;
@@ -81,80 +161,141 @@ impl State {
         self.scroll.set_offset(Position::new(0, offset));
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) {
-        self.pending_key_event = Some(key);
+    /// Starts over with a fresh state if a different project was selected, then brings the
+    /// section states up to date with the model, which may have changed since they were last
+    /// looked at (or, if this is the first time, creates them).
+    fn sync_with_project(
+        &mut self,
+        project_id: &ProjectId,
+        sections_and_items: &[(Option<&Section>, Vec<&Item>)],
+        config: &Config,
+        theme: &Theme,
+    ) {
+        if self.project_id.as_ref() != Some(project_id) {
+            *self = State {
+                project_id: Some(project_id.clone()),
+                ..State::default()
+            };
+        }
+        self.sync_with_model(sections_and_items, config, theme);
+    }
+
+    /// Does an action `count` times on the todos in a project, and returns the todos that it
+    /// expanded or collapsed, along with whether each one is now collapsed. Only what the
+    /// action changed is returned, so that it doesn't undo changes that a sync brought in.
+    pub fn handle_action(
+        &mut self,
+        action: Action,
+        count: usize,
+        project_id: &ProjectId,
+        model: &Model,
+        config: &Config,
+        theme: &Theme,
+    ) -> Vec<(ItemId, bool)> {
+        let sections_and_items = model.sections_and_items_in_project(project_id);
+        self.sync_with_project(project_id, &sections_and_items, config, theme);
+
+        let collapsed_before = self.collapsed_items(&sections_and_items);
+        self.do_action(action, count, &sections_and_items, config, theme);
+        self.collapsed_items(&sections_and_items)
+            .into_iter()
+            .filter(|collapsed| !collapsed_before.contains(collapsed))
+            .collect()
     }
 
-    pub fn handle_key_later(
+    /// Does an action `count` times. Half-page moves go half of the visible height at a time.
+    fn do_action(
         &mut self,
-        key: KeyEvent,
+        action: Action,
+        count: usize,
         sections_and_items: &[(Option<&Section>, Vec<&Item>)],
+        config: &Config,
+        theme: &Theme,
     ) {
-        let section_states_and_tree_items: Vec<(SectionState, Vec<TreeItem<'_, ItemId>>)> =
-            sections_and_items
-                .iter()
-                .map(|(section, items)| {
-                    let section_state = self
-                        .section_states
-                        .iter()
-                        .find(|state| match (&state.id, section) {
-                            (Some(id), Some(section)) => section.id == *id,
-                            (None, None) => true,
-                            _ => false,
-                        })
-                        .expect("we just did this!")
-                        .clone();
-                    let tree_items = SectionState::build_tree(items, None);
-                    (section_state, tree_items)
-                })
-                .collect();
-
-        let (current_index, current_section_state) = self
-            .section_states
-            .iter_mut()
-            .enumerate()
-            .find(|(_, section_state)| section_state.id == self.current_section_id)
-            .expect("currently-selected section id should always match an existing section");
+        let half_page = usize::from(self.page_height / 2).max(1);
+        let (action, steps) = match action {
+            Action::HalfPageDown => (Action::MoveDown, count * half_page),
+            Action::HalfPageUp => (Action::MoveUp, count * half_page),
+            action => (action, count),
+        };
 
-        let current_tree_items = section_states_and_tree_items
-            .iter()
-            .find_map(|(s, t)| {
-                if s.id == self.current_section_id {
-                    Some(t)
+        match action {
+            // jumping to the top or bottom starts by jumping to the first or last section
+            Action::MoveToTop | Action::MoveToBottom => {
+                let section_state = if action == Action::MoveToTop {
+                    self.section_states.first()
                 } else {
-                    None
+                    self.section_states.last()
+                };
+                if let Some(section_state) = section_state {
+                    self.current_section_id = section_state.id.clone();
                 }
-            })
-            .expect("currently-selected section id should always match an existing section");
-
-        let at_boundary = current_section_state.handle_key(key, current_tree_items);
-        if at_boundary {
-            let next_index = match key.code {
-                KeyCode::Down => current_index
-                    .saturating_add(1)
-                    .min(self.section_states.len() - 1),
-                KeyCode::Up => current_index.saturating_sub(1).max(0),
-                _ => 0,
-            };
+            }
+            Action::CollapseAll => {
+                self.section_states
+                    .iter_mut()
+                    .for_each(SectionState::collapse_all);
+            }
+            Action::ExpandAll => {
+                for (section_state, (_, items)) in
+                    self.section_states.iter_mut().zip(sections_and_items)
+                {
+                    section_state.expand_all(items);
+                }
+            }
+            _ => {}
+        }
 
-            self.current_section_id = self
-                .section_states
-                .get(next_index)
-                .map(|x| x.id.clone())
-                .unwrap_or_default();
+        let tree_items = self.tree_items(sections_and_items, config, theme);
+        for _ in 0..steps {
+            let Some(current_index) = self.current_section_index() else {
+                break;
+            };
+            let at_boundary = self.section_states[current_index]
+                .handle_action(action, &tree_items[current_index]);
+
+            if at_boundary {
+                let next_index = match action {
+                    Action::MoveDown => current_index
+                        .saturating_add(1)
+                        .min(self.section_states.len() - 1),
+                    Action::MoveUp => current_index.saturating_sub(1).max(0),
+                    _ => 0,
+                };
+
+                self.current_section_id = self
+                    .section_states
+                    .get(next_index)
+                    .map(|x| x.id.clone())
+                    .unwrap_or_default();
+            }
         }
 
-        self.recompute_scroll_offset(&section_states_and_tree_items);
+        // collapsing or expanding a section changes which items are shown
+        let tree_items = self.tree_items(sections_and_items, config, theme);
+        self.recompute_scroll_offset(&tree_items);
+    }
+
+    /// Whether each item with children is collapsed, so that changes can be saved.
+    fn collapsed_items(
+        &self,
+        sections_and_items: &[(Option<&Section>, Vec<&Item>)],
+    ) -> Vec<(ItemId, bool)> {
+        self.section_states
+            .iter()
+            .zip(sections_and_items)
+            .flat_map(|(section_state, (_, items))| section_state.collapsed_items(items))
+            .collect()
     }
 }
 
 #[derive(Debug, Default)]
 pub struct Widget<'a> {
-    marker: std::marker::PhantomData<(&'a mut AppState, &'a mut Model)>,
+    marker: std::marker::PhantomData<(&'a mut AppState, &'a Model)>,
 }
 
 impl<'a> StatefulWidget for Widget<'a> {
-    type State = (&'a mut AppState, &'a mut Model);
+    type State = (&'a mut AppState, &'a Model);
 
     fn render(self, area: Rect, buf: &mut Buffer, (app_state, model): &mut Self::State) {
         // if no project is selected, just bail
@@ -165,49 +306,27 @@ impl<'a> StatefulWidget for Widget<'a> {
 
         // get a list of sections in the selected project paired with the items in that section
         let sections_and_items = model.sections_and_items_in_project(&project_id);
-
-        // find the section states for each section, or (as is the case on the first render)
-        // create a new one. then compute the tree of items in each section
-        for (section, items) in &sections_and_items {
-            if !app_state
+        app_state.items.sync_with_project(
+            &project_id,
+            &sections_and_items,
+            &app_state.config,
+            &app_state.theme,
+        );
+
+        // the border takes up a line at the top and bottom
+        app_state.items.page_height = area.height.saturating_sub(2);
+
+        let tree_items =
+            app_state
                 .items
-                .section_states
-                .iter()
-                .any(|state| match (&state.id, section) {
-                    (Some(id), Some(section)) => section.id == *id,
-                    (None, None) => true,
-                    _ => false,
-                })
-            {
-                app_state
-                    .items
-                    .section_states
-                    .push(SectionState::new(*section, items));
-            }
-        }
-
-        // we can now handle a key event if there is one
-        if let Some(key) = app_state.items.pending_key_event.take() {
-            app_state.items.handle_key_later(key, &sections_and_items);
-        }
+                .tree_items(&sections_and_items, &app_state.config, &app_state.theme);
 
         let mut section_states_and_tree_items: Vec<(&SectionState, Vec<TreeItem<'_, ItemId>>)> =
-            sections_and_items
+            app_state
+                .items
+                .section_states
                 .iter()
-                .map(|(section, items)| {
-                    let section_state = app_state
-                        .items
-                        .section_states
-                        .iter()
-                        .find(|state| match (&state.id, section) {
-                            (Some(id), Some(section)) => section.id == *id,
-                            (None, None) => true,
-                            _ => false,
-                        })
-                        .expect("we just did this!");
-                    let tree_items = SectionState::build_tree(items, None);
-                    (section_state, tree_items)
-                })
+                .zip(tree_items)
                 .collect();
 
         let heights = section_states_and_tree_items
@@ -219,13 +338,11 @@ impl<'a> StatefulWidget for Widget<'a> {
         let block = Block::default()
             .borders(Borders::ALL)
             .title("Tasks")
-            .border_style(
-                Style::default().fg(if app_state.mode == Mode::SelectingItems {
-                    Color::Yellow
-                } else {
-                    Color::Gray
-                }),
-            );
+            .border_style(if app_state.mode == Mode::SelectingItems {
+                app_state.theme.focused_border
+            } else {
+                app_state.theme.unfocused_border
+            });
 
         // render the border
         let area = {
@@ -244,13 +361,22 @@ impl<'a> StatefulWidget for Widget<'a> {
             .split(scrollview.area());
 
         // render inside each piece
+        let highlight_style = app_state.theme.selected;
         section_states_and_tree_items
             .iter_mut()
             .enumerate()
             .for_each(|(index, (section_state, tree_items))| {
                 if let Some(rect) = layout.get(index) {
-                    SectionWidget::new(app_state.items.current_section_id.clone(), tree_items)
-                        .render(*rect, scrollview.buf_mut(), &mut (section_state, model));
+                    SectionWidget::new(
+                        app_state.items.current_section_id.clone(),
+                        tree_items,
+                        highlight_style,
+                    )
+                    .render(
+                        *rect,
+                        scrollview.buf_mut(),
+                        &mut (section_state, model),
+                    );
                 }
             });
 
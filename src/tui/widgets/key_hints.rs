@@ -1,7 +1,11 @@
-use crate::tui::app_state::{AppState, Mode};
+use crate::tui::{
+    app_state::{AppState, Mode},
+    keymap::{Action, Keymap},
+    theme::Theme,
+};
 use ratatui::{
     prelude::{Buffer, Rect},
-    style::{Color, Modifier, Style},
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Paragraph, StatefulWidget, Widget as RatatuiWidget},
 };
@@ -12,45 +16,63 @@ pub struct KeyHint {
 }
 
 impl KeyHint {
-    fn new<S: Into<String>>(key: S, action: S) -> Self {
-        Self {
-            key: key.into(),
-            action: action.into(),
+    /// Describes the keys for a group of related actions, eg. "↑↓: select".
+    fn new(keymap: &Keymap, actions: &[Action], description: &str) -> Option<Self> {
+        let keys: Vec<String> = actions
+            .iter()
+            .filter_map(|action| keymap.keys(*action).first())
+            .map(ToString::to_string)
+            .collect();
+        if keys.is_empty() {
+            return None;
         }
+
+        Some(Self {
+            key: keys.concat(),
+            action: description.to_string(),
+        })
     }
 
+    /// The hints for a mode, using whichever keys are bound in the keymap.
     #[must_use]
-    pub fn from_mode(mode: &Mode) -> Vec<KeyHint> {
-        match mode {
-            Mode::AddingItem => vec![
-                Self::new("enter", "add todo"),
-                Self::new("escape", "cancel"),
+    pub fn from_mode(mode: &Mode, keymap: &Keymap) -> Vec<KeyHint> {
+        let hints: &[(&[Action], &str)] = match mode {
+            Mode::AddingItem => &[
+                (&[Action::Confirm], "add todo"),
+                (&[Action::Cancel], "cancel"),
             ],
-            Mode::SelectingItems => vec![
-                Self::new("q", "quit"),
-                Self::new("a", "new todo"),
-                Self::new("↑↓", "select"),
-                Self::new("space", "mark complete"),
-                Self::new("tab", "change focus"),
+            Mode::SelectingItems => &[
+                (&[Action::Quit], "quit"),
+                (&[Action::AddItem], "new todo"),
+                (&[Action::MoveUp, Action::MoveDown], "select"),
+                (&[Action::ToggleComplete], "mark complete"),
+                (&[Action::SwitchFocus], "change focus"),
+                (&[Action::Sync], "sync"),
             ],
-            Mode::SelectingProjects => vec![
-                Self::new("q", "quit"),
-                Self::new("a", "new todo"),
-                Self::new("↑↓", "select"),
-                Self::new("tab", "change focus"),
+            Mode::SelectingProjects => &[
+                (&[Action::Quit], "quit"),
+                (&[Action::AddItem], "new todo"),
+                (&[Action::MoveUp, Action::MoveDown], "select"),
+                (&[Action::SwitchFocus], "change focus"),
+                (&[Action::Sync], "sync"),
             ],
-            Mode::Exiting => vec![],
-        }
+            Mode::Exiting => &[],
+        };
+
+        hints
+            .iter()
+            .filter_map(|(actions, description)| Self::new(keymap, actions, description))
+            .collect()
     }
 }
 
-impl<'a> From<KeyHint> for Vec<Span<'a>> {
-    fn from(hint: KeyHint) -> Self {
+impl KeyHint {
+    fn spans<'a>(self, theme: &Theme) -> Vec<Span<'a>> {
         let hint_style = Style::default();
         vec![
-            Span::styled(hint.key, hint_style.add_modifier(Modifier::BOLD)),
+            Span::styled(self.key, hint_style.add_modifier(Modifier::BOLD)),
             Span::styled(": ", hint_style),
-            Span::styled(hint.action, hint_style.fg(Color::Gray)),
+            Span::styled(self.action, theme.muted),
             Span::raw("  "),
         ]
     }
@@ -65,13 +87,22 @@ impl StatefulWidget for Widget {
     type State = AppState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let key_hints = KeyHint::from_mode(&state.mode);
-        let key_hint_line: Line = Line::from(
+        let key_hints = KeyHint::from_mode(&state.mode, &state.keymap);
+        // problems go first, so that they aren't cut off on narrow terminals
+        let mut spans: Vec<Span> = vec![];
+        if let Some(error) = state.error.as_ref().or(state.sync_error.as_ref()) {
+            spans.push(Span::styled(
+                error.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            spans.push(Span::raw("  "));
+        }
+        spans.extend(
             key_hints
                 .into_iter()
-                .flat_map(Into::<Vec<Span>>::into)
-                .collect::<Vec<Span>>(),
+                .flat_map(|hint| hint.spans(&state.theme)),
         );
+        let key_hint_line = Line::from(spans);
         Paragraph::new(key_hint_line).render(area, buf);
     }
 }
@@ -1,28 +1,34 @@
+use super::is_expanded;
 use crate::{
     model::{
         project::{Id as ProjectId, Project},
         Model,
     },
-    tui::app_state::{AppState, Mode},
+    tui::{
+        app_state::{AppState, Mode},
+        keymap::Action,
+        theme::Theme,
+    },
 };
-use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
-    prelude::{Buffer, Rect},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, StatefulWidget},
+    prelude::{Alignment, Buffer, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{block::Title, Block, Borders, StatefulWidget},
 };
 use tui_tree_widget::{Tree, TreeItem, TreeState};
 
+/// The id of the group of favorite projects at the top of the tree. It can't be the same
+/// as a project's id, since those are numbers (or uuids for new projects).
+const FAVORITES_ID: &str = "favorites";
+
 pub struct State {
     /// Records which project's are expanded and which is selected.
     tree: TreeState<ProjectId>,
 
-    /// When a key is pressed, we wait until the next render to process it.
-    /// It's stored here in between key press and processing.
-    pending_key_event: Option<KeyEvent>,
-
-    /// Used to indicate that we need to do some state setup during render.
-    first_render: bool,
+    /// Set until the projects that are expanded in Todoist have been opened in the tree,
+    /// which happens the first time the model is looked at.
+    needs_setup: bool,
 }
 
 impl State {
@@ -32,105 +38,210 @@ impl State {
 
         Self {
             tree,
-            pending_key_event: None,
-            first_render: true,
+            needs_setup: true,
         }
     }
 
+    /// The id of the selected project, which is `None` if it's the favorites group that's selected.
     pub fn selected_id(&self) -> Option<ProjectId> {
-        self.tree.selected().into_iter().last()
+        self.tree
+            .selected()
+            .into_iter()
+            .last()
+            .filter(|id| *id != FAVORITES_ID.into())
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent) {
-        self.pending_key_event = Some(key);
+    /// Opens the favorites group, and the projects that are expanded in Todoist, the first
+    /// time this is called. After that they're left however the user has them.
+    fn set_up(&mut self, projects: &[Project]) {
+        if !self.needs_setup {
+            return;
+        }
+        self.needs_setup = false;
+
+        self.tree.open(vec![FAVORITES_ID.into()]);
+        for project in projects.iter().filter(|project| !project.collapsed) {
+            self.tree.open(project_path(project, projects));
+            if project.is_favorite {
+                self.tree
+                    .open(vec![FAVORITES_ID.into(), project.id.clone()]);
+            }
+        }
     }
 
-    fn handle_key_later(&mut self, key: KeyEvent, tree_items: &[TreeItem<'_, ProjectId>]) {
-        match key.code {
-            KeyCode::Char('\n' | ' ') => self.tree.toggle_selected(),
-            KeyCode::Left => self.tree.key_left(),
-            KeyCode::Right => self.tree.key_right(),
-            KeyCode::Down => self.tree.key_down(tree_items),
-            KeyCode::Up => self.tree.key_up(tree_items),
-            _ => {}
+    /// Does an action `count` times, and returns the id of a project and whether it's now
+    /// collapsed if the action expanded or collapsed that project. Nothing is returned for
+    /// the favorites group, which isn't a project in Todoist.
+    pub fn handle_action(
+        &mut self,
+        action: Action,
+        count: usize,
+        model: &Model,
+        theme: &Theme,
+    ) -> Option<(ProjectId, bool)> {
+        self.set_up(&model.projects);
+        let tree_items = &tree_items(&model.projects, theme);
+
+        let selected = self.tree.selected();
+        let was_expanded = is_expanded(&self.tree, tree_items, &selected);
+
+        for _ in 0..count {
+            match action {
+                Action::ToggleExpanded => self.tree.toggle_selected(),
+                Action::Collapse => self.tree.key_left(),
+                Action::Expand => self.tree.key_right(),
+                Action::MoveDown => self.tree.key_down(tree_items),
+                Action::MoveUp => self.tree.key_up(tree_items),
+                Action::MoveToTop => self.tree.select_first(tree_items),
+                Action::MoveToBottom => self.tree.select_last(tree_items),
+                _ => {}
+            }
+        }
+
+        let expanded = is_expanded(&self.tree, tree_items, &selected);
+        if was_expanded == expanded {
+            return None;
         }
+        selected
+            .last()
+            .filter(|id| **id != FAVORITES_ID.into())
+            .map(|id| (id.clone(), !expanded))
     }
 }
 
 #[derive(Debug, Default)]
 pub struct Widget<'a> {
-    marker: std::marker::PhantomData<(&'a mut AppState, &'a mut Model)>,
+    marker: std::marker::PhantomData<(&'a mut AppState, &'a Model)>,
 }
 
 impl<'a> StatefulWidget for Widget<'a> {
-    type State = (&'a mut AppState, &'a mut Model);
+    type State = (&'a mut AppState, &'a Model);
 
     /// Renders the app state into a terminal frame.
     ///
     /// # Panics
     /// If the model contains projects with duplicate ids
     fn render(self, area: Rect, buf: &mut Buffer, (app_state, model): &mut Self::State) {
-        // recursive helper function
-        fn build_tree<'b>(
-            projects: &'_ [Project],
-            parent_id: Option<&ProjectId>,
-        ) -> Vec<TreeItem<'b, ProjectId>> {
-            projects
-                .iter()
-                .filter_map(|project| {
-                    if project.parent_id.as_ref() == parent_id {
-                        // TODO : sort by `project.child_order`
-                        let children = build_tree(projects, Some(&project.id));
-                        Some(
-                            TreeItem::new(project.id.clone(), project.name.clone(), children)
-                                .expect("Project ids must be unique"),
-                        )
-                    } else {
-                        None
-                    }
-                })
-                .collect()
-        }
-
-        if app_state.projects.first_render {
-            app_state.projects.first_render = false;
-
-            for project in &mut model.projects {
-                if !project.collapsed {
-                    app_state.projects.tree.open(vec![project.id.clone()]);
-                }
-            }
-        }
+        app_state.projects.set_up(&model.projects);
+        let tree_items = tree_items(&model.projects, &app_state.theme);
 
-        let tree_items = build_tree(&model.projects, None);
+        let focused = app_state.mode == Mode::SelectingProjects;
 
-        // now that we've made the tree items, we can handle key events, some of which require
-        // the tree_items to be present.
-        if let Some(key) = app_state.projects.pending_key_event.take() {
-            app_state.projects.handle_key_later(key, &tree_items);
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .title("Projects")
+            .border_style(if focused {
+                app_state.theme.focused_border
+            } else {
+                app_state.theme.unfocused_border
+            });
+        if let Some(ref profile) = app_state.profile {
+            block =
+                block.title(Title::from(format!("profile: {profile}")).alignment(Alignment::Right));
         }
 
-        let focused = app_state.mode == Mode::SelectingProjects;
-
         let tree = Tree::new(tree_items)
             .expect("Project ids must be unique")
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Projects")
-                    .border_style(Style::default().fg(if focused {
-                        Color::Yellow
-                    } else {
-                        Color::Gray
-                    })),
-            )
-            .highlight_style(
-                Style::default()
-                    .bg(Color::White)
-                    .fg(Color::Black)
-                    .add_modifier(Modifier::BOLD),
-            );
+            .block(block)
+            .highlight_style(app_state.theme.selected);
 
         tree.render(area, buf, &mut app_state.projects.tree);
     }
 }
+
+/// A project's name, after a bullet in its colour from Todoist.
+fn project_text<'a>(project: &Project, theme: &Theme) -> Line<'a> {
+    if !theme.show_project_colours {
+        return Line::from(project.name.clone());
+    }
+
+    Line::from(vec![
+        Span::styled("● ", Style::default().fg(todoist_colour(&project.color))),
+        Span::raw(project.name.clone()),
+    ])
+}
+
+/// The tree of projects, with the favorites also listed (without their subprojects) in a
+/// group at the top.
+fn tree_items<'b>(projects: &[Project], theme: &Theme) -> Vec<TreeItem<'b, ProjectId>> {
+    let mut tree_items = build_tree(projects, None, theme);
+
+    let favorites: Vec<TreeItem<'_, ProjectId>> = projects
+        .iter()
+        .filter(|project| project.is_favorite)
+        .map(|project| TreeItem::new_leaf(project.id.clone(), project_text(project, theme)))
+        .collect();
+    if !favorites.is_empty() {
+        tree_items.insert(
+            0,
+            TreeItem::new(FAVORITES_ID.into(), "Favorites", favorites)
+                .expect("Project ids must be unique"),
+        );
+    }
+    tree_items
+}
+
+/// The path to a project in the tree from `tree_items`, through its parent projects.
+fn project_path(project: &Project, projects: &[Project]) -> Vec<ProjectId> {
+    let mut path = vec![project.id.clone()];
+    let mut parent_id = project.parent_id.as_ref();
+    while let Some(parent) = parent_id.and_then(|id| projects.iter().find(|p| p.id == *id)) {
+        // don't go around in circles if the projects are somehow their own ancestors
+        if path.contains(&parent.id) {
+            break;
+        }
+        path.insert(0, parent.id.clone());
+        parent_id = parent.parent_id.as_ref();
+    }
+    path
+}
+
+fn build_tree<'b>(
+    projects: &'_ [Project],
+    parent_id: Option<&ProjectId>,
+    theme: &Theme,
+) -> Vec<TreeItem<'b, ProjectId>> {
+    let mut children: Vec<&Project> = projects
+        .iter()
+        .filter(|project| project.parent_id.as_ref() == parent_id)
+        .collect();
+    children.sort_by_key(|project| project.child_order);
+
+    children
+        .into_iter()
+        .map(|project| {
+            let children = build_tree(projects, Some(&project.id), theme);
+            TreeItem::new(project.id.clone(), project_text(project, theme), children)
+                .expect("Project ids must be unique")
+        })
+        .collect()
+}
+
+/// The colour that Todoist shows for one of its named project colours.
+/// See <https://developer.todoist.com/guides/#colors>.
+fn todoist_colour(name: &str) -> Color {
+    let (r, g, b) = match name {
+        "berry_red" => (0xb8, 0x25, 0x6f),
+        "red" => (0xdb, 0x40, 0x35),
+        "orange" => (0xff, 0x99, 0x33),
+        "yellow" => (0xfa, 0xd0, 0x00),
+        "olive_green" => (0xaf, 0xb8, 0x3b),
+        "lime_green" => (0x7e, 0xcc, 0x49),
+        "green" => (0x29, 0x94, 0x38),
+        "mint_green" => (0x6a, 0xcc, 0xbc),
+        "teal" => (0x15, 0x8f, 0xad),
+        "sky_blue" => (0x14, 0xaa, 0xf5),
+        "light_blue" => (0x96, 0xc3, 0xeb),
+        "blue" => (0x40, 0x73, 0xff),
+        "grape" => (0x88, 0x4d, 0xff),
+        "violet" => (0xaf, 0x38, 0xeb),
+        "lavender" => (0xeb, 0x96, 0xeb),
+        "magenta" => (0xe0, 0x51, 0x94),
+        "salmon" => (0xff, 0x8d, 0x85),
+        "grey" => (0xb8, 0xb8, 0xb8),
+        "taupe" => (0xcc, 0xac, 0x93),
+        // charcoal, and anything new
+        _ => (0x80, 0x80, 0x80),
+    };
+    Color::Rgb(r, g, b)
+}
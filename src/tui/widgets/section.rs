@@ -1,60 +1,188 @@
-use crate::model::{
-    item::{Id as ItemId, Item},
-    section::{Id as SectionId, Section},
-    Model,
+use crate::{
+    model::{
+        item::{Id as ItemId, Item},
+        section::{Id as SectionId, Section},
+        Model,
+    },
+    storage::config_manager::Config,
+    tui::{keymap::Action, theme::Theme},
 };
-use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     prelude::{Buffer, Constraint, Direction, Layout, Rect},
-    style::{Color, Modifier, Style},
+    style::Style,
     text::{Line, Span, Text},
     widgets::{Paragraph, StatefulWidget, Widget as RatatuiWidget},
 };
+use std::collections::HashMap;
 use tui_tree_widget::{Tree, TreeItem, TreeState};
 
-// pub type State = TreeState<ItemId>;
-
 #[derive(Debug, Clone)]
 pub struct State {
     pub id: Option<SectionId>,
     name: Option<String>,
     tree: TreeState<ItemId>,
+
+    /// The ids of all of the items this state has been synced with, and whether each was
+    /// collapsed then, so that we can tell when new items show up or are collapsed elsewhere
+    seen_ids: HashMap<ItemId, bool>,
+
+    /// The ids of the visible items, in the order they were displayed after the last sync
+    visible_ids: Vec<ItemId>,
+
+    /// Whether all of this section's items are hidden
+    collapsed: bool,
 }
 
 impl State {
-    pub fn new(section: Option<&Section>, items: &'_ [&Item]) -> Self {
-        let tree_items = Self::build_tree(items, None);
+    pub fn new(
+        section: Option<&Section>,
+        items: &'_ [&Item],
+        config: &Config,
+        theme: &Theme,
+    ) -> Self {
+        let mut state = Self {
+            id: section.map(|s| s.id.clone()),
+            name: None,
+            tree: TreeState::default(),
+            seen_ids: HashMap::new(),
+            visible_ids: vec![],
+            collapsed: false,
+        };
+        state.sync_with_items(section, items, config, theme);
+        state
+    }
 
-        let mut tree_state = TreeState::default();
+    /// Updates this state to match a (possibly changed) list of items, keeping the same
+    /// item selected if it's still around. If it isn't, the nearest item that's still
+    /// visible gets selected instead.
+    pub fn sync_with_items(
+        &mut self,
+        section: Option<&Section>,
+        items: &'_ [&Item],
+        config: &Config,
+        theme: &Theme,
+    ) {
+        self.name = section.map(|s| s.name.clone());
 
+        // expand items that are new to us, unless they're collapsed in Todoist, and follow
+        // items that have been expanded or collapsed since (like in the web app)
         for item in items {
-            if !item.collapsed {
-                tree_state.open(vec![item.id.clone()]);
+            if self.seen_ids.insert(item.id.clone(), item.collapsed) == Some(item.collapsed) {
+                continue;
+            }
+            if let Some(path) = path_to_item(&item.id, items) {
+                if item.collapsed {
+                    self.tree.close(&path);
+                } else {
+                    self.tree.open(path);
+                }
             }
         }
 
-        tree_state.select_first(&tree_items);
+        let tree_items = Self::build_tree(items, None, config, theme);
+        let visible_paths: Vec<Vec<ItemId>> = self
+            .tree
+            .flatten(&tree_items)
+            .into_iter()
+            .map(|flattened| flattened.identifier)
+            .collect();
 
-        Self {
-            id: section.map(|s| s.id.clone()),
-            name: section.map(|s| s.name.clone()),
-            tree: tree_state,
+        let selected_path = self.tree.selected().pop().and_then(|selected_id| {
+            // the item might have moved in the tree, so recompute its path
+            path_to_item(&selected_id, items)
+                .filter(|path| visible_paths.contains(path))
+                .or_else(|| {
+                    let index = self
+                        .visible_ids
+                        .iter()
+                        .position(|id| *id == selected_id)
+                        .unwrap_or(0);
+                    nearest_remaining(&self.visible_ids, index, |id| {
+                        visible_paths.iter().any(|path| path.last() == Some(id))
+                    })
+                    .and_then(|id| path_to_item(id, items))
+                })
+        });
+
+        match selected_path {
+            Some(path) => {
+                self.tree.select(path);
+            }
+            None => self.tree.select_first(&tree_items),
+        }
+
+        self.visible_ids = visible_paths
+            .into_iter()
+            .filter_map(|mut path| path.pop())
+            .collect();
+    }
+
+    /// Swaps the temporary ids of new items (and of this section, if it's new) for the real
+    /// ids that a sync gave them, so that the same items stay selected and expanded.
+    pub fn replace_temp_ids(&mut self, temp_id_mapping: &HashMap<String, String>) {
+        let real_id = |id: &ItemId| {
+            temp_id_mapping
+                .get(&id.to_string())
+                .map_or_else(|| id.clone(), |real_id| real_id.as_str().into())
+        };
+
+        if let Some(real_id) = self
+            .id
+            .as_ref()
+            .and_then(|id| temp_id_mapping.get(&id.to_string()))
+        {
+            self.id = Some(real_id.as_str().into());
+        }
+
+        let opened: Vec<Vec<ItemId>> = self
+            .tree
+            .opened()
+            .iter()
+            .map(|path| path.iter().map(real_id).collect())
+            .collect();
+        let selected: Vec<ItemId> = self.tree.selected().iter().map(real_id).collect();
+        self.tree.close_all();
+        for path in opened {
+            self.tree.open(path);
+        }
+        self.tree.select(selected);
+
+        self.seen_ids = self
+            .seen_ids
+            .iter()
+            .map(|(id, collapsed)| (real_id(id), *collapsed))
+            .collect();
+        self.visible_ids = self.visible_ids.iter().map(real_id).collect();
+    }
+
+    /// The tree items to show for this section, which there aren't any of when it's collapsed.
+    pub fn tree_items<'b>(
+        &self,
+        items: &'_ [&Item],
+        config: &Config,
+        theme: &Theme,
+    ) -> Vec<TreeItem<'b, ItemId>> {
+        if self.collapsed {
+            vec![]
+        } else {
+            Self::build_tree(items, None, config, theme)
         }
     }
 
     pub fn build_tree<'b>(
         items: &'_ [&Item],
         parent_id: Option<&ItemId>,
+        config: &Config,
+        theme: &Theme,
     ) -> Vec<TreeItem<'b, ItemId>> {
         items
             .iter()
             .filter_map(|item| {
                 if item.parent_id.as_ref() == parent_id {
                     // TODO : sort by `item.child_order`? or should that be done in the model?
-                    let children = Self::build_tree(items, Some(&item.id));
+                    let children = Self::build_tree(items, Some(&item.id), config, theme);
                     Some(
-                        // TODO: format the item's text
-                        TreeItem::new(item.id.clone(), Into::<Text>::into(*item), children)
+                        TreeItem::new(item.id.clone(), item_text(item, config, theme), children)
                             .expect("Item ids must be unique"),
                     )
                 } else {
@@ -86,17 +214,81 @@ impl State {
     }
 
     pub fn selected_item_id(&self) -> Option<ItemId> {
+        if self.collapsed {
+            return None;
+        }
         self.tree.selected().last().cloned()
     }
 
-    pub fn handle_key(&mut self, key: KeyEvent, tree_items: &[TreeItem<'_, ItemId>]) -> bool {
-        match key.code {
-            KeyCode::Char('\n' | ' ') => {
+    /// Whether the item at `path` is expanded.
+    pub fn is_expanded(&self, tree_items: &[TreeItem<'_, ItemId>], path: &[ItemId]) -> bool {
+        super::is_expanded(&self.tree, tree_items, path)
+    }
+
+    /// Whether each of the items with subtasks is collapsed in this section's tree, including
+    /// the ones that are hidden under a collapsed parent.
+    pub fn collapsed_items(&self, items: &[&Item]) -> Vec<(ItemId, bool)> {
+        items
+            .iter()
+            .filter(|item| {
+                items
+                    .iter()
+                    .any(|child| child.parent_id.as_ref() == Some(&item.id))
+            })
+            .filter_map(|item| {
+                let path = path_to_item(&item.id, items)?;
+                Some((item.id.clone(), !self.tree.opened().contains(&path)))
+            })
+            .collect()
+    }
+
+    /// Collapses every item, and the section itself if it has a name to show that it's there.
+    pub fn collapse_all(&mut self) {
+        // keep the selection visible by moving it up to the top level
+        let selected = self.tree.selected();
+        self.tree.close_all();
+        if let Some(top_level_id) = selected.first() {
+            self.tree.select(vec![top_level_id.clone()]);
+        }
+        self.collapsed = self.name.is_some();
+    }
+
+    pub fn expand_all(&mut self, items: &[&Item]) {
+        self.collapsed = false;
+        for item in items {
+            if let Some(path) = path_to_item(&item.id, items) {
+                self.tree.open(path);
+            }
+        }
+    }
+
+    /// Returns true if the action tried to move the selection past the first or last item.
+    pub fn handle_action(&mut self, action: Action, tree_items: &[TreeItem<'_, ItemId>]) -> bool {
+        match action {
+            Action::ToggleExpanded => {
                 self.tree.toggle_selected();
             }
-            KeyCode::Left => self.tree.key_left(),
-            KeyCode::Right => self.tree.key_right(),
-            KeyCode::Down => {
+            Action::Collapse => self.tree.key_left(),
+            Action::Expand => self.tree.key_right(),
+            Action::MoveToTop => self.tree.select_first(tree_items),
+            Action::MoveToBottom => self.tree.select_last(tree_items),
+            Action::CollapseSubtree => {
+                // once there's nothing left to collapse in the section, collapse the section
+                let selected = self.tree.selected();
+                if self.is_expanded(tree_items, &selected) {
+                    self.tree.close(&selected);
+                } else if self.name.is_some() {
+                    self.collapsed = true;
+                }
+            }
+            Action::ExpandSubtree => {
+                if self.collapsed {
+                    self.collapsed = false;
+                } else {
+                    self.tree.open(self.tree.selected());
+                }
+            }
+            Action::MoveDown => {
                 let before = self.tree.selected();
                 self.tree.key_down(tree_items);
                 let after = self.tree.selected();
@@ -105,7 +297,7 @@ impl State {
                     return true;
                 }
             }
-            KeyCode::Up => {
+            Action::MoveUp => {
                 let before = self.tree.selected();
                 self.tree.key_up(tree_items);
                 let after = self.tree.selected();
@@ -120,6 +312,36 @@ impl State {
     }
 }
 
+/// Computes the path through the item tree to the item with the given id, or `None` if
+/// the item (or one of its ancestors) isn't in the list.
+fn path_to_item(item_id: &ItemId, items: &[&Item]) -> Option<Vec<ItemId>> {
+    let mut path = vec![];
+    let mut current_id = Some(item_id);
+
+    while let Some(id) = current_id {
+        let item = items.iter().find(|item| item.id == *id)?;
+        path.push(item.id.clone());
+        current_id = item.parent_id.as_ref();
+    }
+
+    path.reverse();
+    Some(path)
+}
+
+/// Looks through a list of things that used to be displayed for the one closest to `index`
+/// (preferring the ones after it) that's still around.
+pub(super) fn nearest_remaining<T>(
+    previous: &[T],
+    index: usize,
+    remains: impl Fn(&T) -> bool,
+) -> Option<&T> {
+    let (before, after) = previous.split_at(index.min(previous.len()));
+    after
+        .iter()
+        .chain(before.iter().rev())
+        .find(|element| remains(element))
+}
+
 #[derive(Debug, Default)]
 pub struct Widget<'a, 'b> {
     /// The id of the section currently focused by the app
@@ -128,33 +350,36 @@ pub struct Widget<'a, 'b> {
     /// The items in this section, as
     tree_items: &'b [TreeItem<'a, ItemId>],
 
+    /// How the selected item is highlighted
+    highlight_style: Style,
+
     marker: std::marker::PhantomData<(&'b mut State, &'b mut Model)>,
 }
 
 impl<'a, 'b> Widget<'a, 'b> {
-    pub fn new(focused_id: Option<SectionId>, tree_items: &'b [TreeItem<'a, ItemId>]) -> Self {
+    pub fn new(
+        focused_id: Option<SectionId>,
+        tree_items: &'b [TreeItem<'a, ItemId>],
+        highlight_style: Style,
+    ) -> Self {
         Self {
             focused_id,
             tree_items,
+            highlight_style,
             marker: std::marker::PhantomData,
         }
     }
 }
 
 impl<'a, 'b: 'a> StatefulWidget for Widget<'a, 'b> {
-    type State = (&'b State, &'b mut Model);
+    type State = (&'b State, &'b Model);
 
     fn render(self, area: Rect, buf: &mut Buffer, (state, _): &mut Self::State) {
         let mut tree = Tree::new(self.tree_items.to_vec()).expect("Item ids must be unique");
 
         // only highlight things in the currently-focused section
         if state.id == self.focused_id {
-            tree = tree.highlight_style(
-                Style::default()
-                    .bg(Color::White)
-                    .fg(Color::Black)
-                    .add_modifier(Modifier::BOLD),
-            );
+            tree = tree.highlight_style(self.highlight_style);
         }
 
         let tree_area = if let Some(ref name) = state.name {
@@ -166,7 +391,11 @@ impl<'a, 'b: 'a> StatefulWidget for Widget<'a, 'b> {
             let title_area = layout[0];
             let tree_area = layout[1];
 
-            let title = Paragraph::new(name.clone());
+            let title = Paragraph::new(if state.collapsed {
+                format!("{name} ▸")
+            } else {
+                name.clone()
+            });
             title.render(title_area, buf);
 
             tree_area
@@ -178,23 +407,24 @@ impl<'a, 'b: 'a> StatefulWidget for Widget<'a, 'b> {
     }
 }
 
-impl From<&Item> for Text<'_> {
-    fn from(item: &Item) -> Self {
-        let mut spans = vec![
-            Span::raw(if item.checked { "✓ " } else { "- " }),
-            Span::raw(item.content.clone()),
-        ];
-
-        if let Some(due_date) = &item.due {
-            spans.push(Span::styled(
-                format!("  ({due_date})"),
-                Style::default().fg(Color::Gray),
-            ));
-        }
-        let mut text = Text::from(Line::from(spans));
-        if item.checked {
-            text = text.style(Style::default().fg(Color::Green));
-        }
-        text
+fn item_text<'a>(item: &Item, config: &Config, theme: &Theme) -> Text<'a> {
+    let mut spans = vec![
+        Span::raw(if item.checked { "✓ " } else { "- " }),
+        Span::raw(item.content.clone()),
+    ];
+
+    if let Some(due_date) = &item.due {
+        spans.push(Span::styled(
+            format!(
+                "  ({})",
+                due_date.format(&config.date_format, &config.time_format)
+            ),
+            theme.muted,
+        ));
+    }
+    let mut text = Text::from(Line::from(spans));
+    if item.checked {
+        text = text.style(theme.completed);
     }
+    text
 }
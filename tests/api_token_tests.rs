@@ -6,8 +6,9 @@ pub mod test_utils;
 pub mod api_token_tests {
     use anyhow::Result;
     use std::fs;
+    use todoist_tui::sync::{Request, Response};
 
-    use crate::test_utils::FsMockBuilder;
+    use crate::test_utils::{ApiMockBuilder, FsMockBuilder};
 
     #[test]
     fn print_error_when_api_token_missing() -> Result<(), Box<dyn std::error::Error>> {
@@ -23,6 +24,7 @@ pub mod api_token_tests {
         cmd.arg("--local-dir-override").arg(mock_data_dir);
         cmd.arg("--sync-url-override").arg(server_url);
         cmd.arg("sync");
+        cmd.env_remove("TODOIST_API_TOKEN");
 
         // check output
         cmd.assert()
@@ -63,4 +65,100 @@ pub mod api_token_tests {
         );
         Ok(())
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn store_api_token_readable_only_by_user() -> Result<(), Box<dyn std::error::Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        // a temporary file left behind by a crash, which anyone can read
+        let mock_fs = FsMockBuilder::new()?.mock_file_contents("client_auth.toml.tmp", "")?;
+        let mock_data_dir = mock_fs.path();
+        fs::set_permissions(
+            mock_data_dir.join("client_auth.toml.tmp"),
+            fs::Permissions::from_mode(0o644),
+        )?;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("set-token").arg("MOCK_API_TOKEN");
+
+        cmd.assert()
+            .stderr(predicates::str::contains("plain text"))
+            .code(0);
+
+        let permissions = fs::metadata(mock_data_dir.join("client_auth.toml"))?.permissions();
+        assert_eq!(permissions.mode() & 0o777, 0o600);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_store_with_command() -> Result<(), Box<dyn std::error::Error>> {
+        let mock_fs = FsMockBuilder::new()?;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("set-token")
+            .arg("--store")
+            .arg("keyring")
+            .arg("--command")
+            .arg("echo MOCK_API_TOKEN");
+        cmd.assert()
+            .stderr(predicates::str::contains("cannot be used with"))
+            .code(2);
+        assert!(!mock_fs.path().join("client_auth.toml").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_api_token_from_command() -> Result<(), Box<dyn std::error::Error>> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response("sync", |_: Request| true, Response::default())
+            .await;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("set-token")
+            .arg("--command")
+            .arg("echo MOCK_API_TOKEN");
+        cmd.assert().code(0);
+
+        // the mock server only responds to requests with the right token
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.env_remove("TODOIST_API_TOKEN");
+        cmd.arg("sync");
+        cmd.assert()
+            .stdout(predicates::str::contains("Syncing... Done."))
+            .code(0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_api_token_from_environment() -> Result<(), Box<dyn std::error::Error>> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response("sync", |_: Request| true, Response::default())
+            .await;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.env("TODOIST_API_TOKEN", "MOCK_API_TOKEN");
+        cmd.arg("sync");
+        cmd.assert()
+            .stdout(predicates::str::contains("Syncing... Done."))
+            .code(0);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,373 @@
+#![warn(clippy::all, clippy::pedantic, clippy::unwrap_used)]
+pub mod test_utils;
+
+#[cfg(test)]
+pub mod config_tests {
+    use anyhow::Result;
+    use assert_cmd::Command;
+    use std::{fs, path::Path};
+    use todoist_tui::model::{item::Item, project::Project, user::User, Model};
+
+    use crate::test_utils::FsMockBuilder;
+
+    // no need to mock the server, but still going to use a fake url to prevent
+    // accidental calls to the real api
+    fn cmd(mock_data_dir: &Path) -> Result<Command> {
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.env_remove("TODOIST_TUI_PROFILE");
+        Ok(cmd)
+    }
+
+    fn mock_model(projects: Vec<Project>, items: Vec<Item>) -> Model {
+        Model {
+            user: User {
+                full_name: "Drew".to_string(),
+                inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+            },
+            projects,
+            items,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn set_and_get_settings() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("sync_interval")
+            .arg("30")
+            .assert()
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("get")
+            .arg("sync_interval")
+            .assert()
+            .stdout("30\n")
+            .code(0);
+
+        // settings that haven't been set have their defaults
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("get")
+            .arg("date_format")
+            .assert()
+            .stdout("%Y-%m-%d\n")
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("list")
+            .assert()
+            .stdout(predicates::str::contains("sync_interval = 30\n"))
+            .stdout(predicates::str::contains("sort_order = \"manual\"\n"))
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("path")
+            .assert()
+            .stdout(predicates::str::contains("config.toml"))
+            .code(0);
+
+        // only the setting that was set is written to the file
+        let file_contents = fs::read_to_string(mock_data_dir.join("config.toml"))?;
+        assert_eq!(file_contents.trim(), "sync_interval = 30");
+
+        Ok(())
+    }
+
+    #[test]
+    fn set_settings_as_their_types() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+
+        for (key, value) in [
+            ("default_project", "2203306141"),
+            ("date_format", "1"),
+            ("sync_interval", "30"),
+            ("show_completed", "true"),
+        ] {
+            cmd(mock_data_dir)?
+                .arg("config")
+                .arg("set")
+                .arg(key)
+                .arg(value)
+                .assert()
+                .code(0);
+        }
+
+        // project ids and names are strings, even if they look like numbers
+        let file_contents = fs::read_to_string(mock_data_dir.join("config.toml"))?;
+        assert_eq!(
+            file_contents,
+            "default_project = \"2203306141\"\n\
+             date_format = \"1\"\n\
+             sync_interval = 30\n\
+             show_completed = true\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn get_default_keybindings() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("config.toml", "[keybindings]\nquit = \"x\"")?;
+        let mock_data_dir = mock_fs.path();
+
+        for (key, keys) in [
+            ("keybindings.quit", "x\n"),
+            ("keybindings.move_down", "down j\n"),
+        ] {
+            cmd(mock_data_dir)?
+                .arg("config")
+                .arg("get")
+                .arg(key)
+                .assert()
+                .stdout(keys)
+                .code(0);
+        }
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("get")
+            .arg("keybindings.fly")
+            .assert()
+            .stderr(predicates::str::contains("There's no action named 'fly'"))
+            .failure();
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_comments_when_setting() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?.mock_file_contents(
+            "config.toml",
+            "# my settings\n\
+             week_start = \"mon\" # like at work\n\
+             date_format = \"%d.%m.%Y\"\n\
+             \n\
+             [keybindings]\n\
+             # vim keys\n\
+             move_down = \"j\"\n",
+        )?;
+        let mock_data_dir = mock_fs.path();
+
+        for (key, value) in [
+            ("week_start", "sun"),
+            ("sync_interval", "30"),
+            ("keybindings.move_up", "k"),
+        ] {
+            cmd(mock_data_dir)?
+                .arg("config")
+                .arg("set")
+                .arg(key)
+                .arg(value)
+                .assert()
+                .code(0);
+        }
+
+        let file_contents = fs::read_to_string(mock_data_dir.join("config.toml"))?;
+        assert_eq!(
+            file_contents,
+            "# my settings\n\
+             week_start = \"sun\" # like at work\n\
+             date_format = \"%d.%m.%Y\"\n\
+             sync_interval = 30\n\
+             \n\
+             [keybindings]\n\
+             # vim keys\n\
+             move_down = \"j\"\n\
+             move_up = \"k\"\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_invalid_settings() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("sync_interval")
+            .arg("often")
+            .assert()
+            .stderr(predicates::str::contains("sync_interval"))
+            .failure();
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("colour")
+            .arg("blue")
+            .assert()
+            .stderr(predicates::str::contains("unknown field `colour`"))
+            .failure();
+
+        // a date doesn't have a time to show
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("date_format")
+            .arg("%d.%m %H:%M")
+            .assert()
+            .stderr(predicates::str::contains("Invalid value for 'date_format'"))
+            .failure();
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("get")
+            .arg("colour")
+            .assert()
+            .stderr(predicates::str::contains(
+                "There's no setting named 'colour'",
+            ))
+            .failure();
+
+        // nothing should have been written
+        assert!(!mock_data_dir.join("config.toml").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn choose_theme() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("theme")
+            .arg("neon")
+            .assert()
+            .stderr(predicates::str::contains("There's no theme named 'neon'"))
+            .failure();
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("themes.neon.selected_bg")
+            .arg("#ff00ff")
+            .assert()
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("theme")
+            .arg("neon")
+            .assert()
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("themes.neon.muted")
+            .arg("greyish")
+            .assert()
+            .stderr(predicates::str::contains("'themes.neon.muted'"))
+            .failure();
+
+        Ok(())
+    }
+
+    #[test]
+    fn point_at_bad_setting_in_config_file() -> Result<()> {
+        let mock_fs =
+            FsMockBuilder::new()?.mock_file_contents("config.toml", "date_format = \"%Q\"")?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("list")
+            .assert()
+            .stderr(predicates::str::contains("Invalid value for 'date_format'"))
+            .failure();
+
+        // but the config commands still work, so that it can be fixed
+        cmd(mock_data_dir)?
+            .arg("config")
+            .arg("set")
+            .arg("date_format")
+            .arg("%d/%m/%Y")
+            .assert()
+            .code(0);
+
+        cmd(mock_data_dir)?.arg("list").assert().code(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_todo_to_default_project() -> Result<()> {
+        let project = Project::new("Work");
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("config.toml", "default_project = \"work\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&mock_model(vec![project.clone()], vec![]))?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("add")
+            .arg("Todo One!")
+            .arg("--no-sync")
+            .assert()
+            .stdout(predicates::str::contains("'Todo One!' added to Work."))
+            .code(0);
+
+        let file_contents = fs::read_to_string(mock_data_dir.join("sync.json"))?;
+        let model: Model = serde_json::from_str(&file_contents)?;
+        assert_eq!(model.items.len(), 1);
+        assert_eq!(model.items[0].project_id, project.id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_and_complete_in_configured_order() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("config.toml", "sort_order = \"alphabetical\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&mock_model(
+                    vec![],
+                    vec![
+                        Item::new("Cherries", "MOCK_INBOX_PROJECT_ID"),
+                        Item::new("apples", "MOCK_INBOX_PROJECT_ID"),
+                        Item::new("Bananas", "MOCK_INBOX_PROJECT_ID"),
+                    ],
+                ))?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("list")
+            .assert()
+            .stdout("Inbox: \n[1] apples\n[2] Bananas\n[3] Cherries\n")
+            .code(0);
+
+        // the numbers used by `complete` match the ones that were listed
+        cmd(mock_data_dir)?
+            .arg("complete")
+            .arg("2")
+            .arg("--no-sync")
+            .assert()
+            .stdout(predicates::str::contains("'Bananas' marked complete."))
+            .code(0);
+
+        Ok(())
+    }
+}
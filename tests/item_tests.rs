@@ -9,6 +9,8 @@ pub mod item_tests {
     use todoist_tui::model::{
         due_date::{Due, DueDate},
         item::Item,
+        project::Project,
+        section::Section,
         user::User,
         Model,
     };
@@ -205,4 +207,588 @@ pub mod item_tests {
 
         Ok(())
     }
+
+    /// Writes a `sync.json` with two todos in the inbox, one of which is in a section.
+    fn mock_inbox() -> Result<FsMockBuilder> {
+        let mock_item_1 = Item::new("Todo One!", "MOCK_INBOX_PROJECT_ID")
+            .due(Some(Due {
+                date: DueDate::Date(
+                    chrono::NaiveDate::from_ymd_opt(2021, 10, 7).expect("valid date"),
+                ),
+            }))
+            .priority(4);
+        let mock_item_2 =
+            Item::new("Todo\tTwo!", "MOCK_INBOX_PROJECT_ID").section_id("MOCK_SECTION_ID");
+
+        FsMockBuilder::new()?.mock_file_contents(
+            "sync.json",
+            serde_json::to_string_pretty(&Model {
+                user: User {
+                    full_name: "Drew".to_string(),
+                    inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                },
+                projects: vec![Project {
+                    id: "MOCK_INBOX_PROJECT_ID".into(),
+                    ..Project::new("Inbox")
+                }],
+                sections: vec![Section {
+                    id: "MOCK_SECTION_ID".into(),
+                    ..Section::new("Later", "MOCK_INBOX_PROJECT_ID")
+                }],
+                items: vec![mock_item_1, mock_item_2],
+                ..Default::default()
+            })?,
+        )
+    }
+
+    #[test]
+    fn list_todos_as_json_and_ndjson() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("list").arg("--output").arg("json");
+
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        let records: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(
+            records[0],
+            serde_json::json!({
+                "id": records[0]["id"],
+                "content": "Todo One!",
+                "project": "Inbox",
+                "section": null,
+                "due": "2021-10-07",
+                "priority": 4,
+                "checked": false,
+                "parent_id": null,
+                "labels": [],
+            })
+        );
+        assert_eq!(records[1]["section"], "Later");
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("--output").arg("ndjson").arg("list");
+
+        let output = String::from_utf8(cmd.assert().code(0).get_output().stdout.clone())?;
+        let contents = output
+            .lines()
+            .map(|line| Ok(serde_json::from_str::<serde_json::Value>(line)?["content"].clone()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(contents, ["Todo One!", "Todo\tTwo!"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_todos_as_tsv() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("list").arg("-o").arg("tsv");
+
+        cmd.assert()
+            .stdout(predicates::str::starts_with(
+                "id\tcontent\tproject\tsection\tdue\tpriority\tchecked\tparent_id\tlabels\n",
+            ))
+            .stdout(predicates::str::contains(
+                "\tTodo\\tTwo!\tInbox\tLater\t\t1\tfalse\t\t\n",
+            ))
+            .code(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_and_complete_todos_as_json() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("--output").arg("json");
+        cmd.arg("add").arg("new todo").arg("--no-sync");
+
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        let record: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(record["content"], "new todo");
+        assert_eq!(record["project"], "Inbox");
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("--output").arg("json");
+        cmd.arg("complete").arg("3").arg("--no-sync");
+
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        let completed: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(completed["id"], record["id"]);
+        assert_eq!(completed["checked"], true);
+
+        Ok(())
+    }
+
+    #[test]
+    fn exit_with_code_for_invalid_input() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("complete").arg("7").arg("--no-sync");
+
+        cmd.assert()
+            .stderr(predicates::str::contains("no todo matching '7'"))
+            .code(2);
+
+        Ok(())
+    }
+
+    /// Runs the app with the given arguments and returns what it printed.
+    fn run(mock_fs: &FsMockBuilder, args: &[&str]) -> Result<String> {
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("--date-time-override").arg("2021-10-06T08:00:00");
+        cmd.args(args);
+
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        Ok(String::from_utf8(output)?)
+    }
+
+    fn with_id(id: &str, item: Item) -> Item {
+        Item {
+            id: id.into(),
+            ..item
+        }
+    }
+
+    fn date(day: u32) -> Due {
+        Due {
+            date: DueDate::Date(
+                chrono::NaiveDate::from_ymd_opt(2021, 10, day).expect("valid date"),
+            ),
+        }
+    }
+
+    #[test]
+    fn list_project_with_subprojects_as_tree() -> Result<()> {
+        let inbox = Project {
+            id: "MOCK_INBOX_PROJECT_ID".into(),
+            inbox_project: true,
+            ..Project::new("Inbox")
+        };
+        let work = Project::new("Work").child_order(1);
+        let side = Project::new("Side").parent_id(&work.id);
+        let later = Section::new("Later", &work.id);
+        let plan = with_id("1001aaaa", Item::new("Plan", &work.id).child_order(1));
+        let draft = with_id("1002bbbb", Item::new("Draft", &work.id).parent_id(&plan.id));
+        let done = with_id(
+            "1003",
+            Item::new("Done", &work.id)
+                .parent_id(&draft.id)
+                .checked(true),
+        );
+        let mock_fs = FsMockBuilder::new()?.mock_file_contents(
+            "sync.json",
+            serde_json::to_string_pretty(&Model {
+                user: User {
+                    full_name: "Drew".to_string(),
+                    inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                },
+                items: vec![
+                    with_id("2001", Item::new("Review", &work.id).section_id(&later.id)),
+                    with_id("3001", Item::new("Side todo", &side.id)),
+                    with_id("4001", Item::new("Inbox todo", "MOCK_INBOX_PROJECT_ID")),
+                    with_id("5001", Item::new("Think", &work.id).due(Some(date(7)))),
+                    done,
+                    draft,
+                    plan,
+                ],
+                projects: vec![side, work, inbox],
+                sections: vec![later],
+                ..Default::default()
+            })?,
+        )?;
+
+        assert_eq!(
+            run(&mock_fs, &["list", "--project", "work", "--subprojects"])?,
+            "Work\n\
+             - Think  (2021-10-07)  [5001]\n\
+             - Plan  [1001]\n  \
+               - Draft  [1002]\n\
+             \n\
+             Work / Later\n\
+             - Review  [2001]\n\
+             \n\
+             Side\n\
+             - Side todo  [3001]\n"
+        );
+
+        // a completed subtask is shown under its parent
+        assert!(run(&mock_fs, &["list", "-p", "Work", "--completed"])?
+            .contains("- Plan  [1001]\n  - Draft  [1002]\n    ✓ Done  [1003]\n"));
+
+        let all = run(&mock_fs, &["list", "--all", "--output", "ndjson"])?;
+        let contents = all
+            .lines()
+            .map(|line| Ok(serde_json::from_str::<serde_json::Value>(line)?["content"].clone()))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            contents,
+            [
+                "Inbox todo",
+                "Think",
+                "Plan",
+                "Draft",
+                "Review",
+                "Side todo"
+            ]
+        );
+
+        let section = run(&mock_fs, &["list", "-p", "work", "--section", "later"])?;
+        assert_eq!(section, "Work / Later\n- Review  [2001]\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_todos_by_due_date() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?.mock_file_contents(
+            "sync.json",
+            serde_json::to_string_pretty(&Model {
+                user: User {
+                    full_name: "Drew".to_string(),
+                    inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                },
+                projects: vec![Project {
+                    id: "MOCK_INBOX_PROJECT_ID".into(),
+                    ..Project::new("Inbox")
+                }],
+                items: vec![
+                    Item::new("Overdue", "MOCK_INBOX_PROJECT_ID").due(Some(date(5))),
+                    Item::new("Today", "MOCK_INBOX_PROJECT_ID").due(Some(date(6))),
+                    Item::new("Weekend", "MOCK_INBOX_PROJECT_ID").due(Some(date(9))),
+                    Item::new("Next week", "MOCK_INBOX_PROJECT_ID").due(Some(date(12))),
+                    Item::new("Done today", "MOCK_INBOX_PROJECT_ID")
+                        .due(Some(date(6)))
+                        .checked(true),
+                    Item::new("Someday", "MOCK_INBOX_PROJECT_ID"),
+                ],
+                ..Default::default()
+            })?,
+        )?;
+
+        let list = |args: &[&str]| -> Result<Vec<String>> {
+            let output = run(&mock_fs, &[&["list", "-o", "tsv"], args].concat())?;
+            Ok(output
+                .lines()
+                .skip(1)
+                .filter_map(|line| line.split('\t').nth(1).map(ToString::to_string))
+                .collect())
+        };
+
+        assert_eq!(list(&["--due", "today"])?, ["Today"]);
+        assert_eq!(
+            list(&["--due", "today", "--completed"])?,
+            ["Today", "Done today"]
+        );
+        assert_eq!(list(&["--due", "overdue"])?, ["Overdue"]);
+        assert_eq!(list(&["--due", "week"])?, ["Overdue", "Today", "Weekend"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_project_or_section() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        for args in [["list", "--project", "Nope"], ["list", "--section", "Nope"]] {
+            let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+            cmd.arg("--local-dir-override").arg(mock_fs.path());
+            cmd.arg("--sync-url-override").arg("fake/server/url");
+            cmd.args(args);
+
+            cmd.assert()
+                .stderr(predicates::str::contains("'Nope'"))
+                .code(2);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn complete_todos_by_id_or_text() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?.mock_file_contents(
+            "sync.json",
+            serde_json::to_string_pretty(&Model {
+                user: User {
+                    full_name: "Drew".to_string(),
+                    inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                },
+                items: vec![
+                    with_id(
+                        "2995104339",
+                        Item::new("Do a barrel roll!", "MOCK_INBOX_PROJECT_ID"),
+                    ),
+                    with_id(
+                        "2995104340",
+                        Item::new("Roll call", "MOCK_INBOX_PROJECT_ID"),
+                    ),
+                    with_id(
+                        "6Jf8VQXxpwv56VQ7",
+                        Item::new("Use the boost", "OTHER_PROJECT_ID"),
+                    ),
+                ],
+                ..Default::default()
+            })?,
+        )?;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("complete").arg("ROLL").arg("--no-sync");
+        cmd.assert()
+            .stderr(predicates::str::contains(
+                "'ROLL' matches more than one todo",
+            ))
+            .stderr(predicates::str::contains("299510433  Do a barrel roll!"))
+            .stderr(predicates::str::contains("299510434  Roll call"))
+            .code(2);
+
+        assert!(run(&mock_fs, &["complete", "2995104340", "--no-sync"])?.contains("'Roll call'"));
+        assert!(run(&mock_fs, &["complete", "6Jf8", "--no-sync"])?.contains("'Use the boost'"));
+
+        // now that the others are done, there's only one todo that matches
+        assert!(run(&mock_fs, &["complete", "roll", "--no-sync"])?.contains("'Do a barrel roll!'"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_todo_with_quick_add_syntax_and_options() -> Result<()> {
+        let work = Project::new("Work");
+        let calls = Section::new("Calls", &work.id);
+        let plan = with_id(
+            "1001aaaa",
+            Item::new("Plan the trip", &work.id).section_id(&calls.id),
+        );
+        let mock_fs = FsMockBuilder::new()?.mock_file_contents(
+            "sync.json",
+            serde_json::to_string_pretty(&Model {
+                user: User {
+                    full_name: "Drew".to_string(),
+                    inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                },
+                items: vec![plan],
+                projects: vec![work],
+                sections: vec![calls],
+                ..Default::default()
+            })?,
+        )?;
+
+        let added = run(
+            &mock_fs,
+            &[
+                "-o",
+                "json",
+                "add",
+                "Call Bob tomorrow #Work /Calls p2 @phone",
+                "--no-sync",
+            ],
+        )?;
+        let record: serde_json::Value = serde_json::from_str(&added)?;
+        assert_eq!(record["content"], "Call Bob");
+        assert_eq!(record["project"], "Work");
+        assert_eq!(record["section"], "Calls");
+        assert_eq!(record["due"], "2021-10-07");
+        assert_eq!(record["priority"], 3);
+        assert_eq!(record["labels"], serde_json::json!(["phone"]));
+
+        // the options win over the text
+        let added = run(
+            &mock_fs,
+            &[
+                "-o",
+                "json",
+                "add",
+                "Book hotel p4 @travel",
+                "--parent",
+                "trip",
+                "--priority",
+                "1",
+                "--label",
+                "money",
+                "--no-sync",
+            ],
+        )?;
+        let record: serde_json::Value = serde_json::from_str(&added)?;
+        assert_eq!(record["content"], "Book hotel");
+        assert_eq!(record["section"], "Calls");
+        assert_eq!(record["parent_id"], "1001aaaa");
+        assert_eq!(record["priority"], 4);
+        assert_eq!(record["labels"], serde_json::json!(["travel", "money"]));
+
+        let model: Model =
+            serde_json::from_str(&fs::read_to_string(mock_fs.path().join("sync.json"))?)?;
+        assert_eq!(model.commands.len(), 2);
+        assert_eq!(model.items.len(), 3);
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("add").arg("Call Bob #Home").arg("--no-sync");
+        cmd.assert()
+            .stderr(predicates::str::contains("no project named 'Home'"))
+            .code(2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn add_and_complete_todos_from_stdin() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        // nothing is added if any line is wrong
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("add").arg("--stdin").arg("--no-sync");
+        cmd.write_stdin("Fine\nNot fine #Nowhere\n");
+        cmd.assert()
+            .stderr(predicates::str::contains("line 2"))
+            .stderr(predicates::str::contains("no project named 'Nowhere'"))
+            .code(2);
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("add").arg("--stdin").arg("--no-sync");
+        cmd.write_stdin("Three\n\tFour\n");
+        cmd.assert()
+            .stdout(predicates::str::contains(
+                "'Three' added to inbox.\n'Four' added to inbox.",
+            ))
+            .code(0);
+
+        // the numbers all refer to the list from before any of them were completed
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("complete").arg("--stdin").arg("--no-sync");
+        cmd.write_stdin("1\n2\n");
+        cmd.assert()
+            .stdout(predicates::str::contains(
+                "'Todo One!' marked complete.\n'Todo\tTwo!' marked complete.",
+            ))
+            .code(0);
+
+        let model: Model =
+            serde_json::from_str(&fs::read_to_string(mock_fs.path().join("sync.json"))?)?;
+        assert_eq!(model.commands.len(), 4);
+        assert_eq!(model.items[3].parent_id, Some(model.items[2].id.clone()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn print_completion_scripts() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        let bash = run(&mock_fs, &["completions", "bash"])?;
+        assert!(bash.contains("complete -F _todoist-tui_values"));
+
+        let zsh = run(&mock_fs, &["completions", "zsh"])?;
+        assert!(zsh.starts_with("#compdef todoist-tui\n"));
+        assert!(zsh.contains(":PROJECT:_todoist-tui_values projects'"));
+        assert!(zsh.contains("part of its text:_todoist-tui_values todos'"));
+
+        let fish = run(&mock_fs, &["completions", "fish"])?;
+        assert!(fish.contains("(todoist-tui completion-values labels 2>/dev/null)"));
+
+        assert_eq!(
+            run(&mock_fs, &["completion-values", "sections"])?,
+            "Later\tInbox\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_projects() -> Result<()> {
+        let mock_fs = mock_inbox()?;
+
+        assert_eq!(
+            run(&mock_fs, &["export", "--project", "inbox"])?,
+            "# Inbox\n\n- [ ] Todo One! p1 due:2021-10-07\n\n## Later\n\n- [ ] Todo\tTwo!\n"
+        );
+
+        let snapshot: serde_json::Value =
+            serde_json::from_str(&run(&mock_fs, &["export", "--format", "json"])?)?;
+        assert_eq!(snapshot["projects"][0]["name"], "Inbox");
+        assert_eq!(snapshot["sections"][0]["id"], "MOCK_SECTION_ID");
+        assert_eq!(snapshot["items"][1]["content"], "Todo\tTwo!");
+        assert_eq!(snapshot["items"][1]["child_order"], 0);
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("export").arg("--project").arg("Nowhere");
+        cmd.assert()
+            .stderr(predicates::str::contains("no project named 'Nowhere'"))
+            .code(2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_todos_with_dry_run() -> Result<()> {
+        let mock_fs = mock_inbox()?.mock_file_contents(
+            "todo.txt",
+            "(A) Plan the offsite +Later due:2021-10-07 id:1\n\
+             Book a venue parent:1 @phone\n\
+             x Call Bob section:Calls\n",
+        )?;
+        let file = mock_fs.path().join("todo.txt");
+        let file = file.to_str().unwrap_or_default();
+
+        assert_eq!(
+            run(
+                &mock_fs,
+                &["import", file, "--project", "Inbox", "--dry-run"]
+            )?,
+            "Add the section 'Calls' to Inbox\n\
+             Add 'Plan the offsite' to Inbox\n  \
+               Add 'Book a venue' to Inbox\n\
+             Add 'Call Bob' to Inbox\n\
+             Complete 'Call Bob'\n\
+             Nothing was changed, since this was a dry run.\n"
+        );
+        let model: Model =
+            serde_json::from_str(&fs::read_to_string(mock_fs.path().join("sync.json"))?)?;
+        assert!(model.commands.is_empty());
+
+        let imported = run(
+            &mock_fs,
+            &["-o", "json", "import", file, "-p", "Inbox", "--no-sync"],
+        )?;
+        let records: serde_json::Value = serde_json::from_str(&imported)?;
+        assert_eq!(records[0]["section"], "Later");
+        assert_eq!(records[0]["priority"], 4);
+        assert_eq!(records[1]["section"], "Later");
+        assert_eq!(records[1]["parent_id"], records[0]["id"]);
+        assert_eq!(records[2]["section"], "Calls");
+        assert_eq!(records[2]["checked"], true);
+
+        let model: Model =
+            serde_json::from_str(&fs::read_to_string(mock_fs.path().join("sync.json"))?)?;
+        assert_eq!(model.sections.len(), 2);
+        assert_eq!(model.commands.len(), 5);
+
+        Ok(())
+    }
 }
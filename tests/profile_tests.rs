@@ -0,0 +1,169 @@
+#![warn(clippy::all, clippy::pedantic, clippy::unwrap_used)]
+pub mod test_utils;
+
+#[cfg(test)]
+pub mod profile_tests {
+    use anyhow::Result;
+    use assert_cmd::Command;
+    use std::path::Path;
+
+    use crate::test_utils::FsMockBuilder;
+
+    // no need to mock the server, but still going to use a fake url to prevent
+    // accidental calls to the real api
+    fn cmd(mock_data_dir: &Path) -> Result<Command> {
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.env_remove("TODOIST_TUI_PROFILE");
+        Ok(cmd)
+    }
+
+    #[test]
+    fn add_and_remove_profiles() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("profile")
+            .arg("add")
+            .arg("work")
+            .assert()
+            .stdout(predicates::str::contains("Added profile 'work'"))
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("profile")
+            .arg("default")
+            .arg("work")
+            .assert()
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("profile")
+            .arg("list")
+            .assert()
+            .stdout("default\nwork (default)\n")
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("profile")
+            .arg("remove")
+            .arg("work")
+            .assert()
+            .code(0);
+
+        // removing the default profile makes the original profile the default again
+        cmd(mock_data_dir)?
+            .arg("profile")
+            .arg("list")
+            .assert()
+            .stdout("default (default)\n")
+            .code(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_data_separate_between_profiles() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("profile")
+            .arg("add")
+            .arg("work")
+            .assert()
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .arg("--profile")
+            .arg("work")
+            .arg("add")
+            .arg("Work todo")
+            .arg("--no-sync")
+            .assert()
+            .code(0);
+
+        // the default profile doesn't see the todo...
+        cmd(mock_data_dir)?
+            .arg("list")
+            .assert()
+            .stdout(predicates::str::contains("Your inbox is empty."))
+            .code(0);
+
+        // ...but the work profile does, whether it's given as an argument or in the environment
+        cmd(mock_data_dir)?
+            .arg("list")
+            .arg("--profile")
+            .arg("work")
+            .assert()
+            .stdout(predicates::str::contains("[1] Work todo"))
+            .code(0);
+
+        cmd(mock_data_dir)?
+            .env("TODOIST_TUI_PROFILE", "work")
+            .arg("list")
+            .assert()
+            .stdout(predicates::str::contains("[1] Work todo"))
+            .code(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_names_outside_the_profiles_directory() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("sync.json", "{}")?
+            .mock_file_contents("profiles/work/sync.json", "{}")?
+            .mock_file_contents("outside/keep.txt", "keep")?;
+        let mock_data_dir = mock_fs.path();
+        let outside = mock_data_dir.join("outside");
+
+        for name in [
+            "..",
+            "",
+            "work/..",
+            "a/b",
+            outside.to_str().unwrap_or_default(),
+        ] {
+            for command in ["remove", "default"] {
+                cmd(mock_data_dir)?
+                    .arg("profile")
+                    .arg(command)
+                    .arg(name)
+                    .assert()
+                    .stderr(predicates::str::contains("isn't a valid profile name"))
+                    .failure();
+            }
+            cmd(mock_data_dir)?
+                .arg("--profile")
+                .arg(name)
+                .arg("list")
+                .assert()
+                .stderr(predicates::str::contains("isn't a valid profile name"))
+                .failure();
+        }
+
+        assert!(mock_data_dir.join("sync.json").exists());
+        assert!(mock_data_dir.join("profiles/work/sync.json").exists());
+        assert!(outside.join("keep.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn fail_on_unknown_profile() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?;
+        let mock_data_dir = mock_fs.path();
+
+        cmd(mock_data_dir)?
+            .arg("--profile")
+            .arg("nope")
+            .arg("list")
+            .assert()
+            .stderr(predicates::str::contains("There's no profile named 'nope'"))
+            .failure();
+
+        Ok(())
+    }
+}
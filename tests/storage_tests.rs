@@ -0,0 +1,366 @@
+#![warn(clippy::all, clippy::pedantic, clippy::unwrap_used)]
+pub mod test_utils;
+
+#[cfg(test)]
+pub mod storage_tests {
+    use anyhow::Result;
+    use predicates::prelude::*;
+    use std::fs;
+    use todoist_tui::model::{
+        due_date::{Due, DueDate},
+        item::Item,
+        project::Project,
+        section::Section,
+        user::User,
+        Model,
+    };
+
+    use crate::test_utils::FsMockBuilder;
+
+    fn mock_model(items: Vec<Item>) -> Model {
+        Model {
+            user: User {
+                full_name: "Drew".to_string(),
+                inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+            },
+            items,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keep_backup_of_previous_data_file() -> Result<()> {
+        let previous_contents = serde_json::to_string_pretty(&mock_model(vec![Item::new(
+            "Todo One!",
+            "MOCK_INBOX_PROJECT_ID",
+        )]))?;
+        let mock_fs =
+            FsMockBuilder::new()?.mock_file_contents("sync.json", previous_contents.clone())?;
+        let mock_data_dir = mock_fs.path();
+
+        // no need to mock the server, but still going to use a fake url to prevent
+        // accidental calls to the real api
+        let server_url = "fake/server/url";
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(server_url);
+        cmd.arg("add").arg("Todo Two!").arg("--no-sync");
+        cmd.assert().code(0);
+
+        // the backup should have the contents from before the command ran
+        let backup_contents = fs::read_to_string(mock_data_dir.join("sync.json.bak"))?;
+        assert_eq!(backup_contents, previous_contents);
+
+        // and no temporary files should be left behind
+        assert!(!mock_data_dir.join("sync.json.tmp").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fall_back_to_backup_when_data_file_is_corrupt() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("sync.json", "{ \"sync_token\": \"MOCK_SYNC")?
+            .mock_file_contents(
+                "sync.json.bak",
+                serde_json::to_string_pretty(&mock_model(vec![Item::new(
+                    "Todo One!",
+                    "MOCK_INBOX_PROJECT_ID",
+                )]))?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+
+        // no need to mock the server, but still going to use a fake url to prevent
+        // accidental calls to the real api
+        let server_url = "fake/server/url";
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(server_url);
+        cmd.arg("list");
+
+        cmd.assert()
+            .stdout(predicates::str::contains("[1] Todo One!"))
+            .stderr(predicates::str::contains(
+                "Could not parse model file 'sync.json'",
+            ))
+            .stderr(predicates::str::contains("sync.json.bak"))
+            .code(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_backup_when_data_file_is_corrupt() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("sync.json", "{ \"sync_token\": \"MOCK_SYNC")?
+            .mock_file_contents(
+                "sync.json.bak",
+                serde_json::to_string_pretty(&mock_model(vec![Item::new(
+                    "Todo One!",
+                    "MOCK_INBOX_PROJECT_ID",
+                )]))?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg("fake/server/url");
+        cmd.arg("add").arg("Todo Two!").arg("--no-sync");
+        cmd.assert().code(0);
+
+        // the corrupt file shouldn't have replaced the only good copy
+        let backup: Model =
+            serde_json::from_str(&fs::read_to_string(mock_data_dir.join("sync.json.bak"))?)?;
+        assert_eq!(backup.items.len(), 1);
+        let model: Model =
+            serde_json::from_str(&fs::read_to_string(mock_data_dir.join("sync.json"))?)?;
+        assert_eq!(model.items.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ignore_stale_lock() -> Result<()> {
+        // a lock left behind by a process that no longer exists
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&mock_model(vec![]))?,
+            )?
+            .mock_file_contents("data.lock", u32::MAX)?;
+        let mock_data_dir = mock_fs.path();
+
+        // no need to mock the server, but still going to use a fake url to prevent
+        // accidental calls to the real api
+        let server_url = "fake/server/url";
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(server_url);
+        cmd.arg("add").arg("Todo One!").arg("--no-sync");
+        cmd.assert()
+            .stdout(predicates::str::contains("'Todo One!' added"))
+            .code(0);
+
+        // the lock should be released once the command is done
+        assert!(!mock_data_dir.join("data.lock").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn refuse_to_read_newer_schema_version() -> Result<()> {
+        let mut contents = serde_json::to_value(mock_model(vec![]))?;
+        contents["schema_version"] = serde_json::Value::from(u64::MAX);
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("sync.json", contents.to_string())?
+            .mock_file_contents(
+                "sync.json.bak",
+                serde_json::to_string_pretty(&mock_model(vec![]))?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+
+        // no need to mock the server, but still going to use a fake url to prevent
+        // accidental calls to the real api
+        let server_url = "fake/server/url";
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(server_url);
+        cmd.arg("list");
+
+        // the backup shouldn't be used, as it would throw away the newer data on the next write
+        cmd.assert()
+            .stderr(predicates::str::contains("newer version of todoist-tui"))
+            .failure();
+
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_to_sqlite_storage() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("config.toml", "storage = \"sqlite\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&mock_model(vec![Item::new(
+                    "Todo One!",
+                    "MOCK_INBOX_PROJECT_ID",
+                )]))?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+
+        // no need to mock the server, but still going to use a fake url to prevent
+        // accidental calls to the real api
+        let server_url = "fake/server/url";
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(server_url);
+        cmd.arg("add").arg("Todo Two!").arg("--no-sync");
+        cmd.assert().code(0);
+
+        assert!(mock_data_dir.join("sync.db").exists());
+
+        // the json file shouldn't be touched once the model is in the database
+        let mock_fs = mock_fs.mock_file_contents("sync.json", "not even json")?;
+        let mock_data_dir = mock_fs.path();
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(server_url);
+        cmd.arg("list");
+        cmd.assert()
+            .stdout(predicates::str::contains("[1] Todo One!"))
+            .stdout(predicates::str::contains("[2] Todo Two!"))
+            .code(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_rows_from_sqlite_storage() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("config.toml", "storage = \"sqlite\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&mock_model(vec![Item::new(
+                    "Todo One!",
+                    "MOCK_INBOX_PROJECT_ID",
+                )]))?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+        let server_url = "fake/server/url";
+
+        for args in [
+            vec!["add", "Todo Two!", "--no-sync"],
+            vec!["add", "Todo Three!", "--no-sync"],
+            vec!["pending", "drop", "1"],
+        ] {
+            let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+            cmd.arg("--local-dir-override").arg(mock_data_dir);
+            cmd.arg("--sync-url-override").arg(server_url);
+            cmd.args(args);
+            cmd.assert().code(0);
+        }
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(server_url);
+        cmd.arg("list");
+        cmd.assert()
+            .stdout(predicates::str::contains("[1] Todo One!"))
+            .stdout(predicates::str::contains("[2] Todo Three!"))
+            .stdout(predicates::str::contains("Todo Two!").not())
+            .code(0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn list_from_older_sqlite_database() -> Result<()> {
+        let mock_fs =
+            FsMockBuilder::new()?.mock_file_contents("config.toml", "storage = \"sqlite\"")?;
+        let mock_data_dir = mock_fs.path();
+
+        let inbox = Project {
+            id: "MOCK_INBOX_PROJECT_ID".into(),
+            inbox_project: true,
+            ..Project::new("Inbox")
+        };
+        let work = Project::new("Work");
+        let later = Section::new("Later", &work.id);
+        let today = Due {
+            date: DueDate::Date(chrono::NaiveDate::from_ymd_opt(2021, 10, 6).expect("valid date")),
+        };
+        let with_id = |id: &str, item: Item| Item {
+            id: id.into(),
+            ..item
+        };
+        let plan = with_id("1001aaaa", Item::new("Plan", &work.id));
+        let items = [
+            with_id("1001aaab", Item::new("Inbox todo", &inbox.id)),
+            with_id(
+                "1002bbbb",
+                Item::new("Draft", &work.id)
+                    .parent_id(&plan.id)
+                    .due(Some(today)),
+            ),
+            with_id("2001", Item::new("Review", &work.id).section_id(&later.id)),
+            plan,
+        ];
+
+        // a database from before the todos could be looked up by project, section, or due date
+        let connection = rusqlite::Connection::open(mock_data_dir.join("sync.db"))?;
+        connection.execute_batch(
+            "CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE projects (id TEXT PRIMARY KEY, position INTEGER NOT NULL, data TEXT NOT NULL);
+             CREATE TABLE sections (id TEXT PRIMARY KEY, position INTEGER NOT NULL, data TEXT NOT NULL);
+             CREATE TABLE items (id TEXT PRIMARY KEY, position INTEGER NOT NULL, data TEXT NOT NULL);
+             CREATE TABLE commands (uuid TEXT PRIMARY KEY, position INTEGER NOT NULL, data TEXT NOT NULL);",
+        )?;
+        let model = mock_model(vec![]);
+        for (key, value) in [
+            ("schema_version", "5".to_string()),
+            ("sync_token", serde_json::to_string(&model.sync_token)?),
+            ("user", serde_json::to_string(&model.user)?),
+        ] {
+            connection.execute("INSERT INTO meta VALUES (?1, ?2)", (key, value))?;
+        }
+        for (position, project) in [&inbox, &work].into_iter().enumerate() {
+            connection.execute(
+                "INSERT INTO projects VALUES (?1, ?2, ?3)",
+                (
+                    project.id.to_string(),
+                    position,
+                    serde_json::to_string(project)?,
+                ),
+            )?;
+        }
+        connection.execute(
+            "INSERT INTO sections VALUES (?1, 0, ?2)",
+            (later.id.to_string(), serde_json::to_string(&later)?),
+        )?;
+        for (position, item) in items.iter().enumerate() {
+            connection.execute(
+                "INSERT INTO items VALUES (?1, ?2, ?3)",
+                (item.id.to_string(), position, serde_json::to_string(item)?),
+            )?;
+        }
+        drop(connection);
+
+        let list = |args: &[&str]| -> Result<String> {
+            let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+            cmd.arg("--local-dir-override").arg(mock_data_dir);
+            cmd.arg("--sync-url-override").arg("fake/server/url");
+            cmd.arg("--date-time-override").arg("2021-10-06T08:00:00");
+            cmd.arg("list").args(args);
+            let output = cmd.assert().code(0).get_output().stdout.clone();
+            Ok(String::from_utf8(output)?)
+        };
+
+        // the short ids are still unique among all of the todos, not just the listed ones
+        assert_eq!(
+            list(&["-p", "work"])?,
+            "Work\n\
+             - Plan  [1001aaaa]\n  \
+               - Draft  (2021-10-06)  [1002]\n\
+             \n\
+             Work / Later\n\
+             - Review  [2001]\n"
+        );
+        assert_eq!(
+            list(&["-p", "work", "--due", "today"])?,
+            "Work\n- Draft  (2021-10-06)  [1002]\n"
+        );
+        assert_eq!(
+            list(&["-p", "work", "--section", "later"])?,
+            "Work / Later\n- Review  [2001]\n"
+        );
+        assert_eq!(list(&[])?, "Inbox: \n[1] Inbox todo\n");
+
+        Ok(())
+    }
+}
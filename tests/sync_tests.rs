@@ -8,9 +8,10 @@ pub mod sync_tests {
     use std::{collections::HashMap, fs};
     use todoist_tui::{
         model::{
-            command::{self, AddItemArgs, Args},
+            command::{self, AddItemArgs, AddSectionArgs, Args},
             item::Item,
             project::Project,
+            section::Section,
             user::User,
             Model,
         },
@@ -40,9 +41,9 @@ pub mod sync_tests {
                 |request: Request| request.sync_token == "*",
                 Response {
                     full_sync: true,
-                    items: vec![mock_item_1, mock_item_2],
-                    projects: vec![mock_project],
-                    sections: vec![],
+                    items: Some(vec![mock_item_1, mock_item_2]),
+                    projects: Some(vec![mock_project]),
+                    sections: Some(vec![]),
                     sync_status: None,
                     sync_token: String::from("MOCK_SYNC_TOKEN"),
                     temp_id_mapping: HashMap::new(),
@@ -72,6 +73,75 @@ pub mod sync_tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn full_sync_as_json() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?;
+        let mock_data_dir = mock_fs.path();
+
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response(
+                "sync",
+                |request: Request| request.sync_token == "*",
+                Response {
+                    full_sync: true,
+                    items: Some(vec![Item::new("Todo One!", "MOCK_INBOX_PROJECT_ID")]),
+                    projects: Some(vec![Project::new("Project!")]),
+                    sections: Some(vec![]),
+                    sync_status: None,
+                    sync_token: String::from("MOCK_SYNC_TOKEN"),
+                    temp_id_mapping: HashMap::new(),
+                    user: Some(User {
+                        full_name: "Drew".to_string(),
+                        inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                    }),
+                },
+            )
+            .await;
+
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.arg("sync").arg("--output").arg("json");
+
+        // there shouldn't be any progress messages mixed in with the json
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        let record: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(
+            record,
+            serde_json::json!({
+                "full_sync": true,
+                "commands_sent": 0,
+                "commands_pending": 0,
+                "projects": 1,
+                "items": 1,
+            })
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn exit_with_code_when_sync_fails() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?;
+
+        // nothing is listening here
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg("http://127.0.0.1:9");
+        cmd.arg("sync");
+
+        cmd.assert()
+            .stderr(predicates::str::contains(
+                "Could not sync with the Todoist server",
+            ))
+            .code(3);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn full_sync_send_new_todo() -> Result<()> {
         // mock data
@@ -100,7 +170,7 @@ pub mod sync_tests {
                         args: Args::AddItemCommandArgs(AddItemArgs {
                             project_id: "MOCK_INBOX_PROJECT_ID".into(),
                             content: "Todo Two!".to_string(),
-                            due: None,
+                            ..Default::default()
                         }),
                     }],
                     ..Default::default()
@@ -116,13 +186,13 @@ pub mod sync_tests {
                 |request: Request| request.sync_token == "*",
                 Response {
                     full_sync: true,
-                    items: vec![
+                    items: Some(vec![
                         mock_item_1.clone(),
                         mock_item_2_updated.clone(),
                         mock_item_3.clone(),
-                    ],
-                    projects: vec![],
-                    sections: vec![],
+                    ]),
+                    projects: None,
+                    sections: None,
                     sync_status: Some(HashMap::from([(command_uuid, Status::Ok)])),
                     sync_token: String::from("NEW_MOCK_SYNC_TOKEN"),
                     temp_id_mapping: HashMap::from([(
@@ -192,7 +262,7 @@ pub mod sync_tests {
                         args: Args::AddItemCommandArgs(AddItemArgs {
                             project_id: "MOCK_INBOX_PROJECT_ID".into(),
                             content: "Todo Two!".to_string(),
-                            due: None,
+                            ..Default::default()
                         }),
                     }],
                     ..Default::default()
@@ -208,9 +278,9 @@ pub mod sync_tests {
                 |request: Request| request.sync_token == "MOCK_SYNC_TOKEN",
                 Response {
                     full_sync: false,
-                    items: vec![],
-                    projects: vec![],
-                    sections: vec![],
+                    items: Some(vec![]),
+                    projects: Some(vec![]),
+                    sections: Some(vec![]),
                     sync_status: Some(HashMap::from([(command_uuid, Status::Ok)])),
                     sync_token: String::from("NEW_MOCK_SYNC_TOKEN"),
                     temp_id_mapping: HashMap::from([(
@@ -248,4 +318,412 @@ pub mod sync_tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn remove_everything_in_archived_project() -> Result<()> {
+        let work = Project::new("Work");
+        let side = Project::new("Side").parent_id(&work.id);
+        let later = Section::new("Later", &work.id);
+        let someday = Section::new("Someday", &side.id);
+        let inbox_item = Item::new("Inbox todo", "MOCK_INBOX_PROJECT_ID");
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&Model {
+                    sync_token: String::from("MOCK_SYNC_TOKEN"),
+                    user: User {
+                        full_name: "Drew".to_string(),
+                        inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                    },
+                    items: vec![
+                        inbox_item.clone(),
+                        Item::new("Work todo", &work.id).section_id(&later.id),
+                        Item::new("Side todo", &side.id).section_id(&someday.id),
+                    ],
+                    projects: vec![work.clone(), side],
+                    sections: vec![later, someday],
+                    ..Default::default()
+                })?,
+            )?;
+        let mock_data_dir = mock_fs.path();
+
+        // the server only says that the parent project was archived
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response(
+                "sync",
+                |request: Request| request.sync_token == "MOCK_SYNC_TOKEN",
+                Response {
+                    full_sync: false,
+                    items: Some(vec![]),
+                    projects: Some(vec![Project {
+                        is_archived: true,
+                        ..work
+                    }]),
+                    sections: Some(vec![]),
+                    sync_status: None,
+                    sync_token: String::from("NEW_MOCK_SYNC_TOKEN"),
+                    temp_id_mapping: HashMap::new(),
+                    user: None,
+                },
+            )
+            .await;
+
+        let mut cmd = assert_cmd::Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_data_dir);
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.arg("sync").arg("--incremental");
+        cmd.assert().code(0);
+
+        let file_contents = fs::read_to_string(mock_data_dir.join("sync.json"))?;
+        let sync_data: Model = serde_json::from_str(&file_contents)?;
+
+        // its subproject, the sections in both, and their todos are gone too
+        assert!(sync_data.projects.is_empty());
+        assert!(sync_data.sections.is_empty());
+        assert_eq!(sync_data.items.len(), 1);
+        assert_eq!(sync_data.items[0].id, inbox_item.id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_todos_from_stdin_in_one_sync() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&Model {
+                    sync_token: String::from("MOCK_SYNC_TOKEN"),
+                    user: User {
+                        full_name: "Drew".to_string(),
+                        inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                    },
+                    ..Default::default()
+                })?,
+            )?;
+
+        // the server only answers if every todo comes in the same request, with the
+        // subtask pointing at its parent's temporary id
+        let is_checklist = |request: Request| {
+            let args: Vec<AddItemArgs> = request
+                .commands
+                .iter()
+                .filter_map(|command| match &command.args {
+                    Args::AddItemCommandArgs(args) => Some(args.clone()),
+                    _ => None,
+                })
+                .collect();
+            let [flights, prices, pack] = args.as_slice() else {
+                return false;
+            };
+            flights.content == "Book flights"
+                && flights.priority == 4
+                && prices.content == "Compare prices"
+                && prices.parent_id.as_ref().map(ToString::to_string) == request.commands[0].temp_id
+                && pack.content == "Pack"
+                && pack.parent_id.is_none()
+                && pack.labels == ["travel"]
+        };
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response(
+                "sync",
+                is_checklist,
+                Response {
+                    full_sync: false,
+                    items: Some(vec![]),
+                    projects: Some(vec![]),
+                    sections: Some(vec![]),
+                    sync_status: None,
+                    sync_token: String::from("NEW_MOCK_SYNC_TOKEN"),
+                    temp_id_mapping: HashMap::new(),
+                    user: None,
+                },
+            )
+            .await;
+
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.arg("--output").arg("json");
+        cmd.arg("add").arg("--stdin");
+        cmd.write_stdin("- [ ] Book flights p1\n    - Compare prices\n\n- Pack @travel\n");
+
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        let records: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(records[1]["parent_id"], records[0]["id"]);
+        assert_eq!(records[2]["content"], "Pack");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn complete_todo_as_json() -> Result<()> {
+        let item = Item::new("Todo One!", "MOCK_INBOX_PROJECT_ID");
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&Model {
+                    sync_token: String::from("MOCK_SYNC_TOKEN"),
+                    user: User {
+                        full_name: "Drew".to_string(),
+                        inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                    },
+                    projects: vec![Project {
+                        id: "MOCK_INBOX_PROJECT_ID".into(),
+                        ..Project::new("Inbox")
+                    }],
+                    items: vec![item.clone()],
+                    ..Default::default()
+                })?,
+            )?;
+
+        // the server sends the todo back checked off, so it's gone from the model after syncing
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response(
+                "sync",
+                |request: Request| request.commands.len() == 1,
+                Response {
+                    full_sync: false,
+                    items: Some(vec![item.clone().checked(true)]),
+                    projects: Some(vec![]),
+                    sections: Some(vec![]),
+                    sync_status: None,
+                    sync_token: String::from("NEW_MOCK_SYNC_TOKEN"),
+                    temp_id_mapping: HashMap::new(),
+                    user: None,
+                },
+            )
+            .await;
+
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.arg("--output").arg("json");
+        cmd.arg("complete").arg("Todo One");
+
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        let record: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(record["id"], item.id.to_string());
+        assert_eq!(record["content"], "Todo One!");
+        assert_eq!(record["checked"], true);
+
+        let model: Model =
+            serde_json::from_str(&fs::read_to_string(mock_fs.path().join("sync.json"))?)?;
+        assert!(model.items.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn queue_changes_when_sync_is_deferred() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?
+            .mock_file_contents("config.toml", "sync_mode = \"deferred\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&Model {
+                    user: User {
+                        full_name: "Drew".to_string(),
+                        inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                    },
+                    projects: vec![Project {
+                        id: "MOCK_INBOX_PROJECT_ID".into(),
+                        ..Project::new("Inbox")
+                    }],
+                    ..Default::default()
+                })?,
+            )?;
+        let cmd = || -> Result<Command> {
+            let mut cmd = Command::cargo_bin("todoist-tui")?;
+            cmd.arg("--local-dir-override").arg(mock_fs.path());
+            // nothing is listening here
+            cmd.arg("--sync-url-override").arg("http://127.0.0.1:9");
+            Ok(cmd)
+        };
+
+        cmd()?
+            .args(["add", "Do a barrel roll!"])
+            .assert()
+            .stderr(predicates::str::contains(
+                "Could not sync with the Todoist server. 1 change is waiting",
+            ))
+            .code(0);
+        cmd()?
+            .args(["complete", "barrel"])
+            .assert()
+            .stderr(predicates::str::contains("2 changes are waiting"))
+            .code(0);
+
+        cmd()?
+            .arg("pending")
+            .assert()
+            .stdout("[1] Add 'Do a barrel roll!' to Inbox\n[2] Complete 'Do a barrel roll!'\n")
+            .code(0);
+
+        // dropping the new todo drops the change that completed it too
+        cmd()?
+            .args(["pending", "drop", "1"])
+            .assert()
+            .stdout("Dropped: Add 'Do a barrel roll!' to Inbox.\n")
+            .code(0);
+        cmd()?
+            .args(["pending", "drop", "1"])
+            .assert()
+            .stderr(predicates::str::contains("no pending change number 1"))
+            .code(2);
+
+        let model: Model =
+            serde_json::from_str(&fs::read_to_string(mock_fs.path().join("sync.json"))?)?;
+        assert!(model.items.is_empty());
+        assert!(model.commands.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_sections_before_their_todos() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&Model {
+                    sync_token: String::from("MOCK_SYNC_TOKEN"),
+                    user: User {
+                        full_name: "Drew".to_string(),
+                        inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                    },
+                    projects: vec![Project {
+                        id: "MOCK_WORK_PROJECT_ID".into(),
+                        ..Project::new("Work")
+                    }],
+                    ..Default::default()
+                })?,
+            )?
+            .mock_file_contents(
+                "offsite.md",
+                "# Work\n\n## Offsite\n\n- [ ] Plan it p1\n  - [ ] Book a venue\n",
+            )?;
+
+        // the section has to be added first, so that the todos can point at its temporary id
+        let is_import = |request: Request| {
+            let [section, plan, book] = request.commands.as_slice() else {
+                return false;
+            };
+            let section_id = section.temp_id.clone().map(Into::into);
+            match (&section.args, &plan.args, &book.args) {
+                (
+                    Args::AddSectionCommandArgs(AddSectionArgs { name, project_id }),
+                    Args::AddItemCommandArgs(plan_args),
+                    Args::AddItemCommandArgs(book_args),
+                ) => {
+                    name == "Offsite"
+                        && *project_id == "MOCK_WORK_PROJECT_ID".into()
+                        && plan_args.section_id == section_id
+                        && plan_args.priority == 4
+                        && book_args.section_id == section_id
+                        && book_args.parent_id.as_ref().map(ToString::to_string) == plan.temp_id
+                }
+                _ => false,
+            }
+        };
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response(
+                "sync",
+                is_import,
+                Response {
+                    full_sync: false,
+                    items: Some(vec![]),
+                    projects: Some(vec![]),
+                    sections: Some(vec![Section {
+                        id: "REAL_SECTION_ID".into(),
+                        ..Section::new("Offsite", "MOCK_WORK_PROJECT_ID")
+                    }]),
+                    sync_status: None,
+                    sync_token: String::from("NEW_MOCK_SYNC_TOKEN"),
+                    temp_id_mapping: HashMap::new(),
+                    user: None,
+                },
+            )
+            .await;
+
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.arg("import")
+            .arg(mock_fs.path().join("offsite.md"))
+            .arg("--project")
+            .arg("work");
+        cmd.assert()
+            .stdout(predicates::str::contains("2 todos added to Work."))
+            .code(0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn import_completed_todos_as_json() -> Result<()> {
+        let mock_fs = FsMockBuilder::new()?
+            .mock_file_contents("client_auth.toml", "api_token = \"MOCK_API_TOKEN\"")?
+            .mock_file_contents(
+                "sync.json",
+                serde_json::to_string_pretty(&Model {
+                    sync_token: String::from("MOCK_SYNC_TOKEN"),
+                    user: User {
+                        full_name: "Drew".to_string(),
+                        inbox_project_id: "MOCK_INBOX_PROJECT_ID".into(),
+                    },
+                    projects: vec![Project {
+                        id: "MOCK_INBOX_PROJECT_ID".into(),
+                        ..Project::new("Inbox")
+                    }],
+                    ..Default::default()
+                })?,
+            )?
+            .mock_file_contents("venue.md", "- [ ] Plan it\n- [x] Book a venue\n")?;
+
+        // a full sync leaves out the todos that are checked off (and since the mock can't
+        // know the new todos' ids, it leaves out the other one too), so the imported todos
+        // are gone from the model after syncing
+        let mock_server = ApiMockBuilder::new()
+            .await
+            .mock_response(
+                "sync",
+                |request: Request| request.commands.len() == 3,
+                Response {
+                    full_sync: true,
+                    items: Some(vec![]),
+                    projects: None,
+                    sections: None,
+                    sync_status: None,
+                    sync_token: String::from("NEW_MOCK_SYNC_TOKEN"),
+                    temp_id_mapping: HashMap::new(),
+                    user: None,
+                },
+            )
+            .await;
+
+        let mut cmd = Command::cargo_bin("todoist-tui")?;
+        cmd.arg("--local-dir-override").arg(mock_fs.path());
+        cmd.arg("--sync-url-override").arg(mock_server.uri());
+        cmd.arg("--output").arg("json");
+        cmd.arg("import")
+            .arg(mock_fs.path().join("venue.md"))
+            .arg("--project")
+            .arg("inbox");
+
+        let output = cmd.assert().code(0).get_output().stdout.clone();
+        let records: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(records[0]["content"], "Plan it");
+        assert_eq!(records[0]["checked"], false);
+        assert_eq!(records[1]["content"], "Book a venue");
+        assert_eq!(records[1]["checked"], true);
+
+        Ok(())
+    }
 }
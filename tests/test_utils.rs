@@ -101,6 +101,7 @@ mod tui_tester {
     use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
     use ratatui::{backend::TestBackend, buffer::Cell, Terminal};
     use std::fmt::Write;
+    use todoist_tui::sync::Response;
     use todoist_tui::tui::app::App;
     use todoist_tui::tui::app_state::Mode;
 
@@ -158,6 +159,28 @@ mod tui_tester {
             Ok(self)
         }
 
+        /// Renders the buffer and asserts that the given strings are visible, each one
+        /// after (or to the right of) the one before it.
+        ///
+        /// # Panics
+        /// If one of the `needles` cannot be found after the one before it.
+        ///
+        /// # Errors
+        /// If something goes wrong while drawing to the screen.
+        pub fn expect_in_order(&mut self, needles: &[&str]) -> Result<&mut Self> {
+            let screen = self.render_to_string()?;
+            let mut rest = screen.as_str();
+            for needle in needles {
+                let Some(index) = rest.find(needle) else {
+                    panic!(
+                        "The string '{needle}' was not found in order on this screen:\n{screen}"
+                    );
+                };
+                rest = &rest[index + needle.len()..];
+            }
+            Ok(self)
+        }
+
         fn render_to_string(&mut self) -> Result<String> {
             self.terminal.draw(|frame| {
                 self.app.render(frame);
@@ -199,10 +222,28 @@ mod tui_tester {
             self
         }
 
+        /// Updates the app's model with a response from the server, as if a sync just finished.
+        pub fn receive_sync(&mut self, response: Response) -> &mut Self {
+            self.app.receive_sync(response);
+            self
+        }
+
+        /// Tells the app that a sync failed with the given error.
+        pub fn fail_sync(&mut self, error: &str) -> &mut Self {
+            self.app.sync_failed(&anyhow::anyhow!(error.to_string()));
+            self
+        }
+
         /// Sends a enter key press to the app.
         pub fn type_key(&mut self, key: KeyCode) -> &mut Self {
             self.app.handle_key(KeyEvent::new(key, KeyModifiers::NONE));
             self
         }
+
+        /// Sends a key press to the app while holding down modifiers (eg. ctrl).
+        pub fn type_key_with(&mut self, key: KeyCode, modifiers: KeyModifiers) -> &mut Self {
+            self.app.handle_key(KeyEvent::new(key, modifiers));
+            self
+        }
     }
 }
@@ -8,7 +8,8 @@ pub mod tui_tests {
     use crate::test_utils::TuiTester;
     use anyhow::Result;
     use chrono::{NaiveDate, NaiveDateTime};
-    use crossterm::event::KeyCode;
+    use crossterm::event::{KeyCode, KeyModifiers};
+    use std::collections::HashMap;
     use todoist_tui::{
         model::{
             due_date::{Due, DueDate},
@@ -17,6 +18,8 @@ pub mod tui_tests {
             section::Section,
             Model,
         },
+        storage::config_manager::Config,
+        sync::Response,
         tui::app::App,
     };
 
@@ -25,7 +28,7 @@ pub mod tui_tests {
         let mut model = Model::default();
         let app = App::new(&mut model);
 
-        TuiTester::new(app, 20, 20)?
+        TuiTester::new(app, 24, 20)?
             .expect_visible("Inbox")?
             // regression: press down arrow twice in an empty inbox caused a crash
             .type_key(KeyCode::Down)
@@ -66,6 +69,23 @@ pub mod tui_tests {
         Ok(())
     }
 
+    #[test]
+    fn keep_new_todo_open_when_project_is_unknown() -> Result<()> {
+        let mut model = Model::default();
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 80, 10)?
+            .type_string("a")
+            .type_string("Call Bob #Wrok")
+            .type_key(KeyCode::Enter)
+            .expect_visible("There's no project named 'Wrok'.")?
+            .expect_visible("New Todo")?
+            .expect_visible("Call Bob #Wrok")?;
+
+        assert!(model.items.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn add_new_todo_with_smart_date() -> Result<()> {
         let mut model = Model::default();
@@ -168,6 +188,7 @@ pub mod tui_tests {
             .expect_visible("↑↓: select")?
             .expect_visible("a: new todo")?
             .expect_visible("space: mark complete")?
+            .expect_visible("r: sync")?
             // key hints in add item mode
             // tab to move focus to the projects panel
             .type_string("a")
@@ -178,6 +199,108 @@ pub mod tui_tests {
         Ok(())
     }
 
+    #[test]
+    fn use_configured_keybindings() -> Result<()> {
+        let mut model = Model::default();
+        let app = App::new(&mut model).config(Config {
+            keybindings: [
+                ("quit".to_string(), "x".to_string()),
+                ("add_item".to_string(), "n ctrl-n".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        });
+
+        TuiTester::new(app, 100, 10)?
+            // key hints show the first key bound to each action
+            .expect_visible("x: quit")?
+            .expect_visible("n: new todo")?
+            .expect_visible("↑↓: select")?
+            // the default keys don't do anything anymore
+            .type_string("qa")
+            .expect_not_visible("New Todo")?
+            .type_string("n")
+            .expect_visible("New Todo")?
+            .type_key(KeyCode::Esc)
+            .type_string("x")
+            .expect_exiting();
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_and_toggle_todos_with_vim_keys() -> Result<()> {
+        let mut model = Model::default();
+        model.add_item_to_inbox("Item 1", None);
+        model.add_item_to_inbox("Item 2", None);
+        model.add_item_to_inbox("Item 3", None);
+        let app = App::new(&mut model);
+
+        // check the screen after each move
+        TuiTester::new(app, 40, 10)?
+            .expect_visible("Item 1")?
+            .type_string("G")
+            .expect_visible("Item 3")?
+            .type_string("k")
+            .expect_visible("Item 2")?
+            .type_string(" ")
+            .expect_visible("✓ Item 2")?
+            // toggling again marks it incomplete
+            .type_string(" ")
+            .expect_visible("- Item 2")?
+            .type_string("gg")
+            .expect_visible("Item 1")?
+            .type_string("j")
+            .expect_visible("Item 2")?
+            .type_string("j")
+            .expect_visible("Item 3")?
+            .type_string(" ")
+            .expect_visible("✓ Item 3")?;
+
+        assert!(!model.items[1].checked);
+        assert!(model.items[2].checked);
+        // the command to complete item 2 was dropped when it was marked incomplete again
+        let completions = model
+            .commands
+            .iter()
+            .filter(|command| command.request_type == "item_complete")
+            .count();
+        assert_eq!(completions, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn uncomplete_synced_todo() -> Result<()> {
+        let mut model = Model::default();
+        let inbox_id = model.inbox_project().id.clone();
+        model
+            .items
+            .push(Item::new("Done already", &inbox_id).checked(true));
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 40, 10)?
+            .expect_visible("✓ Done already")?
+            .type_string(" ")
+            .expect_visible("- Done already")?;
+
+        assert!(!model.items[0].checked);
+        assert_eq!(model.commands.len(), 1);
+        assert_eq!(model.commands[0].request_type, "item_uncomplete");
+
+        Ok(())
+    }
+
+    #[test]
+    fn show_profile() -> Result<()> {
+        let mut model = Model::default();
+        let app = App::new(&mut model).profile("work");
+
+        TuiTester::new(app, 60, 10)?.expect_visible("profile: work")?;
+
+        Ok(())
+    }
+
     #[test]
     fn show_due_dates_and_times() -> Result<()> {
         let mut model = Model::default();
@@ -211,6 +334,30 @@ pub mod tui_tests {
         Ok(())
     }
 
+    #[test]
+    fn show_due_dates_in_configured_format() -> Result<()> {
+        let mut model = Model::default();
+        model.add_item_to_inbox(
+            "Todo 1",
+            Some(Due {
+                date: DueDate::DateTime(
+                    NaiveDateTime::parse_from_str("2011-10-14 15:48", "%Y-%m-%d %H:%M")
+                        .expect("parse datetime"),
+                ),
+            }),
+        );
+
+        let app = App::new(&mut model).config(Config {
+            date_format: "%d/%m/%Y".to_string(),
+            time_format: "%-I:%M%P".to_string(),
+            ..Default::default()
+        });
+
+        TuiTester::new(app, 70, 10)?.expect_visible("14/10/2011 3:48pm")?;
+
+        Ok(())
+    }
+
     #[test]
     fn show_project_hierarchy() -> Result<()> {
         let mut model = Model::default();
@@ -225,7 +372,7 @@ pub mod tui_tests {
 
         TuiTester::new(app, 100, 10)?
             .expect_visible("Parent")?
-            .expect_visible("  Child")?;
+            .expect_visible("  ● Child")?;
 
         Ok(())
     }
@@ -250,6 +397,233 @@ pub mod tui_tests {
         Ok(())
     }
 
+    #[test]
+    fn show_favorites_and_order_projects() -> Result<()> {
+        let mut model = Model::default();
+
+        let parent_project = Project::new("Parent").child_order(1);
+        let second_child = Project::new("Second child")
+            .parent_id(&parent_project.id)
+            .child_order(2);
+        let first_child = Project::new("First child")
+            .parent_id(&parent_project.id)
+            .child_order(1)
+            .color("berry_red")
+            .favorite();
+        let item = Item::new("Favorite item", &first_child.id);
+
+        model.projects.push(parent_project);
+        model.projects.push(second_child);
+        model.projects.push(first_child);
+        model.items.push(item);
+
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 80, 12)?
+            .expect_visible("▼ Favorites")?
+            .expect_visible("    ● First child")?
+            .expect_in_order(&[
+                "Favorites",
+                "First child",
+                "Parent",
+                "First child",
+                "Second child",
+            ])?
+            // the first project under the favorites is the favorite
+            .type_key(KeyCode::Tab)
+            .type_string("gg")
+            .expect_visible("▼ Favorites")?
+            .type_string("j")
+            .expect_visible("Favorite item")?
+            // the favorites group stays collapsed, and it isn't a project to update
+            .type_string("gg")
+            .type_key(KeyCode::Char(' '))
+            .expect_visible("▶ Favorites")?
+            .type_string("j")
+            .expect_visible("▶ Favorites")?;
+
+        assert!(model.commands.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn open_expanded_subprojects_at_startup() -> Result<()> {
+        let mut model = Model::default();
+
+        let grandparent = Project::new("Grandparent");
+        let parent = Project::new("Parent").parent_id(&grandparent.id);
+        let child = Project::new("Child").parent_id(&parent.id);
+        let collapsed = Project {
+            collapsed: true,
+            ..Project::new("Collapsed").parent_id(&grandparent.id)
+        };
+        let hidden = Project::new("Hidden").parent_id(&collapsed.id);
+
+        model.projects.push(grandparent);
+        model.projects.push(parent);
+        model.projects.push(child);
+        model.projects.push(collapsed);
+        model.projects.push(hidden);
+
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 80, 12)?
+            .expect_visible("    ● Child")?
+            .expect_visible("▶ ● Collapsed")?
+            .expect_not_visible("Hidden")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn collapse_project_and_item() -> Result<()> {
+        let mut model = Model::default();
+        let inbox_id = model.user.inbox_project_id.clone();
+
+        let parent_project = Project::new("Parent");
+        let child_project = Project::new("Child").parent_id(&parent_project.id);
+        let parent_item = Item::new("Parent item", &inbox_id);
+        let child_item = Item::new("Child item", &inbox_id).parent_id(&parent_item.id);
+
+        model.projects.push(parent_project);
+        model.projects.push(child_project);
+        model.items.push(parent_item);
+        model.items.push(child_item);
+
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 100, 10)?
+            .expect_visible("Child item")?
+            // collapse the parent item
+            .type_key(KeyCode::Left)
+            .expect_not_visible("Child item")?
+            // tab to move focus to the projects panel and collapse the parent project
+            .type_key(KeyCode::Tab)
+            .type_key(KeyCode::Down)
+            .expect_visible("  ● Child")?
+            .type_key(KeyCode::Char(' '))
+            .expect_not_visible("  ● Child")?;
+
+        assert!(model.items[0].collapsed);
+        assert!(model.projects[1].collapsed);
+        let command_types: Vec<_> = model
+            .commands
+            .iter()
+            .map(|command| command.request_type.as_str())
+            .collect();
+        assert_eq!(command_types, vec!["item_update", "project_update"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_collapse_from_sync() -> Result<()> {
+        let mut model = Model::default();
+        let inbox_id = model.user.inbox_project_id.clone();
+        let parent_item = Item::new("Parent item", &inbox_id);
+        let child_item = Item::new("Child item", &inbox_id).parent_id(&parent_item.id);
+        let collapsed_parent = Item {
+            collapsed: true,
+            ..parent_item.clone()
+        };
+        model.items.push(parent_item);
+        model.items.push(child_item);
+        model.items.push(Item::new("Other item", &inbox_id));
+
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 100, 10)?
+            .expect_visible("Child item")?
+            // the parent is collapsed in the web app...
+            .receive_sync(Response {
+                items: Some(vec![collapsed_parent]),
+                ..Default::default()
+            })
+            .expect_not_visible("Child item")?
+            // ...which moving around doesn't undo
+            .type_key(KeyCode::Down)
+            .expect_not_visible("Child item")?;
+
+        assert!(model.items.iter().any(|item| item.collapsed));
+        assert!(model.commands.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn move_by_counts_and_half_pages() -> Result<()> {
+        let mut model = Model::default();
+        for n in 1..=20 {
+            model.add_item_to_inbox(&format!("Todo {n}"), None);
+        }
+        let app = App::new(&mut model);
+
+        // 15 lines tall, so 12 lines of items between the border and the key hints,
+        // and 6 in half a page
+        TuiTester::new(app, 40, 15)?
+            .type_string("5j")
+            .expect_visible("Todo 6")?
+            .type_string(" ")
+            .expect_visible("✓ Todo 6")?
+            .type_key_with(KeyCode::Char('d'), KeyModifiers::CONTROL)
+            .expect_visible("Todo 12")?
+            .type_string(" ")
+            .expect_visible("✓ Todo 12")?
+            .type_key_with(KeyCode::Char('u'), KeyModifiers::CONTROL)
+            .type_string("2k")
+            .expect_visible("Todo 4")?
+            .type_string(" ")
+            .expect_visible("✓ Todo 4")?;
+
+        let checked: Vec<&str> = model
+            .items
+            .iter()
+            .filter(|item| item.checked)
+            .map(|item| item.content.as_str())
+            .collect();
+        assert_eq!(checked, vec!["Todo 4", "Todo 6", "Todo 12"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fold_items_and_sections() -> Result<()> {
+        let mut model = Model::default();
+        let inbox_id = model.user.inbox_project_id.clone();
+        let section = Section::new("Section", &inbox_id);
+        let parent_item = Item::new("Parent item", &inbox_id);
+        let child_item = Item::new("Child item", &inbox_id).parent_id(&parent_item.id);
+        let sectioned_item = Item::new("Sectioned item", &inbox_id).section_id(&section.id);
+
+        model.sections.push(section);
+        model.items.push(parent_item);
+        model.items.push(child_item);
+        model.items.push(sectioned_item);
+
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 60, 12)?
+            .expect_visible("Child item")?
+            .type_string("zc")
+            .expect_not_visible("Child item")?
+            .type_string("zo")
+            .expect_visible("Child item")?
+            // close everything, including the section
+            .type_string("zM")
+            .expect_not_visible("Child item")?
+            .expect_not_visible("Sectioned item")?
+            .expect_visible("Section ▸")?
+            // and open it all back up
+            .type_string("zR")
+            .expect_visible("Child item")?
+            .expect_visible("Sectioned item")?
+            .expect_not_visible("▸")?;
+
+        assert!(!model.items[0].collapsed);
+
+        Ok(())
+    }
+
     #[test]
     fn show_items_in_sections() -> Result<()> {
         let mut model = Model::default();
@@ -275,4 +649,104 @@ pub mod tui_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn keep_selection_after_sync() -> Result<()> {
+        let mut model = Model::default();
+        model.add_item_to_inbox("Todo 1", None);
+        model.add_item_to_inbox("Todo 2", None);
+        let new_item = Item::new("Todo 3", &model.user.inbox_project_id);
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 40, 10)?
+            .expect_visible("- Todo 1")?
+            .type_key(KeyCode::Down)
+            .expect_visible("- Todo 2")?
+            .receive_sync(Response {
+                items: Some(vec![new_item]),
+                ..Default::default()
+            })
+            .expect_visible("- Todo 3")?
+            // the second item should still be selected
+            .type_key(KeyCode::Char(' '))
+            .expect_visible("- Todo 1")?
+            .expect_visible("✓ Todo 2")?
+            .expect_visible("- Todo 3")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_next_item_when_selected_item_is_removed() -> Result<()> {
+        let mut model = Model::default();
+        model.add_item_to_inbox("Todo 1", None);
+        model.add_item_to_inbox("Todo 2", None);
+        model.add_item_to_inbox("Todo 3", None);
+        let removed_item = Item {
+            is_deleted: true,
+            ..model.items[1].clone()
+        };
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 40, 10)?
+            .type_key(KeyCode::Down)
+            .expect_visible("- Todo 2")?
+            .receive_sync(Response {
+                items: Some(vec![removed_item]),
+                ..Default::default()
+            })
+            .expect_not_visible("Todo 2")?
+            // the item after the removed one should now be selected
+            .type_key(KeyCode::Char(' '))
+            .expect_visible("- Todo 1")?
+            .expect_visible("✓ Todo 3")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_selection_when_new_todo_gets_its_real_id() -> Result<()> {
+        let mut model = Model::default();
+        model.add_item_to_inbox("Todo 1", None);
+        model.add_item_to_inbox("Todo 2", None);
+        model.add_item_to_inbox("Todo 3", None);
+        let temp_id = model.items[1].id.to_string();
+        let synced_item = Item {
+            id: "REAL_ID".into(),
+            ..model.items[1].clone()
+        };
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 40, 10)?
+            .type_key(KeyCode::Down)
+            .expect_visible("- Todo 2")?
+            .receive_sync(Response {
+                items: Some(vec![synced_item]),
+                temp_id_mapping: HashMap::from([(temp_id, "REAL_ID".to_string())]),
+                ..Default::default()
+            })
+            .expect_visible("- Todo 2")?
+            // the second item should still be selected
+            .type_key(KeyCode::Char(' '))
+            .expect_visible("- Todo 1")?
+            .expect_visible("✓ Todo 2")?
+            .expect_visible("- Todo 3")?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn show_failed_sync_until_one_works() -> Result<()> {
+        let mut model = Model::default();
+        model.add_item_to_inbox("Todo 1", None);
+        let app = App::new(&mut model);
+
+        TuiTester::new(app, 80, 10)?
+            .fail_sync("The server couldn't be reached.")
+            .expect_visible("Sync failed: The server couldn't be reached.")?
+            .receive_sync(Response::default())
+            .expect_not_visible("Sync failed")?;
+
+        Ok(())
+    }
 }